@@ -0,0 +1,12 @@
+-- Per-category breakdown of each expense across its overflow chain
+CREATE TABLE IF NOT EXISTS transaction_allocations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id INTEGER NOT NULL,
+    category_id INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+    FOREIGN KEY(category_id) REFERENCES categories(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_allocations_transaction
+    ON transaction_allocations(transaction_id);
@@ -0,0 +1,5 @@
+-- Categories become user-defined: Savings is a role flag instead of a fixed name
+ALTER TABLE categories ADD COLUMN is_savings INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+
+UPDATE categories SET is_savings = 1 WHERE name = 'Savings';
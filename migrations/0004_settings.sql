@@ -0,0 +1,8 @@
+-- Per-ledger key/value settings
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- Existing ledgers were always kept in rupiah
+INSERT OR IGNORE INTO settings (key, value) VALUES ('currency', 'IDR');
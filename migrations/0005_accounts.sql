@@ -0,0 +1,27 @@
+-- Where money physically sits (cash, bank, e-wallet), independent of categories
+CREATE TABLE IF NOT EXISTS accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL DEFAULT 'Cash'
+);
+
+INSERT INTO accounts (name, kind) VALUES ('Cash', 'Cash');
+
+ALTER TABLE funds ADD COLUMN account_id INTEGER REFERENCES accounts(id);
+ALTER TABLE transactions ADD COLUMN account_id INTEGER REFERENCES accounts(id);
+
+-- Everything recorded so far is attributed to the default account
+UPDATE funds SET account_id = (SELECT id FROM accounts WHERE name = 'Cash');
+UPDATE transactions SET account_id = (SELECT id FROM accounts WHERE name = 'Cash');
+
+-- Moves between accounts; these never touch category balances
+CREATE TABLE IF NOT EXISTS transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    from_account_id INTEGER NOT NULL,
+    to_account_id INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    note TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(from_account_id) REFERENCES accounts(id),
+    FOREIGN KEY(to_account_id) REFERENCES accounts(id)
+);
@@ -0,0 +1,16 @@
+-- Scheduled expenses and fund entries, posted by the scheduler when next_due passes
+CREATE TABLE IF NOT EXISTS recurring_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL DEFAULT 'Expense', -- 'Expense' or 'Income'
+    amount TEXT NOT NULL,
+    category_id INTEGER,
+    account_id INTEGER,
+    description TEXT,
+    cadence TEXT NOT NULL, -- RRULE-like, e.g. FREQ=MONTHLY;INTERVAL=1
+    starts_at TEXT NOT NULL,
+    next_due TEXT NOT NULL,
+    requires_confirmation INTEGER NOT NULL DEFAULT 0,
+    active INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY(category_id) REFERENCES categories(id),
+    FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
@@ -0,0 +1,3 @@
+-- Where a fund entry came from (Salary, Bonus, side gig) and a free-form note
+ALTER TABLE funds ADD COLUMN source TEXT;
+ALTER TABLE funds ADD COLUMN note TEXT;
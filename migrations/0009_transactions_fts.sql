@@ -0,0 +1,24 @@
+-- Full-text index over expense descriptions, kept in sync by triggers
+CREATE VIRTUAL TABLE IF NOT EXISTS transactions_fts USING fts5(
+    description,
+    content='transactions',
+    content_rowid='id'
+);
+
+CREATE TRIGGER IF NOT EXISTS transactions_fts_insert AFTER INSERT ON transactions BEGIN
+    INSERT INTO transactions_fts(rowid, description) VALUES (new.id, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS transactions_fts_delete AFTER DELETE ON transactions BEGIN
+    INSERT INTO transactions_fts(transactions_fts, rowid, description)
+    VALUES ('delete', old.id, old.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS transactions_fts_update AFTER UPDATE OF description ON transactions BEGIN
+    INSERT INTO transactions_fts(transactions_fts, rowid, description)
+    VALUES ('delete', old.id, old.description);
+    INSERT INTO transactions_fts(rowid, description) VALUES (new.id, new.description);
+END;
+
+-- Index everything recorded before this migration
+INSERT INTO transactions_fts(transactions_fts) VALUES ('rebuild');
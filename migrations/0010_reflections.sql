@@ -0,0 +1,11 @@
+-- Kakeibo reflection per budget period, keyed by the fund entry that opened the period
+CREATE TABLE IF NOT EXISTS reflections (
+    fund_id INTEGER PRIMARY KEY,
+    savings_goal TEXT,
+    answer_received TEXT NOT NULL DEFAULT '',
+    answer_save TEXT NOT NULL DEFAULT '',
+    answer_spent TEXT NOT NULL DEFAULT '',
+    answer_improve TEXT NOT NULL DEFAULT '',
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY(fund_id) REFERENCES funds(id)
+);
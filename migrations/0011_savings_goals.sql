@@ -0,0 +1,20 @@
+-- Named targets funded from the Savings category
+CREATE TABLE IF NOT EXISTS savings_goals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    target TEXT NOT NULL,
+    deadline DATETIME,
+    created_at DATETIME NOT NULL
+);
+
+-- Money taken out of Savings for a goal; a goal's progress is the sum of these
+CREATE TABLE IF NOT EXISTS goal_withdrawals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    goal_id INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    note TEXT,
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY(goal_id) REFERENCES savings_goals(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_goal_withdrawals_goal ON goal_withdrawals(goal_id);
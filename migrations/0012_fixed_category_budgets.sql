@@ -0,0 +1,2 @@
+-- A category budgets either a percentage of income or a fixed amount (NULL = percentage)
+ALTER TABLE categories ADD COLUMN fixed_amount TEXT;
@@ -0,0 +1,4 @@
+-- A refund is a transaction with a negative amount that points at the expense it reverses
+ALTER TABLE transactions ADD COLUMN refund_of_id INTEGER REFERENCES transactions(id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_refund_of ON transactions(refund_of_id);
@@ -0,0 +1,4 @@
+-- Lines of one receipt split across categories share a split_id (the first line's id)
+ALTER TABLE transactions ADD COLUMN split_id INTEGER REFERENCES transactions(id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_split ON transactions(split_id);
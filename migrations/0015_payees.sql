@@ -0,0 +1,12 @@
+-- Who an expense was paid to: its description without #tags, matched case-insensitively
+CREATE TABLE IF NOT EXISTS payees (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+ALTER TABLE transactions ADD COLUMN payee_id INTEGER REFERENCES payees(id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_payee ON transactions(payee_id);
+
+-- Earlier expenses get their payees from DB::backfill_payees, which applies the same
+-- matching as new ones (models::match_payee)
@@ -0,0 +1,19 @@
+-- How to read one bank's or e-wallet's CSV statement. Column references are a header name
+-- or a 1-based column number.
+CREATE TABLE IF NOT EXISTS import_profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    delimiter TEXT NOT NULL DEFAULT ',',
+    skip_lines INTEGER NOT NULL DEFAULT 0, -- preamble above the header row
+    date_column TEXT NOT NULL,
+    date_format TEXT NOT NULL, -- chrono format, e.g. %d/%m/%Y
+    amount_column TEXT NOT NULL,
+    second_column TEXT, -- credit column or DB/CR marker, depending on sign_convention
+    description_column TEXT NOT NULL,
+    sign_convention TEXT NOT NULL DEFAULT 'Negative is expense',
+    decimal_separator TEXT NOT NULL DEFAULT '.',
+    account_id INTEGER,
+    category_id INTEGER, -- used when the payee has no earlier expense
+    FOREIGN KEY(account_id) REFERENCES accounts(id),
+    FOREIGN KEY(category_id) REFERENCES categories(id)
+);
@@ -0,0 +1,6 @@
+-- Bank transaction id of imported expenses (OFX FITID, or one derived from a QIF entry), so
+-- importing the same statement again records each expense only once
+ALTER TABLE transactions ADD COLUMN import_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_import_id
+ON transactions(import_id) WHERE import_id IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Archived categories get no allocation: hand their percentage to Savings, so the active
+-- percentages still add up to 100% and no part of an income goes unallocated
+UPDATE categories
+SET limit_percentage = limit_percentage + (
+    SELECT COALESCE(SUM(limit_percentage), 0) FROM categories
+    WHERE archived = 1 AND fixed_amount IS NULL
+)
+WHERE is_savings = 1;
+
+UPDATE categories SET limit_percentage = 0 WHERE archived = 1;
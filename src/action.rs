@@ -24,6 +24,10 @@ pub enum Action {
     // Form actions
     SubmitTransaction,
     SubmitFunds,
+    ToggleSplit,
+    AddSplitLine, // Empty line amount takes whatever is left of the total
+    RemoveSplitLine,
+    SkipDuplicate, // Drop an Add Expense entry flagged as already recorded
     CancelInput,
 
     // Data refresh
@@ -32,6 +36,27 @@ pub enum Action {
 
     // Reporting
     ChangeDateRange(DateRange),
+    StartEditTransaction,
+    SubmitTransactionEdit,
+    RequestDeleteTransaction,
+    StartRefund,
+    SubmitRefund,
+    ToggleReportView,
+    StartSearch,
+    ClearSearch,                  // Drops the description filter
+    ExportCsv(Option<DateRange>), // None exports all time
+
+    // Tags
+    OpenTagPicker,
+    ConfirmTagPick, // Inserts #tag into the description
+    CloseTagPicker,
+
+    // Payees
+    ConfirmPayeePick, // Fills in the payee and its last category
+
+    // Confirmation modal
+    ConfirmYes,
+    ConfirmNo,
 
     // UI toggles
     ToggleHelp,
@@ -48,6 +73,57 @@ pub enum Action {
     StartEditingCategory,
     ConfirmCategoryEdit, // Renamed from SaveCategoryLimit to be clearer about memory update
     SaveSettings,        // Triggers DB persist
+    StartNewCategory,
+    StartRenameCategory,
+    SubmitCategoryName, // Create/rename are persisted immediately
+    ToggleArchiveCategory,
+    ToggleFixedBudget, // Percentage <-> fixed amount, in-memory until SaveSettings
+    StartPickOverflow,
+    ConfirmOverflowPick, // In-memory until SaveSettings, like limits
+    CloseOverflowPicker,
+    CycleCurrency,
+
+    // Savings goals (Dashboard)
+    StartNewGoal,
+    SubmitGoal,
+    StartGoalWithdrawal,
+    SubmitGoalWithdrawal,
+    RequestDeleteGoal,
+
+    // Reflection
+    StartEditReflection,
+    SubmitReflection,
+
+    // Accounts
+    StartNewAccount,
+    SubmitAccountName,
+    CycleAccountKind,
+    StartTransfer,
+    SubmitTransfer,
+
+    // Recurring rules
+    StartNewRule,
+    SubmitRule,
+    ToggleRuleActive,
+    ToggleRuleConfirmation,
+    RequestDeleteRule,
+
+    // Statement import
+    StartNewImportProfile,
+    StartEditImportProfile,
+    SubmitImportProfile,
+    RequestDeleteImportProfile,
+    LoadImport, // Reads the statement into the preview; nothing is recorded yet
+    ToggleImportRow,
+    CycleImportAccount, // Account the previewed rows are paid from
+    RequestCommitImport,
+    DiscardImport,
+
+    // Profiles
+    OpenProfilePicker,
+    ConfirmProfilePick, // Swaps the open ledger
+    StartNewProfile,
+    SubmitProfileName,
 }
 
 /// Direction for navigation
@@ -1,67 +1,127 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
+use chrono::{DateTime, Local};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::widgets::*;
 use rust_decimal::Decimal;
 
 use crate::action::Action;
+use crate::config::{Config, expand_tilde, profile_name};
 use crate::database::DB;
 use crate::error::Result;
-use crate::models::CategoryName;
-use crate::state::{ActiveInput, ActiveTab, InputMode, State};
+use crate::export;
+use crate::import::{self, ImportRow, RowStatus};
+use crate::models::{
+    AccountKind, Allocation, Cadence, Category, ENTRY_DUPLICATE_WINDOW, ImportProfile,
+    KAKEIBO_QUESTIONS, RecurringKind, RecurringRule, Reflection, Transaction, find_duplicate,
+    group_by_payee, group_by_tag, overflow_chain, parse_datetime_input, parse_deadline,
+    validate_overflow_chains,
+};
+use crate::state::{
+    ActiveInput, ActiveTab, DuplicateWarning, InputMode, NamePrompt, PendingConfirm, ReportView,
+    State,
+};
 use crate::tui::{self, Tui};
 
-/// Format a Decimal as IDR currency (e.g., 1000000 -> "1.000.000")
-fn format_idr(amount: Decimal) -> String {
-    let num = amount.trunc().to_string();
-    let is_negative = num.starts_with('-');
-    let digits: String = num.chars().filter(|c| c.is_ascii_digit()).collect();
+/// How many recent transfers the Accounts tab shows
+const TRANSFER_HISTORY: i64 = 50;
 
-    if digits.is_empty() {
-        return "0".to_string();
-    }
+/// Income sources listed in the Reports stats block
+const MAX_SOURCES_SHOWN: usize = 5;
 
-    let mut result = String::new();
-    for (i, c) in digits.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.push('.');
-        }
-        result.push(c);
-    }
+/// Periods compared side by side on the Reflection tab
+const REFLECTIONS_SIDE_BY_SIDE: usize = 4;
 
-    let formatted: String = result.chars().rev().collect();
-    if is_negative {
-        format!("-{}", formatted)
-    } else {
-        formatted
-    }
-}
+/// Dashboard rows given to savings goals before the list scrolls (two per goal)
+const DASHBOARD_GOAL_ROWS: usize = 8;
+
+/// Titles of the import profile text fields, in `State::mapping_inputs` order
+const MAPPING_FIELDS: [&str; 9] = [
+    " Name ",
+    " Delimiter ",
+    " Skip lines ",
+    " Date column ",
+    " Date format ",
+    " Amount / debit column ",
+    " Credit / marker column ",
+    " Description column ",
+    " Decimal separator ",
+];
 
 /// Main application struct
 pub struct App {
     db: DB,
+    config: Config,
     state: State,
     should_quit: bool,
 }
 
 impl App {
-    pub async fn new() -> Result<Self> {
-        let db = DB::new().await?;
-        let mut state = State::new();
+    pub async fn new(config: Config) -> Result<Self> {
+        let db = DB::new(&config.db_path).await?;
+        let mut app = Self {
+            db,
+            config,
+            state: State::new(),
+            should_quit: false,
+        };
+        app.load_ledger().await?;
+        app.run_scheduler().await?;
+        Ok(app)
+    }
+
+    /// Fill `State` from the open ledger
+    async fn load_ledger(&mut self) -> Result<()> {
+        let db = &self.db;
+        let state = &mut self.state;
         state.categories = db.get_categories().await?;
+        state.latest_income = db.get_latest_income().await?;
         state.balances = db.get_category_balances().await?;
+        state.currency = db.get_currency().await?;
+        state.accounts = db.get_accounts().await?;
+        state.transfers = db.get_transfers(TRANSFER_HISTORY).await?;
+        state.recurring_rules = db.get_recurring_rules().await?;
+        state.import_profiles = db.get_import_profiles().await?;
+        state.payees = db.get_payees().await?;
+        state.reflections = db.get_reflections().await?;
+        state.savings_goals = db.get_savings_goals().await?;
+        state.period_length = db.get_typical_period_length().await?;
 
         // Load initial report data
-        state.transactions = db.get_transactions(state.report_date_range).await?;
-        state.summary_stats = Some(db.get_summary_stats().await?);
+        state.transactions = db
+            .get_transactions(state.report_date_range, &state.search_terms())
+            .await?;
+        state.summary_stats = Some(db.get_summary_stats(state.report_date_range).await?);
+        Ok(())
+    }
 
-        Ok(Self {
-            db,
-            state,
-            should_quit: false,
-        })
+    /// Open another ledger file and reload everything from it, staying on the current tab
+    async fn switch_profile(&mut self, path: PathBuf) -> Result<()> {
+        let db = match DB::new(&path).await {
+            Ok(db) => db,
+            Err(e) => {
+                self.state.clear_inputs();
+                self.state
+                    .set_status(format!("Could not open {}: {}", path.display(), e));
+                return Ok(());
+            }
+        };
+
+        self.db = db;
+        self.config.db_path = path;
+        let active_tab = self.state.active_tab;
+        self.state = State::new();
+        self.state.active_tab = active_tab;
+        self.load_ledger().await?;
+        self.state.set_status(format!(
+            "Switched to profile {}",
+            profile_name(&self.config.db_path)
+        ));
+        self.run_scheduler().await?;
+        Ok(())
     }
 
     pub async fn run(&mut self) -> Result<()> {
@@ -103,9 +163,53 @@ impl App {
             self.draw_content(frame, layout[1]);
             self.draw_footer(frame, layout[2]);
 
+            if self.state.editing_transaction.is_some() {
+                self.draw_edit_transaction(frame, area);
+            }
+
+            if self.state.refunding.is_some() {
+                self.draw_refund_form(frame, area);
+            }
+
+            if self.state.transferring {
+                self.draw_transfer(frame, area);
+            }
+
+            if self.state.creating_rule {
+                self.draw_rule_form(frame, area);
+            }
+
+            if self.state.editing_import_profile.is_some() {
+                self.draw_import_profile_form(frame, area);
+            }
+
+            if self.state.editing_reflection {
+                self.draw_reflection_form(frame, area);
+            }
+
+            if self.state.creating_goal {
+                self.draw_goal_form(frame, area);
+            }
+
+            if self.state.funding_goal {
+                self.draw_goal_withdrawal(frame, area);
+            }
+
             if self.state.show_help {
                 self.draw_help_overlay(frame, area);
             }
+
+            if let Some(index) = self.state.tag_picker {
+                self.draw_tag_picker(frame, area, index);
+            }
+
+            if let Some(index) = self.state.profile_picker {
+                self.draw_profile_picker(frame, area, index);
+            }
+
+            if self.state.pending_confirm.is_some() {
+                self.draw_confirm(frame, area);
+            }
         })?;
         Ok(())
     }
@@ -130,7 +234,11 @@ impl App {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(" Ebisu - Kakeibo Tracker "),
+                    .title(format!(
+                        " Ebisu - Kakeibo Tracker [{}] ",
+                        profile_name(&self.config.db_path)
+                    ))
+                    .title(Line::from(" p: profiles ").right_aligned()),
             )
             .select(self.state.active_tab as usize)
             .highlight_style(Style::default().fg(Color::Yellow));
@@ -144,11 +252,16 @@ impl App {
             ActiveTab::AddFunds => self.draw_add_funds(frame, area),
             ActiveTab::AddExpense => self.draw_add_expense(frame, area),
             ActiveTab::Reports => self.draw_reports(frame, area),
+            ActiveTab::Reflection => self.draw_reflection(frame, area),
+            ActiveTab::Accounts => self.draw_accounts(frame, area),
+            ActiveTab::Recurring => self.draw_recurring(frame, area),
+            ActiveTab::Import => self.draw_import(frame, area),
             ActiveTab::Settings => self.draw_settings(frame, area),
         }
     }
 
     fn draw_dashboard(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
         let layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
@@ -158,6 +271,15 @@ impl App {
             .state
             .balances
             .iter()
+            .filter(|b| {
+                // Archived categories only show while they still hold money
+                let archived = self
+                    .state
+                    .categories
+                    .iter()
+                    .any(|c| c.id == b.category_id && c.archived);
+                !archived || b.available != b.spent
+            })
             .map(|b| {
                 let spent_pct = if b.allocated > Decimal::ZERO {
                     (b.spent / b.allocated * Decimal::from(100))
@@ -173,9 +295,9 @@ impl App {
                         Style::default().fg(Color::Cyan),
                     ),
                     Span::raw(format!(
-                        " IDR {:>12} / IDR {:>12} ({:>5}%)",
-                        format_idr(remaining),
-                        format_idr(b.allocated),
+                        " {:>16} / {:>16} ({:>5}%)",
+                        cur.format(remaining),
+                        cur.format(b.allocated),
                         spent_pct
                     )),
                 ]))
@@ -190,7 +312,15 @@ impl App {
             )
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
-        frame.render_widget(balances_list, layout[0]);
+        // Two rows per goal (caption + gauge), capped so balances keep room
+        let goal_rows = (self.state.savings_goals.len().max(1) * 2).min(DASHBOARD_GOAL_ROWS) as u16;
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(goal_rows + 2)])
+            .split(layout[0]);
+
+        frame.render_widget(balances_list, left[0]);
+        self.draw_goals(frame, left[1]);
 
         let total_available: Decimal = self.state.balances.iter().map(|b| b.available).sum();
         let total_spent: Decimal = self.state.balances.iter().map(|b| b.spent).sum();
@@ -200,7 +330,7 @@ impl App {
             .state
             .balances
             .iter()
-            .find(|b| b.category_name == CategoryName::Savings)
+            .find(|b| b.is_savings)
             .map(|b| b.available - b.spent)
             .unwrap_or_default();
 
@@ -208,21 +338,21 @@ impl App {
             Line::from(vec![
                 Span::styled("Total Available: ", Style::default().fg(Color::Gray)),
                 Span::styled(
-                    format!("IDR {}", format_idr(total_available)),
+                    self.state.currency.format(total_available),
                     Style::default().fg(Color::Green),
                 ),
             ]),
             Line::from(vec![
                 Span::styled("Total Spent:     ", Style::default().fg(Color::Gray)),
                 Span::styled(
-                    format!("IDR {}", format_idr(total_spent)),
+                    self.state.currency.format(total_spent),
                     Style::default().fg(Color::Red),
                 ),
             ]),
             Line::from(vec![
                 Span::styled("Remaining:       ", Style::default().fg(Color::Gray)),
                 Span::styled(
-                    format!("IDR {}", format_idr(total_remaining)),
+                    self.state.currency.format(total_remaining),
                     Style::default().fg(Color::Yellow),
                 ),
             ]),
@@ -230,7 +360,7 @@ impl App {
             Line::from(vec![
                 Span::styled("Savings:         ", Style::default().fg(Color::Gray)),
                 Span::styled(
-                    format!("IDR {}", format_idr(savings)),
+                    self.state.currency.format(savings),
                     Style::default()
                         .fg(Color::Magenta)
                         .add_modifier(Modifier::BOLD),
@@ -238,10 +368,256 @@ impl App {
             ]),
         ];
 
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(7), Constraint::Min(0)])
+            .split(layout[1]);
+
         let summary = Paragraph::new(summary_text)
             .block(Block::default().borders(Borders::ALL).title(" Summary "));
 
-        frame.render_widget(summary, layout[1]);
+        frame.render_widget(summary, right[0]);
+
+        let account_items: Vec<ListItem> = self
+            .state
+            .accounts
+            .iter()
+            .map(|a| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<12}", a.name), Style::default().fg(Color::Cyan)),
+                    Span::styled(format!(" {:<8}", a.kind), Style::default().fg(Color::Gray)),
+                    Span::raw(format!(" {:>16}", cur.format(a.balance))),
+                ]))
+            })
+            .collect();
+
+        frame.render_widget(
+            List::new(account_items)
+                .block(Block::default().borders(Borders::ALL).title(" Accounts ")),
+            right[1],
+        );
+    }
+
+    fn draw_goals(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Savings Goals (n: new, w: withdraw, d: delete) ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        if self.state.savings_goals.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No savings goals yet. Press n to add one.")
+                    .style(Style::default().fg(Color::DarkGray)),
+                inner,
+            );
+            return;
+        }
+
+        // Keep the selected goal on screen when there are more goals than rows
+        let visible = (inner.height / 2).max(1) as usize;
+        let first = self.state.selected_goal.saturating_sub(visible - 1);
+        let now = Local::now();
+        for (row, (i, goal)) in self
+            .state
+            .savings_goals
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .enumerate()
+        {
+            let is_selected = i == self.state.selected_goal;
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            let prefix = if is_selected { "> " } else { "  " };
+            let deadline = goal
+                .deadline
+                .map(|d| format!(" by {}", d.format("%Y-%m")))
+                .unwrap_or_default();
+            let outlook = if goal.achieved() {
+                "reached".to_string()
+            } else {
+                match (
+                    goal.required_per_period(now, self.state.period_length),
+                    goal.periods_left(now, self.state.period_length),
+                ) {
+                    (Some(per), Some(left)) => format!(
+                        "{} per period, {} period{} left",
+                        cur.format(per),
+                        left,
+                        if left == 1 { "" } else { "s" }
+                    ),
+                    _ if goal.deadline.is_some() => "overdue".to_string(),
+                    _ => "no deadline".to_string(),
+                }
+            };
+            let caption = Line::from(vec![
+                Span::styled(format!("{}{}{}", prefix, goal.name, deadline), style),
+                Span::styled(format!("  {}", outlook), Style::default().fg(Color::Gray)),
+            ]);
+
+            let y = inner.y + row as u16 * 2;
+            if y + 1 >= inner.y + inner.height {
+                break;
+            }
+            frame.render_widget(
+                Paragraph::new(caption),
+                Rect::new(inner.x, y, inner.width, 1),
+            );
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(if goal.achieved() {
+                    Color::Green
+                } else {
+                    Color::Magenta
+                }))
+                .ratio(goal.progress())
+                .label(format!(
+                    "{} / {} ({:.0}%)",
+                    cur.format(goal.saved),
+                    cur.format(goal.target),
+                    goal.progress() * 100.0
+                ));
+            frame.render_widget(gauge, Rect::new(inner.x, y + 1, inner.width, 1));
+        }
+    }
+
+    fn draw_goal_form(&self, frame: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(60, 60, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" New Savings Goal ");
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(inner);
+
+        let field = |title: &'static str, value: &str, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value.to_string())
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(" Name ", &self.state.name_input, ActiveInput::GoalName),
+            layout[0],
+        );
+        frame.render_widget(
+            field(" Target ", &self.state.amount_input, ActiveInput::Amount),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Deadline (YYYY-MM or YYYY-MM-DD, empty = none) ",
+                &self.state.date_input,
+                ActiveInput::Date,
+            ),
+            layout[2],
+        );
+
+        let instructions = Paragraph::new(
+            "Goals are funded from the Savings balance. i: type | Tab: switch fields | Enter: save | Esc: cancel",
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[3]);
+    }
+
+    fn draw_goal_withdrawal(&self, frame: &mut Frame, area: Rect) {
+        let Some(goal) = self.state.savings_goals.get(self.state.selected_goal) else {
+            return;
+        };
+        let cur = self.state.currency;
+        let savings = self
+            .state
+            .balances
+            .iter()
+            .find(|b| b.is_savings)
+            .map(|b| b.available - b.spent)
+            .unwrap_or_default();
+
+        let popup_area = centered_rect(60, 50, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Withdraw for {} ", goal.name));
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(inner);
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Savings: {}   Still needed: {}",
+                cur.format(savings),
+                cur.format(goal.remaining())
+            ))
+            .style(Style::default().fg(Color::Gray)),
+            layout[0],
+        );
+
+        let field = |title: &'static str, value: &str, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value.to_string())
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(" Amount ", &self.state.amount_input, ActiveInput::Amount),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Note ",
+                &self.state.description_input,
+                ActiveInput::Description,
+            ),
+            layout[2],
+        );
+
+        let instructions = Paragraph::new(
+            "The amount is taken out of Savings. i: type | Tab: switch fields | Enter: withdraw | Esc: cancel",
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[3]);
     }
 
     fn draw_add_funds(&self, frame: &mut Frame, area: Rect) {
@@ -253,26 +629,54 @@ impl App {
             .direction(Direction::Vertical)
             .margin(1)
             .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Min(0),
             ])
             .split(inner);
 
-        let input_style = if self.state.active_input == ActiveInput::Amount {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
+        let field = |title: &'static str, value: &str, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value.to_string())
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
         };
 
-        let amount_input = Paragraph::new(self.state.amount_input.as_str())
-            .style(input_style)
-            .block(Block::default().borders(Borders::ALL).title(" Amount "));
-        frame.render_widget(amount_input, layout[0]);
+        frame.render_widget(
+            field(" Amount ", &self.state.amount_input, ActiveInput::Amount),
+            layout[0],
+        );
+        frame.render_widget(
+            field(
+                " Source (Salary, Bonus, ...) ",
+                &self.state.source_input,
+                ActiveInput::Source,
+            ),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Note ",
+                &self.state.description_input,
+                ActiveInput::Description,
+            ),
+            layout[2],
+        );
+        frame.render_widget(self.account_selector(), layout[3]);
+        frame.render_widget(self.entry_date_field(), layout[4]);
 
-        let instructions = Paragraph::new("Press i to type, Enter to submit, Esc to cancel")
-            .style(Style::default().fg(Color::DarkGray));
-        frame.render_widget(instructions, layout[2]);
+        let instructions = Paragraph::new(
+            "Press i to type, Tab: switch fields | Up/Down: account | Enter to submit, Esc to cancel",
+        )
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[5]);
     }
 
     fn draw_add_expense(&mut self, frame: &mut Frame, area: Rect) {
@@ -289,6 +693,9 @@ impl App {
                 Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(6),
+                Constraint::Length(if self.state.splitting { 3 } else { 0 }),
+                Constraint::Length(3),
+                Constraint::Length(3),
                 Constraint::Min(0),
             ])
             .split(inner);
@@ -298,9 +705,14 @@ impl App {
         } else {
             Style::default()
         };
+        let amount_title = if self.state.splitting {
+            " Total (empty = sum of lines) "
+        } else {
+            " Amount "
+        };
         let amount_input = Paragraph::new(self.state.amount_input.as_str())
             .style(amount_style)
-            .block(Block::default().borders(Borders::ALL).title(" Amount "));
+            .block(Block::default().borders(Borders::ALL).title(amount_title));
         frame.render_widget(amount_input, layout[0]);
 
         let desc_style = if self.state.active_input == ActiveInput::Description {
@@ -317,14 +729,233 @@ impl App {
             );
         frame.render_widget(desc_input, layout[1]);
 
-        let selectable_cats: Vec<&crate::models::Category> = self
+        if self.state.splitting {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .split(layout[2]);
+            frame.render_widget(self.category_picker(), columns[0]);
+            frame.render_widget(self.split_lines_panel(), columns[1]);
+
+            let line_style = if self.state.active_input == ActiveInput::LineAmount {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            frame.render_widget(
+                Paragraph::new(self.state.line_amount_input.as_str())
+                    .style(line_style)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(" Line amount (empty = rest of total) "),
+                    ),
+                layout[3],
+            );
+        } else {
+            frame.render_widget(self.category_picker(), layout[2]);
+        }
+        frame.render_widget(self.account_selector(), layout[4]);
+        frame.render_widget(self.entry_date_field(), layout[5]);
+
+        let warning = self.state.duplicate_warning().map(|entry| {
+            Line::from(format!(
+                "Looks like {}. Enter: record it anyway | Ctrl+X: skip it",
+                self.duplicate_label(entry)
+            ))
+            .style(Style::default().fg(Color::Yellow))
+        });
+        let instructions = if self.state.splitting {
+            "Tab: switch fields | Up/Down: category or account | Ctrl+N: add line | Ctrl+D: drop last line | Ctrl+S: stop splitting | Enter: submit"
+        } else {
+            "Tab: switch fields | Up/Down: category or account | Ctrl+S: split | Enter: submit"
+        };
+        let mut text: Vec<Line> = warning.into_iter().collect();
+        text.push(Line::from(instructions).style(Style::default().fg(Color::DarkGray)));
+        frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), layout[6]);
+
+        self.draw_payee_suggestions(frame, layout[1]);
+    }
+
+    /// Recorded expense a duplicate warning points at, e.g.
+    /// `2026-10-10 12:30 Indomaret, Rp 25.000 (Needs)`
+    fn duplicate_label(&self, entry: &[Transaction]) -> String {
+        let Some(first) = entry.first() else {
+            return String::new();
+        };
+        let categories: Vec<&str> = entry
+            .iter()
+            .filter_map(|t| t.category_name.as_deref())
+            .collect();
+        format!(
+            "{} {}, {} ({})",
+            first.created_at.format("%Y-%m-%d %H:%M"),
+            first.description.as_deref().unwrap_or("no description"),
+            self.state
+                .currency
+                .format(entry.iter().map(|t| t.amount).sum()),
+            categories.join(" + ")
+        )
+    }
+
+    /// Dropdown of matching payees under the Description field, drawn over the fields below
+    fn draw_payee_suggestions(&self, frame: &mut Frame, description_area: Rect) {
+        let suggestions = self.state.payee_suggestions();
+        if suggestions.is_empty() {
+            return;
+        }
+        let items: Vec<ListItem> = suggestions
+            .iter()
+            .map(|p| {
+                let category = p
+                    .last_category_id
+                    .and_then(|id| self.state.categories.iter().find(|c| c.id == id))
+                    .map(|c| c.name.clone())
+                    .unwrap_or_default();
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {:<24}", p.name)),
+                    Span::styled(
+                        format!(" {:<12}", category),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        format!(" {:>3}x ", p.uses),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+
+        let area = Rect {
+            x: description_area.x + 1,
+            y: description_area.bottom(),
+            width: description_area.width.saturating_sub(2).min(48),
+            height: suggestions.len() as u16 + 2,
+        }
+        .intersection(frame.area());
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Payees ")
+                        .title_bottom(" Down: pick  Enter: use "),
+                )
+                .highlight_style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::REVERSED),
+                ),
+            area,
+            &mut ListState::default().with_selected(self.state.payee_pick),
+        );
+    }
+
+    /// Lines of the split being entered, with how much of the total is still unassigned
+    fn split_lines_panel(&self) -> Paragraph<'static> {
+        let cur = self.state.currency;
+        let assigned: Decimal = self.state.split_lines.iter().map(|(_, a)| a).sum();
+        let mut lines: Vec<Line> = self
             .state
-            .categories
+            .split_lines
             .iter()
-            .filter(|c| c.name != CategoryName::Savings)
+            .map(|(cat, amount)| {
+                let name = self
+                    .state
+                    .categories
+                    .iter()
+                    .find(|c| c.id == *cat)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "?".to_string());
+                Line::from(vec![
+                    Span::styled(format!("{:<12}", name), Style::default().fg(Color::Cyan)),
+                    Span::raw(format!(" {:>16}", cur.format(*amount))),
+                ])
+            })
             .collect();
+        let summary = match cur.parse_amount(&self.state.amount_input) {
+            Some(total) => {
+                let left = total - assigned;
+                let color = if left.is_zero() {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                Span::styled(
+                    format!(
+                        "Assigned {} of {}, {} left",
+                        cur.format(assigned),
+                        cur.format(total),
+                        cur.format(left)
+                    ),
+                    Style::default().fg(color),
+                )
+            }
+            None => Span::styled(
+                format!("Assigned {}", cur.format(assigned)),
+                Style::default().fg(Color::Gray),
+            ),
+        };
+        lines.insert(0, Line::from(summary));
+
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Split lines "),
+        )
+    }
+
+    /// Account the Add Funds/Add Expense form books against, e.g. `< BCA (Bank) >`
+    fn account_selector(&self) -> Paragraph<'static> {
+        let style = if self.state.active_input == ActiveInput::Account {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let text = match self.state.form_account() {
+            Some(a) => format!("< {} ({}) >", a.name, a.kind),
+            None => "No accounts".to_string(),
+        };
+
+        Paragraph::new(text)
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(" Account "))
+    }
+
+    /// Optional entry date of the Add Funds/Add Expense form, with the resolved timestamp
+    fn entry_date_field(&self) -> Paragraph<'static> {
+        let style = if self.state.active_input == ActiveInput::Date {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let resolved = match self.state.entry_date() {
+            _ if self.state.date_input.trim().is_empty() => "now".to_string(),
+            Some(at) => at.format("%Y-%m-%d %H:%M").to_string(),
+            None => "invalid date".to_string(),
+        };
+
+        Paragraph::new(Line::from(vec![
+            Span::raw(self.state.date_input.clone()),
+            Span::styled(
+                format!("  → {}", resolved),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]))
+        .style(style)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Date (empty = now; y, -3d, 2026-10-01) "),
+        )
+    }
 
-        let cat_items: Vec<ListItem> = selectable_cats
+    /// Expense category list with the current selection highlighted
+    fn category_picker(&self) -> List<'static> {
+        let cat_items: Vec<ListItem> = self
+            .state
+            .expense_categories()
             .iter()
             .enumerate()
             .map(|(i, c)| {
@@ -339,23 +970,35 @@ impl App {
             })
             .collect();
 
-        let cat_list = List::new(cat_items)
-            .block(Block::default().borders(Borders::ALL).title(" Category "))
-            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
-        frame.render_widget(cat_list, layout[2]);
+        let title_style = if self.state.active_input == ActiveInput::Category {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
 
-        let instructions = Paragraph::new("Tab: switch fields | Up/Down: category | Enter: submit")
-            .style(Style::default().fg(Color::DarkGray));
-        frame.render_widget(instructions, layout[3]);
+        List::new(cat_items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Category ")
+                    .title_style(title_style),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
     }
 
-    fn draw_reports(&self, frame: &mut Frame, area: Rect) {
+    fn draw_reports(&mut self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
         // Layout: Stats on top, Transactions list below
+        let source_rows =
+            self.state
+                .summary_stats
+                .as_ref()
+                .map_or(0, |s| s.income_by_source.len().min(MAX_SOURCES_SHOWN)) as u16;
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(7), // Summary Stats
-                Constraint::Min(0),    // Transactions
+                Constraint::Length(4 + (2 + source_rows).max(3)), // Summary Stats
+                Constraint::Min(0),                               // Transactions
             ])
             .split(area);
 
@@ -366,7 +1009,10 @@ impl App {
             .title(format!(" Period Stats: {} ", date_range_title))
             .title_alignment(Alignment::Center);
 
-        if let Some(stats) = &self.state.summary_stats {
+        let search_terms = self.state.search_terms();
+        if !search_terms.is_empty() {
+            self.draw_search_stats(frame, stats_block, layout[0]);
+        } else if let Some(stats) = &self.state.summary_stats {
             let inner_layout = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -379,14 +1025,14 @@ impl App {
                 Line::from(vec![
                     Span::styled("Total Funds Added: ", Style::default().fg(Color::Gray)),
                     Span::styled(
-                        format!("IDR {}", format_idr(stats.total_funds_added)),
+                        self.state.currency.format(stats.total_funds_added),
                         Style::default().fg(Color::Green),
                     ),
                 ]),
                 Line::from(vec![
                     Span::styled("Total Expenses:    ", Style::default().fg(Color::Gray)),
                     Span::styled(
-                        format!("IDR {}", format_idr(stats.total_spent)),
+                        self.state.currency.format(stats.total_spent),
                         Style::default().fg(Color::Red),
                     ),
                 ]),
@@ -402,43 +1048,95 @@ impl App {
                 Color::Red
             };
 
-            let right_stats = vec![Line::from(vec![
-                Span::styled("Net Flow:          ", Style::default().fg(Color::Gray)),
-                Span::styled(
-                    format!("IDR {}", format_idr(net)),
-                    Style::default().fg(net_color).add_modifier(Modifier::BOLD),
-                ),
-            ])];
-            frame.render_widget(Paragraph::new(right_stats), inner_layout[1]);
-        } else {
-            frame.render_widget(stats_block, layout[0]);
-            frame.render_widget(Paragraph::new("Loading..."), layout[0]);
-        }
-
-        // --- Transactions List ---
-        let list_block = Block::default()
-            .borders(Borders::ALL)
-            .title(" Transaction History ");
-
-        let items: Vec<ListItem> = self
-            .state
-            .transactions
-            .iter()
-            .map(|t| {
+            let mut right_stats = vec![
+                Line::from(vec![
+                    Span::styled("Net Flow:          ", Style::default().fg(Color::Gray)),
+                    Span::styled(
+                        self.state.currency.format(net),
+                        Style::default().fg(net_color).add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+                Line::from(Span::styled(
+                    "Income by Source:",
+                    Style::default().fg(Color::Gray),
+                )),
+            ];
+            for s in stats.income_by_source.iter().take(MAX_SOURCES_SHOWN) {
+                right_stats.push(Line::from(vec![
+                    Span::styled(
+                        format!("  {:<16}", s.source),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        self.state.currency.format(s.total),
+                        Style::default().fg(Color::Green),
+                    ),
+                ]));
+            }
+            frame.render_widget(Paragraph::new(right_stats), inner_layout[1]);
+        } else {
+            frame.render_widget(stats_block, layout[0]);
+            frame.render_widget(Paragraph::new("Loading..."), layout[0]);
+        }
+
+        match self.state.report_view {
+            ReportView::Tags => return self.draw_tag_report(frame, layout[1]),
+            ReportView::Payees => return self.draw_payee_report(frame, layout[1]),
+            ReportView::Transactions => {}
+        }
+
+        // --- Transactions List ---
+        let searching = self.state.active_input == ActiveInput::Search;
+        let list_title = if searching {
+            format!(
+                " Search: {}_ (Enter: keep, Esc: clear) ",
+                self.state.search_input
+            )
+        } else if !self.state.search_input.is_empty() {
+            format!(
+                " Transaction History matching \"{}\" (/: edit, Esc: clear) ",
+                self.state.search_input
+            )
+        } else {
+            " Transaction History (/: search) ".to_string()
+        };
+        let list_block = Block::default().borders(Borders::ALL).title(list_title);
+        let highlight = Style::default()
+            .fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+
+        let items: Vec<ListItem> = self
+            .state
+            .report_entries()
+            .into_iter()
+            .map(|entry| {
+                let t = &entry[0];
+                let is_split = entry.len() > 1;
                 let date_str = t.created_at.format("%Y-%m-%d %H:%M").to_string();
-                let cat_name = t
-                    .category_name
-                    .map(|c| c.to_string())
-                    .unwrap_or("Unknown".to_string());
+                let cat_name = if is_split {
+                    format!("Split ({})", entry.len())
+                } else {
+                    t.category_name
+                        .clone()
+                        .unwrap_or_else(|| "Unknown".to_string())
+                };
+                let amount: Decimal = entry.iter().map(|line| line.amount).sum();
 
                 let desc = t.description.clone().unwrap_or_default();
-                let desc_display = if desc.len() > 20 {
-                    format!("{}...", &desc[0..17])
+                // Refunds give up two columns to their marker
+                let desc_width = if t.is_refund() { 18 } else { 20 };
+                let desc_display = if desc.chars().count() > desc_width {
+                    format!(
+                        "{}...",
+                        desc.chars().take(desc_width - 3).collect::<String>()
+                    )
                 } else {
                     desc
                 };
+                let desc_padding = desc_width.saturating_sub(desc_display.chars().count());
 
-                ListItem::new(Line::from(vec![
+                let mut spans = vec![
                     Span::styled(
                         format!("{:<18}", date_str),
                         Style::default().fg(Color::DarkGray),
@@ -450,81 +1148,423 @@ impl App {
                     ),
                     Span::raw(" | "),
                     Span::styled(
-                        format!("IDR {:>12}", format_idr(t.amount)),
-                        Style::default().fg(Color::White),
+                        format!("{:>16}", cur.format(amount)),
+                        Style::default().fg(if t.is_refund() {
+                            Color::Green
+                        } else {
+                            Color::White
+                        }),
                     ),
                     Span::raw(" | "),
-                    Span::styled(desc_display, Style::default().fg(Color::Gray)),
-                ]))
+                ];
+                if t.is_refund() {
+                    spans.push(Span::styled("↩ ", Style::default().fg(Color::Green)));
+                }
+                spans.extend(highlight_terms(
+                    &desc_display,
+                    &search_terms,
+                    Style::default().fg(Color::Gray),
+                    highlight,
+                ));
+                spans.push(Span::raw(" ".repeat(desc_padding)));
+
+                // A split lists its lines, e.g. "= 30.000 Needs, 20.000 Wants"
+                if is_split {
+                    let lines = entry
+                        .iter()
+                        .rev()
+                        .map(|line| {
+                            format!(
+                                "{} {}",
+                                cur.format(line.amount),
+                                line.category_name.as_deref().unwrap_or("Unknown")
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    spans.push(Span::styled(
+                        format!(" = {}", lines),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+
+                // Show where the money actually came from, e.g. "→ 30.000 Wants, 20.000 Unexpected"
+                // (or, for refunds, which categories got it back)
+                if entry.iter().any(|line| line.overflowed()) {
+                    let breakdown = merge_allocations(
+                        entry.iter().rev().flat_map(|line| line.allocations.clone()),
+                    )
+                    .iter()
+                    .map(|a| {
+                        format!(
+                            "{} {}",
+                            cur.format(a.amount.abs()),
+                            a.category_name.as_deref().unwrap_or("Unknown")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                    let arrow = if t.is_refund() { "←" } else { "→" };
+                    spans.push(Span::styled(
+                        format!(" {} {}", arrow, breakdown),
+                        Style::default().fg(Color::Magenta),
+                    ));
+                }
+
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
-        let list = List::new(items).block(list_block);
-        frame.render_widget(list, layout[1]);
+        let list = List::new(items)
+            .block(list_block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, layout[1], &mut self.state.transaction_list);
     }
 
-    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
-        let mode_str = match self.state.input_mode {
-            InputMode::Normal => "NORMAL",
-            InputMode::Insert => "INSERT",
+    /// Period Stats narrowed to the expenses matching the Reports search
+    fn draw_search_stats(&self, frame: &mut Frame, block: Block, area: Rect) {
+        let cur = self.state.currency;
+        // A split receipt counts once, at its full amount; refunds only lower the total
+        let (refunds, matches): (Vec<Decimal>, Vec<Decimal>) = self
+            .state
+            .report_entries()
+            .iter()
+            .map(|entry| entry.iter().map(|t| t.amount).sum::<Decimal>())
+            .partition(|amount| *amount < Decimal::ZERO);
+        let spent: Decimal = matches.iter().sum();
+        let total = spent + refunds.iter().sum::<Decimal>();
+        let average = if matches.is_empty() {
+            Decimal::ZERO
+        } else {
+            spent / Decimal::from(matches.len())
         };
+        let largest = matches.iter().copied().max().unwrap_or_default();
 
-        let status = self
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .margin(1)
+            .split(block.inner(area));
+        frame.render_widget(block, area);
+
+        let left_stats = vec![
+            Line::from(vec![
+                Span::styled("Matching Expenses: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    matches.len().to_string(),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Total Spent:       ", Style::default().fg(Color::Gray)),
+                Span::styled(cur.format(total), Style::default().fg(Color::Red)),
+            ]),
+        ];
+        frame.render_widget(Paragraph::new(left_stats), inner_layout[0]);
+
+        let right_stats = vec![
+            Line::from(vec![
+                Span::styled("Average:           ", Style::default().fg(Color::Gray)),
+                Span::styled(cur.format(average), Style::default().fg(Color::Cyan)),
+            ]),
+            Line::from(vec![
+                Span::styled("Largest:           ", Style::default().fg(Color::Gray)),
+                Span::styled(cur.format(largest), Style::default().fg(Color::Cyan)),
+            ]),
+        ];
+        frame.render_widget(Paragraph::new(right_stats), inner_layout[1]);
+    }
+
+    /// Reports transactions of the selected range grouped by `#tag`
+    fn draw_tag_report(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let items: Vec<ListItem> = group_by_tag(&self.state.transactions)
+            .into_iter()
+            .map(|g| {
+                let (label, color) = match &g.tag {
+                    Some(tag) => (format!("#{}", tag), Color::Cyan),
+                    None => ("(untagged)".to_string(), Color::DarkGray),
+                };
+                let breakdown = g
+                    .by_category
+                    .iter()
+                    .map(|(name, amount)| format!("{} {}", name, cur.format(*amount)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<16}", label), Style::default().fg(color)),
+                    Span::styled(
+                        format!(" {:>3} expenses ", g.count),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{:>16}", cur.format(g.total)),
+                        Style::default().fg(Color::White),
+                    ),
+                    Span::styled(
+                        format!(" → {}", breakdown),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ]))
+            })
+            .collect();
+
+        frame.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Spending by Tag (g: by payee) "),
+            ),
+            area,
+        );
+    }
+
+    /// Reports transactions of the selected range grouped by payee
+    fn draw_payee_report(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let items: Vec<ListItem> = group_by_payee(&self.state.transactions)
+            .into_iter()
+            .map(|g| {
+                let (label, color) = match &g.payee {
+                    Some(payee) => (payee.clone(), Color::Cyan),
+                    None => ("(no payee)".to_string(), Color::DarkGray),
+                };
+                let label = if label.chars().count() > 20 {
+                    format!("{}...", label.chars().take(17).collect::<String>())
+                } else {
+                    label
+                };
+                let breakdown = g
+                    .by_category
+                    .iter()
+                    .map(|(name, amount)| format!("{} {}", name, cur.format(*amount)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<20}", label), Style::default().fg(color)),
+                    Span::styled(
+                        format!(" {:>3} expenses ", g.count),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{:>16}", cur.format(g.total)),
+                        Style::default().fg(Color::White),
+                    ),
+                    Span::styled(
+                        format!(" → {}", breakdown),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ]))
+            })
+            .collect();
+
+        frame.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Spending by Payee (g: back to transactions) "),
+            ),
+            area,
+        );
+    }
+
+    fn draw_tag_picker(&self, frame: &mut Frame, area: Rect, index: usize) {
+        let items: Vec<ListItem> = self
             .state
-            .status_message
-            .clone()
-            .unwrap_or_else(|| "Ready".to_string());
+            .known_tags
+            .iter()
+            .map(|t| ListItem::new(format!(" #{} ", t)))
+            .collect();
 
-        let footer_text = Line::from(vec![
-            Span::styled(
-                format!(" {} ", mode_str),
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Tags ")
+                    .title_bottom(" Enter: insert  Esc: close "),
+            )
+            .highlight_style(
                 Style::default()
-                    .bg(Color::Blue)
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::REVERSED),
+            );
+
+        let popup_area = centered_rect(30, 50, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(
+            list,
+            popup_area,
+            &mut ListState::default().with_selected(Some(index)),
+        );
+    }
+
+    fn draw_edit_transaction(&self, frame: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(60, 70, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Edit Expense ");
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(4),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let field = |title: &'static str, value: &str, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value.to_string())
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(" Amount ", &self.state.amount_input, ActiveInput::Amount),
+            layout[0],
+        );
+        frame.render_widget(
+            field(
+                " Description ",
+                &self.state.description_input,
+                ActiveInput::Description,
             ),
-            Span::raw(" "),
-            Span::styled(status, Style::default().fg(Color::Gray)),
-            Span::raw(" | "),
-            Span::styled("? for Help", Style::default().fg(Color::DarkGray)),
-        ]);
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Date (YYYY-MM-DD HH:MM) ",
+                &self.state.date_input,
+                ActiveInput::Date,
+            ),
+            layout[2],
+        );
+        frame.render_widget(self.category_picker(), layout[3]);
 
-        let footer = Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL));
-        frame.render_widget(footer, area);
+        let instructions =
+            Paragraph::new("i: type | Tab: switch fields | Enter: save | Esc: cancel")
+                .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[4]);
     }
 
-    fn draw_settings(&self, frame: &mut Frame, area: Rect) {
+    fn draw_refund_form(&self, frame: &mut Frame, area: Rect) {
+        let Some(original) = self
+            .state
+            .refunding
+            .and_then(|id| self.state.transactions.iter().find(|t| t.id == id))
+        else {
+            return;
+        };
+        let popup_area = centered_rect(60, 60, area);
+        frame.render_widget(Clear, popup_area);
+
         let block = Block::default()
             .borders(Borders::ALL)
-            .title(" Settings - Category Allocation ");
-        let inner = block.inner(area);
-        frame.render_widget(block, area);
+            .title(" Record Refund ");
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
 
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
             .split(inner);
 
-        // Category List
+        let split_line = self
+            .state
+            .selected_entry()
+            .filter(|lines| lines.len() > 1)
+            .and_then(|lines| {
+                let index = lines.iter().position(|t| t.id == original.id)?;
+                Some(format!(
+                    " (line {} of {}, ↑/↓: other line)",
+                    index + 1,
+                    lines.len()
+                ))
+            });
+        frame.render_widget(
+            Paragraph::new(format!(
+                "For {} {} on {} ({}){}",
+                self.state.currency.format(original.amount),
+                original.description.as_deref().unwrap_or(""),
+                original.created_at.format("%Y-%m-%d"),
+                original.category_name.as_deref().unwrap_or("Unknown"),
+                split_line.unwrap_or_default()
+            ))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::Gray)),
+            layout[0],
+        );
+
+        let field = |title: &'static str, value: &str, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value.to_string())
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(" Amount ", &self.state.amount_input, ActiveInput::Amount),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Description ",
+                &self.state.description_input,
+                ActiveInput::Description,
+            ),
+            layout[2],
+        );
+        frame.render_widget(self.entry_date_field(), layout[3]);
+
+        let instructions = Paragraph::new(
+            "The categories this expense drained get the money back. i: type | Tab: switch fields | Enter: save | Esc: cancel",
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[4]);
+    }
+
+    fn draw_accounts(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[0]);
+
         let mut items: Vec<ListItem> = self
             .state
-            .categories
+            .accounts
             .iter()
             .enumerate()
-            .map(|(i, cat)| {
-                let is_selected = i == self.state.selected_category;
-                // Check if this category is being edited
-                let is_editing = self.state.input_mode == InputMode::Insert
-                    && self.state.active_input == ActiveInput::CategoryLimit
-                    && is_selected;
-
-                let limit_display = if is_editing {
-                    format!("{}%", self.state.amount_input)
-                } else {
-                    format!("{}%", cat.limit_percentage)
-                };
-
+            .map(|(i, a)| {
+                let is_selected = i == self.state.selected_account;
                 let style = if is_selected {
                     Style::default()
                         .fg(Color::Yellow)
@@ -532,306 +1572,3245 @@ impl App {
                 } else {
                     Style::default()
                 };
-
                 let prefix = if is_selected { "> " } else { "  " };
-                let edit_indicator = if is_editing { " [EDITING]" } else { "" };
-
                 ListItem::new(Line::from(vec![
-                    Span::styled(format!("{}{:<15}", prefix, cat.name), style),
-                    Span::raw(" | "),
-                    Span::styled(format!("{:>6}", limit_display), style),
-                    Span::styled(edit_indicator, Style::default().fg(Color::Green)),
+                    Span::styled(format!("{}{:<15}", prefix, a.name), style),
+                    Span::styled(format!(" {:<8}", a.kind), Style::default().fg(Color::Gray)),
+                    Span::styled(format!(" {:>16}", cur.format(a.balance)), style),
                 ]))
             })
             .collect();
 
-        // Add Save Button
-        let save_selected = self.state.selected_category == self.state.categories.len();
-        let save_style = if save_selected {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
-        let save_prefix = if save_selected { "> " } else { "  " };
-        items.push(ListItem::new(Line::from(vec![Span::styled(
-            format!("{}[ SAVE CHANGES ]", save_prefix),
-            save_style,
-        )])));
-
-        let list =
-            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
-        // We handle selection manual rendering above for more control
+        if self.state.name_prompt == Some(NamePrompt::NewAccount) {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("+ {}_", self.state.name_input),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(" [NEW]", Style::default().fg(Color::Green)),
+            ])));
+        }
 
-        frame.render_widget(list, layout[0]);
+        frame.render_widget(
+            List::new(items).block(Block::default().borders(Borders::ALL).title(" Accounts ")),
+            columns[0],
+        );
 
-        // Total Percentage Check
-        let total_percent: Decimal = self
+        let account_name = |id: i64| {
+            self.state
+                .accounts
+                .iter()
+                .find(|a| a.id == id)
+                .map(|a| a.name.clone())
+                .unwrap_or_else(|| "?".to_string())
+        };
+        let transfer_items: Vec<ListItem> = self
             .state
-            .categories
+            .transfers
             .iter()
-            .map(|c| c.limit_percentage)
-            .sum();
-        let total_color = if total_percent == Decimal::new(100, 0) {
-            Color::Green
-        } else {
-            Color::Red
-        };
+            .map(|t| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<11}", t.created_at.format("%Y-%m-%d")),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!(
+                            "{} → {}",
+                            account_name(t.from_account_id),
+                            account_name(t.to_account_id)
+                        ),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(format!(" {:>14}", cur.format(t.amount))),
+                    Span::styled(
+                        format!(" {}", t.note.as_deref().unwrap_or_default()),
+                        Style::default().fg(Color::Gray),
+                    ),
+                ]))
+            })
+            .collect();
 
-        let info_text = vec![
-            Line::from(vec![
-                Span::raw("Total Allocation: "),
-                Span::styled(
-                    format!("{}%", total_percent),
-                    Style::default().fg(total_color),
+        frame.render_widget(
+            List::new(transfer_items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Recent Transfers "),
+            ),
+            columns[1],
+        );
+
+        let hints = Line::from(vec![
+            Span::styled("n", Style::default().fg(Color::Yellow)),
+            Span::raw(": New account  "),
+            Span::styled("c", Style::default().fg(Color::Yellow)),
+            Span::raw(": Change kind  "),
+            Span::styled("t", Style::default().fg(Color::Yellow)),
+            Span::raw(": Transfer from selected"),
+        ]);
+        frame.render_widget(Paragraph::new(hints), layout[1]);
+    }
+
+    fn draw_transfer(&self, frame: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(50, 50, area);
+        frame.render_widget(Clear, popup_area);
+
+        let from = self
+            .state
+            .accounts
+            .get(self.state.selected_account)
+            .map(|a| a.name.clone())
+            .unwrap_or_default();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Transfer from {} ", from));
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let field = |title: &'static str, value: String, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value)
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        let target = self
+            .state
+            .accounts
+            .get(self.state.transfer_target)
+            .map(|a| format!("< {} ({}) >", a.name, a.kind))
+            .unwrap_or_default();
+
+        frame.render_widget(
+            field(
+                " Amount ",
+                self.state.amount_input.clone(),
+                ActiveInput::Amount,
+            ),
+            layout[0],
+        );
+        frame.render_widget(
+            field(" To ", target, ActiveInput::TransferTarget),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Note ",
+                self.state.description_input.clone(),
+                ActiveInput::Description,
+            ),
+            layout[2],
+        );
+
+        let instructions = Paragraph::new(
+            "i: type | Tab: switch fields | Up/Down: account | Enter: transfer | Esc: cancel",
+        )
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[3]);
+    }
+
+    fn draw_recurring(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        let items: Vec<ListItem> = self
+            .state
+            .recurring_rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let is_selected = i == self.state.selected_rule;
+                let style = if is_selected {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else if !rule.active {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+                let prefix = if is_selected { "> " } else { "  " };
+                let target = match rule.kind {
+                    RecurringKind::Income => "Income".to_string(),
+                    RecurringKind::Expense => rule
+                        .category_id
+                        .and_then(|id| self.state.categories.iter().find(|c| c.id == id))
+                        .map(|c| c.name.clone())
+                        .unwrap_or_else(|| "?".to_string()),
+                };
+                let amount_color = match rule.kind {
+                    RecurringKind::Income => Color::Green,
+                    RecurringKind::Expense => Color::Red,
+                };
+                let mut flags = String::new();
+                if rule.requires_confirmation {
+                    flags.push_str(" [CONFIRM]");
+                }
+                if !rule.active {
+                    flags.push_str(" [PAUSED]");
+                }
+
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{}{:<17}", prefix, rule.next_due.format("%Y-%m-%d %H:%M")),
+                        style,
+                    ),
+                    Span::styled(format!(" {:<12}", target), Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        format!(" {:>16}", cur.format(rule.amount)),
+                        Style::default().fg(amount_color),
+                    ),
+                    Span::styled(format!(" {:<20}", rule.label()), style),
+                    Span::styled(
+                        format!(" {}", rule.cadence.describe()),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Span::styled(flags, Style::default().fg(Color::Magenta)),
+                ]))
+            })
+            .collect();
+
+        frame.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Recurring (next due first) "),
+            ),
+            layout[0],
+        );
+
+        let hints = Line::from(vec![
+            Span::styled("n", Style::default().fg(Color::Yellow)),
+            Span::raw(": New rule  "),
+            Span::styled("a", Style::default().fg(Color::Yellow)),
+            Span::raw(": Pause/Resume  "),
+            Span::styled("c", Style::default().fg(Color::Yellow)),
+            Span::raw(": Ask before posting  "),
+            Span::styled("d", Style::default().fg(Color::Yellow)),
+            Span::raw(": Delete"),
+        ]);
+        frame.render_widget(Paragraph::new(hints), layout[1]);
+    }
+
+    fn draw_rule_form(&self, frame: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(60, 80, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" New Recurring Rule ");
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let is_expense = self.state.rule_kind == RecurringKind::Expense;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(if is_expense { 6 } else { 0 }),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(inner);
+
+        let field = |title: &'static str, value: String, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value)
+                .style(style)
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(
+                " Kind ",
+                format!("< {} >", self.state.rule_kind),
+                ActiveInput::RuleKind,
+            ),
+            layout[0],
+        );
+        frame.render_widget(
+            field(
+                " Amount ",
+                self.state.amount_input.clone(),
+                ActiveInput::Amount,
+            ),
+            layout[1],
+        );
+        frame.render_widget(
+            field(
+                " Description ",
+                self.state.description_input.clone(),
+                ActiveInput::Description,
+            ),
+            layout[2],
+        );
+        if is_expense {
+            frame.render_widget(self.category_picker(), layout[3]);
+        }
+        frame.render_widget(self.account_selector(), layout[4]);
+        frame.render_widget(
+            field(
+                " Cadence (monthly, weekly, FREQ=WEEKLY;INTERVAL=2) ",
+                self.state.cadence_input.clone(),
+                ActiveInput::Cadence,
+            ),
+            layout[5],
+        );
+        frame.render_widget(
+            field(
+                " First due (YYYY-MM-DD HH:MM) ",
+                self.state.date_input.clone(),
+                ActiveInput::Date,
+            ),
+            layout[6],
+        );
+
+        let instructions = Paragraph::new(
+            "i: type | Tab: switch fields | Up/Down: kind, category, account | Enter: save | Esc: cancel",
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, layout[7]);
+    }
+
+    fn draw_import(&mut self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let path_style = if self.state.active_input == ActiveInput::ImportPath {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        frame.render_widget(
+            Paragraph::new(self.state.import_path_input.clone())
+                .style(path_style)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Statement file (CSV, OFX, QIF) "),
                 ),
-            ]),
+            layout[0],
+        );
+
+        let hints = if self.state.import_rows.is_empty() {
             Line::from(vec![
-                Span::styled("Enter/i", Style::default().fg(Color::Yellow)),
+                Span::styled("i", Style::default().fg(Color::Yellow)),
+                Span::raw(": File path  "),
+                Span::styled("Enter", Style::default().fg(Color::Yellow)),
+                Span::raw(": Preview  "),
+                Span::styled("n", Style::default().fg(Color::Yellow)),
+                Span::raw(": New profile  "),
+                Span::styled("e", Style::default().fg(Color::Yellow)),
                 Span::raw(": Edit  "),
+                Span::styled("d", Style::default().fg(Color::Yellow)),
+                Span::raw(": Delete"),
+            ])
+        } else {
+            Line::from(vec![
+                Span::styled("space", Style::default().fg(Color::Yellow)),
+                Span::raw(": Skip/keep  "),
+                Span::styled("h/l", Style::default().fg(Color::Yellow)),
+                Span::raw(": Category  "),
+                Span::styled("a", Style::default().fg(Color::Yellow)),
+                Span::raw(": Account  "),
+                Span::styled("Enter", Style::default().fg(Color::Yellow)),
+                Span::raw(": Import  "),
                 Span::styled("Esc", Style::default().fg(Color::Yellow)),
-                Span::raw(": Cancel  "),
-                Span::styled("Save Button", Style::default().fg(Color::Yellow)),
-                Span::raw(": Save All"),
-            ]),
-        ];
-        frame.render_widget(Paragraph::new(info_text), layout[1]);
+                Span::raw(": Close preview"),
+            ])
+        };
+        frame.render_widget(Paragraph::new(hints), layout[2]);
+
+        if self.state.import_rows.is_empty() {
+            self.draw_import_profiles(frame, layout[1]);
+            return;
+        }
+
+        let category_name = |id: Option<i64>| {
+            id.and_then(|id| self.state.categories.iter().find(|c| c.id == id))
+                .map(|c| c.name.clone())
+                .unwrap_or_default()
+        };
+        let rows: Vec<Row> = self
+            .state
+            .import_rows
+            .iter()
+            .map(|row| {
+                let (status, color) = match &row.status {
+                    RowStatus::Ready if row.duplicate_of.is_some() => {
+                        ("duplicate? import anyway".to_string(), Color::Yellow)
+                    }
+                    RowStatus::Skipped if row.duplicate_of.is_some() => {
+                        ("duplicate? skip".to_string(), Color::Yellow)
+                    }
+                    RowStatus::Ready => ("import".to_string(), Color::Green),
+                    RowStatus::Skipped => ("skip".to_string(), Color::DarkGray),
+                    RowStatus::Income => ("money in: use Add Funds".to_string(), Color::Cyan),
+                    RowStatus::Invalid(reason) => (reason.clone(), Color::Red),
+                    RowStatus::Imported => ("imported".to_string(), Color::Green),
+                    RowStatus::AlreadyImported => ("already imported".to_string(), Color::DarkGray),
+                    RowStatus::Failed(e) => (format!("failed: {}", e), Color::Red),
+                };
+                let is_expense = matches!(
+                    row.status,
+                    RowStatus::Ready
+                        | RowStatus::Skipped
+                        | RowStatus::Imported
+                        | RowStatus::AlreadyImported
+                        | RowStatus::Failed(_)
+                );
+                let text_style = match row.status {
+                    RowStatus::Ready | RowStatus::Failed(_) => Style::default(),
+                    _ => Style::default().fg(Color::DarkGray),
+                };
+                let amount_color = if row.amount < Decimal::ZERO {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                Row::new(vec![
+                    Cell::from(
+                        row.date
+                            .map(|d| d.format("%Y-%m-%d").to_string())
+                            .unwrap_or_else(|| "?".to_string()),
+                    )
+                    .style(text_style),
+                    Cell::from(row.description.clone()).style(text_style),
+                    Cell::from(
+                        Line::from(if matches!(row.status, RowStatus::Invalid(_)) {
+                            String::new()
+                        } else {
+                            cur.format(row.amount.abs())
+                        })
+                        .right_aligned(),
+                    )
+                    .style(Style::default().fg(amount_color)),
+                    Cell::from(if is_expense {
+                        category_name(row.category_id)
+                    } else {
+                        String::new()
+                    })
+                    .style(Style::default().fg(Color::Cyan)),
+                    Cell::from(status).style(Style::default().fg(color)),
+                ])
+            })
+            .collect();
+
+        let ready: Vec<&ImportRow> = self
+            .state
+            .import_rows
+            .iter()
+            .filter(|r| r.status == RowStatus::Ready)
+            .collect();
+        let title = format!(
+            " Preview: {} of {} rows to import, {}, paid from {} ",
+            ready.len(),
+            self.state.import_rows.len(),
+            cur.format(ready.iter().map(|r| r.amount).sum()),
+            self.state.import_account().map_or("?", |a| a.name.as_str())
+        );
+        let match_title = self
+            .state
+            .import_table
+            .selected()
+            .and_then(|i| self.state.import_rows.get(i))
+            .and_then(|row| row.duplicate_of.as_deref())
+            .map(|entry| {
+                Line::from(format!(" Looks like {} ", self.duplicate_label(entry)))
+                    .style(Style::default().fg(Color::Yellow))
+            })
+            .unwrap_or_default();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Fill(1),
+                Constraint::Length(16),
+                Constraint::Length(12),
+                Constraint::Length(28),
+            ],
+        )
+        .header(
+            Row::new(["Date", "Description", "Amount", "Category", "Status"])
+                .style(Style::default().fg(Color::Gray))
+                .bottom_margin(1),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(match_title),
+        )
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, layout[1], &mut self.state.import_table);
     }
 
-    fn draw_help_overlay(&self, frame: &mut Frame, area: Rect) {
-        let help_text = vec![
-            Line::from("Navigation:"),
-            Line::from("  Tab/Shift+Tab  Switch tabs"),
-            Line::from("  Up/Down        Navigate lists"),
-            Line::from(""),
-            Line::from("Input:"),
-            Line::from("  i              Enter insert mode"),
-            Line::from("  Esc            Exit insert mode"),
-            Line::from("  Enter          Submit form"),
-            Line::from(""),
-            Line::from("General:"),
-            Line::from("  ?              Toggle help"),
-            Line::from("  q              Quit application"),
-        ];
+    /// Import profiles with the mapping of the highlighted one
+    fn draw_import_profiles(&self, frame: &mut Frame, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
 
-        let help_block = Paragraph::new(help_text)
-            .block(
+        let items: Vec<ListItem> = self
+            .state
+            .import_profiles
+            .iter()
+            .enumerate()
+            .map(|(i, profile)| {
+                let is_selected = i == self.state.selected_import_profile;
+                let style = if is_selected {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let prefix = if is_selected { "> " } else { "  " };
+                ListItem::new(format!("{}{}", prefix, profile.name)).style(style)
+            })
+            .collect();
+        frame.render_widget(
+            List::new(items).block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(" Help ")
-                    .style(Style::default().bg(Color::DarkGray)),
-            )
-            .alignment(Alignment::Left);
+                    .title(" Import profiles "),
+            ),
+            layout[0],
+        );
 
-        let popup_area = centered_rect(50, 60, area);
-        frame.render_widget(Clear, popup_area);
-        frame.render_widget(help_block, popup_area);
+        let details = match self.state.selected_import_profile() {
+            None => vec![Line::from(
+                "No import profiles yet. OFX and QIF files need none; for CSV, press n to describe how your bank's file is laid out.",
+            )],
+            Some(profile) => {
+                let account = profile
+                    .account_id
+                    .and_then(|id| self.state.accounts.iter().find(|a| a.id == id))
+                    .map(|a| a.name.clone())
+                    .unwrap_or_else(|| "-".to_string());
+                let category = profile
+                    .category_id
+                    .and_then(|id| self.state.categories.iter().find(|c| c.id == id))
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "-".to_string());
+                let row = |label: &'static str, value: String| {
+                    Line::from(vec![
+                        Span::styled(format!("{:<14}", label), Style::default().fg(Color::Gray)),
+                        Span::raw(value),
+                    ])
+                };
+                let mut lines = vec![
+                    row(
+                        "Layout",
+                        format!(
+                            "'{}' separated, {} lines skipped, decimal '{}'",
+                            profile.delimiter, profile.skip_lines, profile.decimal_separator
+                        ),
+                    ),
+                    row(
+                        "Date",
+                        format!("{} ({})", profile.date_column, profile.date_format),
+                    ),
+                    row("Amount", profile.amount_column.clone()),
+                    row("Sign", profile.sign_convention.to_string()),
+                ];
+                if let Some(second) = &profile.second_column {
+                    lines.push(row("Credit/marker", second.clone()));
+                }
+                lines.extend([
+                    row("Description", profile.description_column.clone()),
+                    row("Account", account),
+                    row(
+                        "Category",
+                        format!("{} (unless the payee has one)", category),
+                    ),
+                ]);
+                lines
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(details)
+                .wrap(Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title(" Mapping ")),
+            layout[1],
+        );
     }
 
-    fn handle_events(&mut self) -> Result<Option<Action>> {
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    return Ok(None);
-                }
+    fn draw_import_profile_form(&self, frame: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(70, 80, area);
+        frame.render_widget(Clear, popup_area);
 
-                if key.code == KeyCode::Char('q') && self.state.input_mode == InputMode::Normal {
-                    return Ok(Some(Action::Quit));
-                }
+        let title = if self.state.editing_import_profile == Some(0) {
+            " New Import Profile "
+        } else {
+            " Edit Import Profile "
+        };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
 
-                if key.code == KeyCode::Char('?') && self.state.input_mode == InputMode::Normal {
-                    return Ok(Some(Action::ToggleHelp));
-                }
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Min(1),
+            ])
+            .split(inner);
+        let columns = |area: Rect, count: u32| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints((0..count).map(|_| Constraint::Ratio(1, count)))
+                .split(area)
+        };
+        let field = |i: usize| {
+            let style = if self.state.active_input == ActiveInput::Mapping(i) {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(self.state.mapping_inputs[i].clone())
+                .style(style)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(MAPPING_FIELDS[i]),
+                )
+        };
+
+        let top = columns(rows[0], 3);
+        for (i, area) in top.iter().enumerate() {
+            frame.render_widget(field(i), *area);
+        }
+        let date = columns(rows[1], 2);
+        frame.render_widget(field(3), date[0]);
+        frame.render_widget(field(4), date[1]);
 
-                match self.state.input_mode {
-                    InputMode::Normal => return self.handle_normal_mode(key),
-                    InputMode::Insert => return self.handle_insert_mode(key),
+        let amount = columns(rows[2], 3);
+        let sign_style = if self.state.active_input == ActiveInput::SignConvention {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        frame.render_widget(
+            Paragraph::new(format!("< {} >", self.state.sign_convention))
+                .style(sign_style)
+                .block(Block::default().borders(Borders::ALL).title(" Sign ")),
+            amount[0],
+        );
+        frame.render_widget(field(5), amount[1]);
+        if self.state.sign_convention.uses_second_column() {
+            frame.render_widget(field(6), amount[2]);
+        }
+        let text = columns(rows[3], 2);
+        frame.render_widget(field(7), text[0]);
+        frame.render_widget(field(8), text[1]);
+
+        let targets = columns(rows[4], 2);
+        let account_area = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(targets[0]);
+        frame.render_widget(self.account_selector(), account_area[0]);
+        frame.render_widget(self.category_picker(), targets[1]);
+
+        let instructions = Paragraph::new(
+            "Columns: header name or number (1 = first). Date format: %d/%m/%Y, %Y-%m-%d %H:%M:%S. \
+             Category is used when the payee has no earlier expense. \
+             i: type | Tab: switch fields | Up/Down: sign, account, category | Enter: save | Esc: cancel",
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(instructions, rows[5]);
+    }
+
+    /// Kakeibo reflections: the selected period and the ones before it side by side, with the
+    /// selected period's answers below
+    fn draw_reflection(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        if self.state.reflections.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No budget periods yet. Each fund entry opens one.")
+                    .block(Block::default().borders(Borders::ALL).title(" Reflection ")),
+                area,
+            );
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(9),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        // --- Side by side: selected period first, older ones to the right ---
+        let shown: Vec<&Reflection> = self
+            .state
+            .reflections
+            .iter()
+            .skip(self.state.selected_reflection)
+            .take(REFLECTIONS_SIDE_BY_SIDE)
+            .collect();
+        let label = |text: &'static str| Cell::from(text).style(Style::default().fg(Color::Gray));
+        let row = |name: &'static str, cell: &dyn Fn(&Reflection) -> Cell<'static>| {
+            Row::new(std::iter::once(label(name)).chain(shown.iter().map(|r| cell(r))))
+        };
+
+        let header = Row::new(
+            std::iter::once(Cell::from("")).chain(shown.iter().enumerate().map(|(i, r)| {
+                let style = if i == 0 {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Cyan)
+                };
+                Cell::from(r.period.label()).style(style)
+            })),
+        );
+        let rows = vec![
+            row("Received", &|r| {
+                Cell::from(cur.format(r.received)).style(Style::default().fg(Color::Green))
+            }),
+            row("Savings goal", &|r| {
+                Cell::from(r.savings_goal.map(|g| cur.format(g)).unwrap_or("-".into()))
+            }),
+            row("Spent", &|r| {
+                Cell::from(cur.format(r.spent)).style(Style::default().fg(Color::Red))
+            }),
+            row("Saved", &|r| {
+                let color = if r.saved() >= Decimal::ZERO {
+                    Color::Cyan
+                } else {
+                    Color::Red
+                };
+                Cell::from(cur.format(r.saved())).style(Style::default().fg(color))
+            }),
+            row("Goal", &|r| match r.goal_met() {
+                Some(true) => Cell::from("met").style(Style::default().fg(Color::Green)),
+                Some(false) => Cell::from("missed").style(Style::default().fg(Color::Red)),
+                None => Cell::from("-"),
+            }),
+        ];
+
+        let widths = std::iter::once(Constraint::Length(14))
+            .chain(shown.iter().map(|_| Constraint::Fill(1)))
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(header.bottom_margin(1))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Reflection (newest left) "),
+                ),
+            layout[0],
+        );
+
+        // --- Answers of the selected period ---
+        let selected = shown[0];
+        let figures = [
+            format!("Received {}", cur.format(selected.received)),
+            match selected.savings_goal {
+                Some(goal) => format!("Goal {}", cur.format(goal)),
+                None => "No goal set".to_string(),
+            },
+            format!(
+                "Spent {}, saved {}",
+                cur.format(selected.spent),
+                cur.format(selected.saved())
+            ),
+            String::new(),
+        ];
+        let mut lines = Vec::new();
+        for ((question, figure), answer) in KAKEIBO_QUESTIONS
+            .iter()
+            .zip(&figures)
+            .zip(&selected.answers)
+        {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    question.to_string(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("  {}", figure), Style::default().fg(Color::Cyan)),
+            ]));
+            lines.push(if answer.is_empty() {
+                Line::from(Span::styled(
+                    "  (no answer yet)",
+                    Style::default().fg(Color::DarkGray),
+                ))
+            } else {
+                Line::from(format!("  {}", answer))
+            });
+            lines.push(Line::from(""));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", selected.period.label())),
+            ),
+            layout[1],
+        );
+
+        let hints = Line::from(vec![
+            Span::styled("h/l", Style::default().fg(Color::Yellow)),
+            Span::raw(": Newer/older period  "),
+            Span::styled("Enter/e", Style::default().fg(Color::Yellow)),
+            Span::raw(": Write reflection"),
+        ]);
+        frame.render_widget(Paragraph::new(hints), layout[2]);
+    }
+
+    fn draw_reflection_form(&self, frame: &mut Frame, area: Rect) {
+        let Some(reflection) = self.state.reflections.get(self.state.selected_reflection) else {
+            return;
+        };
+        let cur = self.state.currency;
+        let popup_area = centered_rect(70, 80, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Reflection {} ", reflection.period.label()));
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(4),
+                Constraint::Length(4),
+                Constraint::Length(4),
+                Constraint::Min(4),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let field = |title: String, value: String, input: ActiveInput| {
+            let style = if self.state.active_input == input {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Paragraph::new(value)
+                .style(style)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+
+        frame.render_widget(
+            field(
+                " Savings goal (empty = none) ".to_string(),
+                self.state.amount_input.clone(),
+                ActiveInput::Amount,
+            ),
+            layout[0],
+        );
+        let figures = [
+            format!(" ({})", cur.format(reflection.received)),
+            String::new(),
+            format!(
+                " ({} spent, {} saved)",
+                cur.format(reflection.spent),
+                cur.format(reflection.saved())
+            ),
+            String::new(),
+        ];
+        for (i, (question, figure)) in KAKEIBO_QUESTIONS.iter().zip(&figures).enumerate() {
+            frame.render_widget(
+                field(
+                    format!(" {}{} ", question, figure),
+                    self.state.answer_inputs[i].clone(),
+                    ActiveInput::Answer(i),
+                ),
+                layout[i + 1],
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new("i: type | Tab: switch fields | Enter: save | Esc: cancel")
+                .style(Style::default().fg(Color::DarkGray)),
+            layout[5],
+        );
+    }
+
+    fn draw_confirm(&self, frame: &mut Frame, area: Rect) {
+        let question = match &self.state.pending_confirm {
+            Some(PendingConfirm::RecurringOccurrence(rule)) => {
+                let what = match rule.kind {
+                    RecurringKind::Expense => "expense",
+                    RecurringKind::Income => "income",
+                };
+                vec![
+                    Line::from(format!(
+                        "Recurring {} due {}:",
+                        what,
+                        rule.next_due.format("%Y-%m-%d %H:%M")
+                    )),
+                    Line::from(format!(
+                        "{} {}",
+                        self.state.currency.format(rule.amount),
+                        rule.label()
+                    ))
+                    .style(Style::default().fg(Color::Cyan)),
+                    Line::from("Post it now? (n skips this occurrence)"),
+                ]
+            }
+            Some(PendingConfirm::DeleteGoal(id)) => {
+                let name = self
+                    .state
+                    .savings_goals
+                    .iter()
+                    .find(|g| g.id == *id)
+                    .map(|g| g.name.clone())
+                    .unwrap_or_default();
+                vec![
+                    Line::from("Delete this savings goal? Withdrawn money is not returned."),
+                    Line::from(name).style(Style::default().fg(Color::Cyan)),
+                ]
+            }
+            Some(PendingConfirm::DeleteRecurringRule(id)) => {
+                let label = self
+                    .state
+                    .recurring_rules
+                    .iter()
+                    .find(|r| r.id == *id)
+                    .map(|r| r.label())
+                    .unwrap_or_default();
+                vec![
+                    Line::from("Delete this recurring rule? Posted entries are kept."),
+                    Line::from(label).style(Style::default().fg(Color::Cyan)),
+                ]
+            }
+            Some(PendingConfirm::ChangeCurrency(currency)) => vec![
+                Line::from(format!(
+                    "Change the ledger currency from {} to {}?",
+                    self.state.currency.code, currency.code
+                )),
+                Line::from(
+                    "Every amount is shown in the new currency as is; nothing is converted.",
+                )
+                .style(Style::default().fg(Color::Cyan)),
+            ],
+            Some(PendingConfirm::DeleteImportProfile(id)) => {
+                let name = self
+                    .state
+                    .import_profiles
+                    .iter()
+                    .find(|p| p.id == *id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default();
+                vec![
+                    Line::from("Delete this import profile? Imported expenses are kept."),
+                    Line::from(name).style(Style::default().fg(Color::Cyan)),
+                ]
+            }
+            Some(PendingConfirm::CommitImport {
+                account_id,
+                count,
+                total,
+                closed_period,
+                allocations,
+            }) => {
+                let account = self
+                    .state
+                    .accounts
+                    .iter()
+                    .find(|a| a.id == *account_id)
+                    .map(|a| a.name.clone())
+                    .unwrap_or_default();
+                let mut lines = vec![
+                    Line::from(format!(
+                        "Record {} expenses totalling {} paid from {}.",
+                        count,
+                        self.state.currency.format(*total),
+                        account
+                    )),
+                    Line::from("They will be covered by:"),
+                ];
+                lines.extend(self.allocation_lines(allocations));
+                if *closed_period > 0 {
+                    lines.push(Line::from(format!(
+                        "{} of them are dated before the current period, so Savings pays.",
+                        closed_period
+                    )));
+                }
+                lines.push(Line::from("Import them?"));
+                lines
+            }
+            Some(PendingConfirm::DeleteTransaction(id)) => {
+                let entry = self
+                    .state
+                    .report_entries()
+                    .into_iter()
+                    .find(|entry| entry.iter().any(|t| t.id == *id));
+                let detail = entry
+                    .map(|entry| {
+                        format!(
+                            "{} {}",
+                            self.state
+                                .currency
+                                .format(entry.iter().map(|t| t.amount).sum()),
+                            entry[0].description.clone().unwrap_or_default()
+                        )
+                    })
+                    .unwrap_or_default();
+                let question = match entry {
+                    Some(entry) if entry.len() > 1 => {
+                        format!("Delete this expense and all {} of its lines?", entry.len())
+                    }
+                    _ => "Delete this expense?".to_string(),
+                };
+                vec![
+                    Line::from(question),
+                    Line::from(detail).style(Style::default().fg(Color::Cyan)),
+                ]
+            }
+            Some(PendingConfirm::OverflowExpense {
+                category_name,
+                amount,
+                created_at,
+                closed_period,
+                allocations,
+                ..
+            }) => {
+                let reason = if *closed_period {
+                    format!(
+                        "{} is before the current period; its leftover went to Savings.",
+                        created_at.format("%Y-%m-%d")
+                    )
+                } else {
+                    format!(
+                        "{} exceeds what is left in {}.",
+                        self.state.currency.format(*amount),
+                        category_name
+                    )
+                };
+                let mut lines = vec![Line::from(reason), Line::from("It will be covered by:")];
+                lines.extend(self.allocation_lines(allocations));
+                lines.push(Line::from("Record this expense?"));
+                lines
+            }
+            Some(PendingConfirm::OverflowSplit {
+                lines: split,
+                created_at,
+                closed_period,
+                allocations,
+                ..
+            }) => {
+                let total: Decimal = split.iter().map(|(_, amount)| amount).sum();
+                let reason = if *closed_period {
+                    format!(
+                        "{} is before the current period; its leftover went to Savings.",
+                        created_at.format("%Y-%m-%d")
+                    )
+                } else {
+                    "Some lines exceed what is left in their category.".to_string()
+                };
+                let mut lines = vec![
+                    Line::from(reason),
+                    Line::from(format!(
+                        "The {} split will be covered by:",
+                        self.state.currency.format(total)
+                    )),
+                ];
+                lines.extend(self.allocation_lines(allocations));
+                lines.push(Line::from("Record this expense?"));
+                lines
+            }
+            None => return,
+        };
+
+        let mut text = question;
+        text.push(Line::from(""));
+        text.push(Line::from(vec![
+            Span::styled("y", Style::default().fg(Color::Yellow)),
+            Span::raw(": yes  "),
+            Span::styled("n/Esc", Style::default().fg(Color::Yellow)),
+            Span::raw(": no"),
+        ]));
+
+        let popup_area = centered_rect(60, 40, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(text)
+                .wrap(Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title(" Confirm ")),
+            popup_area,
+        );
+    }
+
+    /// One line per covering category, with what it has left afterwards
+    fn allocation_lines(&self, allocations: &[Allocation]) -> Vec<Line<'static>> {
+        allocations
+            .iter()
+            .map(|a| {
+                let left = self
+                    .state
+                    .balances
+                    .iter()
+                    .find(|b| b.category_id == a.category_id)
+                    .map(|b| b.available - b.spent - a.amount)
+                    .unwrap_or_default();
+                let name = a.category_name.as_deref().unwrap_or("Unknown");
+                Line::from(vec![
+                    Span::styled(format!("  {:<12}", name), Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        format!(" -{:>16}", self.state.currency.format(a.amount)),
+                        Style::default().fg(Color::Red),
+                    ),
+                    Span::styled(
+                        format!("  (left: {})", self.state.currency.format(left)),
+                        Style::default().fg(Color::Gray),
+                    ),
+                ])
+            })
+            .collect()
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let mode_str = match self.state.input_mode {
+            InputMode::Normal => "NORMAL",
+            InputMode::Insert => "INSERT",
+        };
+
+        let status = self
+            .state
+            .status_message
+            .clone()
+            .unwrap_or_else(|| "Ready".to_string());
+
+        let footer_text = Line::from(vec![
+            Span::styled(
+                format!(" {} ", mode_str),
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" "),
+            Span::styled(status, Style::default().fg(Color::Gray)),
+            Span::raw(" | "),
+            Span::styled("? for Help", Style::default().fg(Color::DarkGray)),
+            Span::raw(" | "),
+            Span::styled(
+                format!("DB: {}", self.config.db_path.display()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+
+        let footer = Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(footer, area);
+    }
+
+    fn draw_settings(&self, frame: &mut Frame, area: Rect) {
+        let cur = self.state.currency;
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Settings - Category Allocation ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(inner);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(layout[0]);
+
+        // Category List
+        let mut items: Vec<ListItem> = self
+            .state
+            .categories
+            .iter()
+            .enumerate()
+            .map(|(i, cat)| {
+                let is_selected = i == self.state.selected_category;
+                // Check if this category is being edited
+                let is_editing = self.state.input_mode == InputMode::Insert
+                    && self.state.active_input == ActiveInput::CategoryLimit
+                    && is_selected;
+
+                let is_renaming =
+                    self.state.name_prompt == Some(NamePrompt::RenameCategory(cat.id));
+
+                let limit_display = match (is_editing, cat.fixed_amount) {
+                    (true, Some(_)) => format!("{} {}", cur.symbol, self.state.amount_input),
+                    (true, None) => format!("{}%", self.state.amount_input),
+                    (false, Some(fixed)) => cur.format(fixed),
+                    (false, None) => format!("{}%", cat.limit_percentage),
+                };
+                let name_display = if is_renaming {
+                    format!("{}_", self.state.name_input)
+                } else {
+                    cat.name.clone()
+                };
+
+                let style = if is_selected {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else if cat.archived {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+
+                let prefix = if is_selected { "> " } else { "  " };
+                let edit_indicator = if is_editing || is_renaming {
+                    " [EDITING]"
+                } else {
+                    ""
+                };
+                let role = if cat.is_savings {
+                    " [SAVINGS]"
+                } else if cat.archived {
+                    " [ARCHIVED]"
+                } else {
+                    ""
+                };
+
+                let overflow_display = cat
+                    .overflow_to_id
+                    .map(|id| {
+                        self.state
+                            .categories
+                            .iter()
+                            .find(|c| c.id == id)
+                            .map(|c| c.name.clone())
+                            .unwrap_or_else(|| "?".to_string())
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}{:<15}", prefix, name_display), style),
+                    Span::raw(" | "),
+                    Span::styled(format!("{:>16}", limit_display), style),
+                    Span::raw(" | → "),
+                    Span::styled(format!("{:<12}", overflow_display), style),
+                    Span::styled(role, Style::default().fg(Color::Magenta)),
+                    Span::styled(edit_indicator, Style::default().fg(Color::Green)),
+                ]))
+            })
+            .collect();
+
+        if self.state.name_prompt == Some(NamePrompt::NewCategory) {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("+ {}_", self.state.name_input),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(" [NEW]", Style::default().fg(Color::Green)),
+            ])));
+        }
+
+        // Add Save Button
+        let save_selected = self.state.selected_category == self.state.categories.len()
+            && self.state.name_prompt.is_none();
+        let save_style = if save_selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let save_prefix = if save_selected { "> " } else { "  " };
+        items.push(ListItem::new(Line::from(vec![Span::styled(
+            format!("{}[ SAVE CHANGES ]", save_prefix),
+            save_style,
+        )])));
+
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        // We handle selection manual rendering above for more control
+
+        frame.render_widget(list, columns[0]);
+        self.draw_overflow_tree(frame, columns[1]);
+
+        // Percentages share what is left after fixed amounts, so they still total 100%
+        let active = || self.state.categories.iter().filter(|c| !c.archived);
+        let total_percent: Decimal = active()
+            .filter(|c| c.fixed_amount.is_none())
+            .map(|c| c.limit_percentage)
+            .sum();
+        let total_fixed: Decimal = active().filter_map(|c| c.fixed_amount).sum();
+        let total_color = if total_percent == Decimal::new(100, 0) {
+            Color::Green
+        } else {
+            Color::Red
+        };
+        let fixed_fits = self
+            .state
+            .latest_income
+            .is_none_or(|income| total_fixed <= income);
+        let fixed_text = match self.state.latest_income {
+            Some(income) => format!(
+                "{} of last income {}",
+                cur.format(total_fixed),
+                cur.format(income)
+            ),
+            None => cur.format(total_fixed),
+        };
+
+        let info_text = vec![
+            Line::from(vec![
+                Span::raw("Total Allocation: "),
+                Span::styled(
+                    format!("{}%", total_percent),
+                    Style::default().fg(total_color),
+                ),
+                Span::raw("  Fixed: "),
+                Span::styled(
+                    fixed_text,
+                    Style::default().fg(if fixed_fits { Color::Green } else { Color::Red }),
+                ),
+                Span::raw("  Currency: "),
+                Span::styled(
+                    format!(
+                        "{} ({})",
+                        self.state.currency.code,
+                        self.state.currency.format(Decimal::new(123456789, 2))
+                    ),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(" c", Style::default().fg(Color::Yellow)),
+                Span::raw(": change"),
+            ]),
+            Line::from(vec![
+                Span::styled("Enter/i", Style::default().fg(Color::Yellow)),
+                Span::raw(": Edit  "),
+                Span::styled("Esc", Style::default().fg(Color::Yellow)),
+                Span::raw(": Cancel  "),
+                Span::styled("Save Button", Style::default().fg(Color::Yellow)),
+                Span::raw(": Save All  "),
+                Span::styled("n", Style::default().fg(Color::Yellow)),
+                Span::raw(": New  "),
+                Span::styled("r", Style::default().fg(Color::Yellow)),
+                Span::raw(": Rename  "),
+                Span::styled("a", Style::default().fg(Color::Yellow)),
+                Span::raw(": Archive/Restore  "),
+                Span::styled("f", Style::default().fg(Color::Yellow)),
+                Span::raw(": % / Fixed  "),
+                Span::styled("o", Style::default().fg(Color::Yellow)),
+                Span::raw(": Overflow target"),
+            ]),
+        ];
+        frame.render_widget(Paragraph::new(info_text), layout[1]);
+
+        if let Some(index) = self.state.overflow_picker {
+            self.draw_overflow_picker(frame, area, index);
+        }
+    }
+
+    /// Overflow chains as a tree: each chain end is a root, categories spilling into it
+    /// are its children.
+    fn draw_overflow_tree(&self, frame: &mut Frame, area: Rect) {
+        fn push_children(
+            categories: &[Category],
+            parent_id: i64,
+            depth: usize,
+            lines: &mut Vec<Line<'static>>,
+        ) {
+            // Depth bound guards against cycles in unsaved edits
+            if depth > categories.len() {
+                return;
+            }
+            for child in categories
+                .iter()
+                .filter(|c| c.overflow_to_id == Some(parent_id))
+            {
+                let style = if child.archived {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().fg(Color::Cyan)
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(format!("{}└─ ", "   ".repeat(depth))),
+                    Span::styled(child.name.clone(), style),
+                ]));
+                push_children(categories, child.id, depth + 1, lines);
+            }
+        }
+
+        let categories = &self.state.categories;
+        let mut lines = Vec::new();
+        for root in categories.iter().filter(|c| {
+            c.overflow_to_id
+                .is_none_or(|id| !categories.iter().any(|other| other.id == id))
+        }) {
+            lines.push(Line::from(Span::styled(
+                root.name.clone(),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            push_children(categories, root.id, 0, &mut lines);
+        }
+
+        if let Err(e) = validate_overflow_chains(categories) {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                e.to_string(),
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Overflow Chains "),
+            ),
+            area,
+        );
+    }
+
+    fn draw_overflow_picker(&self, frame: &mut Frame, area: Rect, index: usize) {
+        let name = self
+            .state
+            .categories
+            .get(self.state.selected_category)
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+
+        let items: Vec<ListItem> = self
+            .state
+            .overflow_candidates()
+            .iter()
+            .map(|c| match c {
+                Some(c) => ListItem::new(format!(" {} ", c.name)),
+                None => ListItem::new(" (none - end of chain) "),
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" Overflow target for {} ", name)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::REVERSED),
+            );
+
+        let popup_area = centered_rect(40, 50, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(
+            list,
+            popup_area,
+            &mut ListState::default().with_selected(Some(index)),
+        );
+    }
+
+    fn draw_profile_picker(&self, frame: &mut Frame, area: Rect, index: usize) {
+        let mut items: Vec<ListItem> = self
+            .state
+            .profiles
+            .iter()
+            .map(|path| {
+                let marker = if *path == self.config.db_path {
+                    " (open)"
+                } else {
+                    ""
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {}", profile_name(path))),
+                    Span::styled(marker, Style::default().fg(Color::Green)),
+                ]))
+            })
+            .collect();
+
+        let creating = self.state.name_prompt == Some(NamePrompt::NewProfile);
+        if creating {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("+ {}_", self.state.name_input),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(" [NEW]", Style::default().fg(Color::Green)),
+            ])));
+        }
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Profiles ")
+                    .title_bottom(" Enter: open  n: new  Esc: close "),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::REVERSED),
+            );
+
+        let popup_area = centered_rect(40, 50, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(
+            list,
+            popup_area,
+            &mut ListState::default().with_selected((!creating).then_some(index)),
+        );
+    }
+
+    fn draw_help_overlay(&self, frame: &mut Frame, area: Rect) {
+        let help_text = vec![
+            Line::from("Navigation:"),
+            Line::from("  Tab/Shift+Tab  Switch tabs"),
+            Line::from("  Up/Down        Navigate lists"),
+            Line::from(""),
+            Line::from("Input:"),
+            Line::from("  i              Enter insert mode"),
+            Line::from("  Esc            Exit insert mode"),
+            Line::from("  Enter          Submit form"),
+            Line::from("  Ctrl+T         Pick a #tag (in Description)"),
+            Line::from("  Down, Enter    Use a suggested payee (in Description)"),
+            Line::from(""),
+            Line::from("Add Expense:"),
+            Line::from("  Ctrl+S         Split across categories on/off"),
+            Line::from("  Ctrl+N / D     Add split line / drop the last one"),
+            Line::from("  Ctrl+X         Skip an entry flagged as duplicate"),
+            Line::from(""),
+            Line::from("Reports:"),
+            Line::from("  h/l            Change date range"),
+            Line::from("  Enter/e        Edit selected expense"),
+            Line::from("  d              Delete selected expense"),
+            Line::from("  r              Record a refund for selected expense"),
+            Line::from("  g              Spending by #tag, by payee, or list"),
+            Line::from("  x / X          Export range / everything to CSV"),
+            Line::from("  /              Search descriptions (Esc clears)"),
+            Line::from(""),
+            Line::from("Dashboard:"),
+            Line::from("  n / d          New / delete savings goal"),
+            Line::from("  w              Withdraw from Savings for goal"),
+            Line::from(""),
+            Line::from("Reflection:"),
+            Line::from("  h/l            Newer / older period"),
+            Line::from("  Enter/e        Write reflection"),
+            Line::from(""),
+            Line::from("Accounts:"),
+            Line::from("  n / c          New account / change kind"),
+            Line::from("  t              Transfer from selected account"),
+            Line::from(""),
+            Line::from("Recurring:"),
+            Line::from("  n / d          New / delete rule"),
+            Line::from("  a / c          Pause / ask before posting"),
+            Line::from(""),
+            Line::from("Import:"),
+            Line::from("  n / e / d      New / edit / delete CSV profile"),
+            Line::from("                 (OFX and QIF need no profile)"),
+            Line::from("  i, Enter       Type statement path, preview it"),
+            Line::from("  space, h/l     Skip or keep row, change category"),
+            Line::from("  a              Account the rows are paid from"),
+            Line::from("  Enter / Esc    Import kept rows / close preview"),
+            Line::from(""),
+            Line::from("Settings:"),
+            Line::from("  n / r / a      New / rename / archive category"),
+            Line::from("  f              Percentage or fixed amount"),
+            Line::from("  o              Pick overflow target"),
+            Line::from("  c              Change ledger currency"),
+            Line::from(""),
+            Line::from("General:"),
+            Line::from("  p              Switch or create profile"),
+            Line::from("  ?              Toggle help"),
+            Line::from("  q              Quit application"),
+            Line::from(""),
+            Line::from("Database:"),
+            Line::from(format!("  {}", self.config.db_path.display())),
+        ];
+
+        let help_block = Paragraph::new(help_text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Help ")
+                    .style(Style::default().bg(Color::DarkGray)),
+            )
+            .alignment(Alignment::Left);
+
+        let popup_area = centered_rect(50, 60, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(help_block, popup_area);
+    }
+
+    fn handle_events(&mut self) -> Result<Option<Action>> {
+        if !event::poll(Duration::from_millis(100))? {
+            return Ok(Some(Action::Tick));
+        }
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                return Ok(None);
+            }
+
+            if self.state.pending_confirm.is_some() {
+                return Ok(match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => Some(Action::ConfirmYes),
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        Some(Action::ConfirmNo)
+                    }
+                    _ => None,
+                });
+            }
+
+            if self.state.active_tab == ActiveTab::AddExpense
+                && key.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                match key.code {
+                    KeyCode::Char('s') => return Ok(Some(Action::ToggleSplit)),
+                    KeyCode::Char('n') if self.state.splitting => {
+                        return Ok(Some(Action::AddSplitLine));
+                    }
+                    KeyCode::Char('d') if self.state.splitting => {
+                        return Ok(Some(Action::RemoveSplitLine));
+                    }
+                    KeyCode::Char('x') if self.state.duplicate_warning().is_some() => {
+                        return Ok(Some(Action::SkipDuplicate));
+                    }
+                    _ => {}
+                }
+            }
+
+            if key.code == KeyCode::Char('q') && self.state.input_mode == InputMode::Normal {
+                return Ok(Some(Action::Quit));
+            }
+
+            if key.code == KeyCode::Char('?') && self.state.input_mode == InputMode::Normal {
+                return Ok(Some(Action::ToggleHelp));
+            }
+
+            match self.state.input_mode {
+                InputMode::Normal => return self.handle_normal_mode(key),
+                InputMode::Insert => return self.handle_insert_mode(key),
+            }
+        }
+        Ok(None)
+    }
+
+    fn handle_normal_mode(&mut self, key: event::KeyEvent) -> Result<Option<Action>> {
+        if self.state.profile_picker.is_some() {
+            return Ok(match key.code {
+                KeyCode::Up | KeyCode::Char('k') => Some(Action::Up),
+                KeyCode::Down | KeyCode::Char('j') => Some(Action::Down),
+                KeyCode::Enter => Some(Action::ConfirmProfilePick),
+                KeyCode::Char('n') => Some(Action::StartNewProfile),
+                KeyCode::Esc => Some(Action::CancelInput),
+                _ => None,
+            });
+        }
+
+        if self.state.overflow_picker.is_some() {
+            return Ok(match key.code {
+                KeyCode::Up | KeyCode::Char('k') => Some(Action::Up),
+                KeyCode::Down | KeyCode::Char('j') => Some(Action::Down),
+                KeyCode::Enter => Some(Action::ConfirmOverflowPick),
+                KeyCode::Esc => Some(Action::CloseOverflowPicker),
+                _ => None,
+            });
+        }
+
+        match key.code {
+            KeyCode::Tab => {
+                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                    Ok(Some(Action::PrevTab))
+                } else {
+                    Ok(Some(Action::NextTab))
+                }
+            }
+            KeyCode::Char('i') => Ok(Some(Action::EnterInsert)),
+            KeyCode::Char('p')
+                if self.state.editing_transaction.is_none()
+                    && self.state.refunding.is_none()
+                    && !self.state.transferring
+                    && !self.state.creating_rule
+                    && self.state.editing_import_profile.is_none()
+                    && !self.state.editing_reflection
+                    && !self.state.creating_goal
+                    && !self.state.funding_goal =>
+            {
+                Ok(Some(Action::OpenProfilePicker))
+            }
+            KeyCode::Up | KeyCode::Char('k') => Ok(Some(Action::Up)),
+            KeyCode::Down | KeyCode::Char('j') => Ok(Some(Action::Down)),
+            KeyCode::Enter => match self.state.active_tab {
+                ActiveTab::Dashboard if self.state.creating_goal => Ok(Some(Action::SubmitGoal)),
+                ActiveTab::Dashboard if self.state.funding_goal => {
+                    Ok(Some(Action::SubmitGoalWithdrawal))
+                }
+                ActiveTab::AddFunds => Ok(Some(Action::SubmitFunds)),
+                ActiveTab::AddExpense => Ok(Some(Action::SubmitTransaction)),
+                ActiveTab::Accounts if self.state.transferring => Ok(Some(Action::SubmitTransfer)),
+                ActiveTab::Recurring if self.state.creating_rule => Ok(Some(Action::SubmitRule)),
+                ActiveTab::Import if self.state.editing_import_profile.is_some() => {
+                    Ok(Some(Action::SubmitImportProfile))
+                }
+                ActiveTab::Import if self.previewing_import() => {
+                    Ok(Some(Action::RequestCommitImport))
+                }
+                ActiveTab::Import => Ok(Some(Action::LoadImport)),
+                ActiveTab::Reflection if self.state.editing_reflection => {
+                    Ok(Some(Action::SubmitReflection))
+                }
+                ActiveTab::Reflection => Ok(Some(Action::StartEditReflection)),
+                ActiveTab::Reports => {
+                    if self.state.refunding.is_some() {
+                        Ok(Some(Action::SubmitRefund))
+                    } else if self.state.editing_transaction.is_some() {
+                        Ok(Some(Action::SubmitTransactionEdit))
+                    } else if self.state.report_view == ReportView::Transactions {
+                        Ok(Some(Action::StartEditTransaction))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                ActiveTab::Settings => {
+                    if self.state.selected_category == self.state.categories.len() {
+                        Ok(Some(Action::SaveSettings))
+                    } else {
+                        Ok(Some(Action::StartEditingCategory))
+                    }
+                }
+                _ => Ok(None),
+            },
+            KeyCode::Char('n') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::StartNewCategory))
+            }
+            KeyCode::Char('r') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::StartRenameCategory))
+            }
+            KeyCode::Char('a') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::ToggleArchiveCategory))
+            }
+            KeyCode::Char('f') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::ToggleFixedBudget))
+            }
+            KeyCode::Char('o') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::StartPickOverflow))
+            }
+            KeyCode::Char('c') if self.state.active_tab == ActiveTab::Settings => {
+                Ok(Some(Action::CycleCurrency))
+            }
+            KeyCode::Char('n')
+                if self.state.active_tab == ActiveTab::Accounts && !self.state.transferring =>
+            {
+                Ok(Some(Action::StartNewAccount))
+            }
+            KeyCode::Char('c')
+                if self.state.active_tab == ActiveTab::Accounts && !self.state.transferring =>
+            {
+                Ok(Some(Action::CycleAccountKind))
+            }
+            KeyCode::Char('t')
+                if self.state.active_tab == ActiveTab::Accounts && !self.state.transferring =>
+            {
+                Ok(Some(Action::StartTransfer))
+            }
+            KeyCode::Char('n')
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                Ok(Some(Action::StartNewRule))
+            }
+            KeyCode::Char('a')
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                Ok(Some(Action::ToggleRuleActive))
+            }
+            KeyCode::Char('c')
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                Ok(Some(Action::ToggleRuleConfirmation))
+            }
+            KeyCode::Char('d')
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                Ok(Some(Action::RequestDeleteRule))
+            }
+            KeyCode::Char('n') if self.import_idle() => Ok(Some(Action::StartNewImportProfile)),
+            KeyCode::Char('e') if self.import_idle() => Ok(Some(Action::StartEditImportProfile)),
+            KeyCode::Char('d') if self.import_idle() => {
+                Ok(Some(Action::RequestDeleteImportProfile))
+            }
+            KeyCode::Char(' ') if self.previewing_import() => Ok(Some(Action::ToggleImportRow)),
+            KeyCode::Left | KeyCode::Char('h') if self.previewing_import() => {
+                Ok(Some(Action::Left))
+            }
+            KeyCode::Right | KeyCode::Char('l') if self.previewing_import() => {
+                Ok(Some(Action::Right))
+            }
+            KeyCode::Esc if self.previewing_import() => Ok(Some(Action::DiscardImport)),
+            KeyCode::Char('a') if self.previewing_import() => Ok(Some(Action::CycleImportAccount)),
+            KeyCode::Char('n') if self.dashboard_idle() => Ok(Some(Action::StartNewGoal)),
+            KeyCode::Char('w') if self.dashboard_idle() => Ok(Some(Action::StartGoalWithdrawal)),
+            KeyCode::Char('d') if self.dashboard_idle() => Ok(Some(Action::RequestDeleteGoal)),
+            KeyCode::Char('e')
+                if self.state.active_tab == ActiveTab::Reflection
+                    && !self.state.editing_reflection =>
+            {
+                Ok(Some(Action::StartEditReflection))
+            }
+            KeyCode::Left | KeyCode::Char('h')
+                if self.state.active_tab == ActiveTab::Reflection
+                    && !self.state.editing_reflection =>
+            {
+                Ok(Some(Action::Left))
+            }
+            KeyCode::Right | KeyCode::Char('l')
+                if self.state.active_tab == ActiveTab::Reflection
+                    && !self.state.editing_reflection =>
+            {
+                Ok(Some(Action::Right))
+            }
+            KeyCode::Esc
+                if self.state.editing_transaction.is_some()
+                    || self.state.refunding.is_some()
+                    || self.state.transferring
+                    || self.state.creating_rule
+                    || self.state.editing_import_profile.is_some()
+                    || self.state.editing_reflection
+                    || self.state.creating_goal
+                    || self.state.funding_goal =>
+            {
+                Ok(Some(Action::CancelInput))
+            }
+            KeyCode::Char('e') if self.reports_idle() => Ok(Some(Action::StartEditTransaction)),
+            KeyCode::Char('d') if self.reports_idle() => Ok(Some(Action::RequestDeleteTransaction)),
+            KeyCode::Char('r') if self.reports_idle() => Ok(Some(Action::StartRefund)),
+            KeyCode::Char('g')
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.editing_transaction.is_none()
+                    && self.state.refunding.is_none() =>
+            {
+                Ok(Some(Action::ToggleReportView))
+            }
+            KeyCode::Char('x')
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.editing_transaction.is_none()
+                    && self.state.refunding.is_none() =>
+            {
+                Ok(Some(Action::ExportCsv(Some(self.state.report_date_range))))
+            }
+            KeyCode::Char('X')
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.editing_transaction.is_none()
+                    && self.state.refunding.is_none() =>
+            {
+                Ok(Some(Action::ExportCsv(None)))
+            }
+            KeyCode::Char('/')
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.editing_transaction.is_none()
+                    && self.state.refunding.is_none() =>
+            {
+                Ok(Some(Action::StartSearch))
+            }
+            KeyCode::Esc
+                if self.state.active_tab == ActiveTab::Reports
+                    && !self.state.search_input.is_empty() =>
+            {
+                Ok(Some(Action::ClearSearch))
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                if self.state.active_tab == ActiveTab::Reports {
+                    Ok(Some(Action::ChangeDateRange(
+                        self.state.report_date_range.next(),
+                    ))) // Cycling for now
+                } else {
+                    Ok(None)
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if self.state.active_tab == ActiveTab::Reports {
+                    Ok(Some(Action::ChangeDateRange(
+                        self.state.report_date_range.next(),
+                    )))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn handle_insert_mode(&mut self, key: event::KeyEvent) -> Result<Option<Action>> {
+        if self.state.tag_picker.is_some() {
+            return Ok(match key.code {
+                KeyCode::Up => Some(Action::Up),
+                KeyCode::Down => Some(Action::Down),
+                KeyCode::Enter => Some(Action::ConfirmTagPick),
+                KeyCode::Esc => Some(Action::CloseTagPicker),
+                _ => None,
+            });
+        }
+
+        if key.code == KeyCode::Char('t')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && self.state.active_input == ActiveInput::Description
+        {
+            return Ok(Some(Action::OpenTagPicker));
+        }
+
+        if !self.state.payee_suggestions().is_empty() {
+            match key.code {
+                KeyCode::Up => return Ok(Some(Action::Up)),
+                KeyCode::Down => return Ok(Some(Action::Down)),
+                KeyCode::Enter if self.state.payee_pick.is_some() => {
+                    return Ok(Some(Action::ConfirmPayeePick));
+                }
+                _ => {}
+            }
+        }
+
+        match key.code {
+            KeyCode::Esc if self.state.active_input == ActiveInput::Search => {
+                Ok(Some(Action::ClearSearch))
+            }
+            KeyCode::Esc => {
+                if self.state.input_mode == InputMode::Insert
+                    && matches!(
+                        self.state.active_input,
+                        ActiveInput::CategoryLimit
+                            | ActiveInput::CategoryName
+                            | ActiveInput::AccountName
+                            | ActiveInput::ProfileName
+                    )
+                {
+                    Ok(Some(Action::CancelInput))
+                } else {
+                    Ok(Some(Action::EnterNormal))
+                }
+            }
+            KeyCode::Enter if self.state.name_prompt == Some(NamePrompt::NewProfile) => {
+                Ok(Some(Action::SubmitProfileName))
+            }
+            KeyCode::Enter => match self.state.active_tab {
+                ActiveTab::Dashboard if self.state.creating_goal => Ok(Some(Action::SubmitGoal)),
+                ActiveTab::Dashboard if self.state.funding_goal => {
+                    Ok(Some(Action::SubmitGoalWithdrawal))
+                }
+                ActiveTab::AddFunds => Ok(Some(Action::SubmitFunds)),
+                ActiveTab::AddExpense => Ok(Some(Action::SubmitTransaction)),
+                ActiveTab::Reports if self.state.refunding.is_some() => {
+                    Ok(Some(Action::SubmitRefund))
+                }
+                ActiveTab::Reports if self.state.editing_transaction.is_some() => {
+                    Ok(Some(Action::SubmitTransactionEdit))
+                }
+                ActiveTab::Settings if self.state.name_prompt.is_some() => {
+                    Ok(Some(Action::SubmitCategoryName))
+                }
+                ActiveTab::Settings => Ok(Some(Action::ConfirmCategoryEdit)),
+                ActiveTab::Accounts if self.state.name_prompt.is_some() => {
+                    Ok(Some(Action::SubmitAccountName))
+                }
+                ActiveTab::Accounts if self.state.transferring => Ok(Some(Action::SubmitTransfer)),
+                ActiveTab::Recurring if self.state.creating_rule => Ok(Some(Action::SubmitRule)),
+                ActiveTab::Import if self.state.editing_import_profile.is_some() => {
+                    Ok(Some(Action::SubmitImportProfile))
+                }
+                ActiveTab::Import => Ok(Some(Action::LoadImport)),
+                ActiveTab::Reflection if self.state.editing_reflection => {
+                    Ok(Some(Action::SubmitReflection))
+                }
+                _ => Ok(Some(Action::EnterNormal)),
+            },
+            KeyCode::Tab => {
+                // Name and limit prompts are single-field, so they have no form fields
+                let fields = self.state.form_fields();
+                if !fields.is_empty() {
+                    let next = fields
+                        .iter()
+                        .position(|f| *f == self.state.active_input)
+                        .map_or(0, |i| (i + 1) % fields.len());
+                    self.state.active_input = fields[next];
+                }
+                Ok(None)
+            }
+            KeyCode::Char(c) => Ok(Some(Action::InputChar(c))),
+            KeyCode::Backspace => Ok(Some(Action::InputBackspace)),
+            KeyCode::Delete => Ok(Some(Action::InputDelete)),
+            KeyCode::Up => Ok(Some(Action::Up)),
+            KeyCode::Down => Ok(Some(Action::Down)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn update(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
+            }
+            Action::NextTab => {
+                self.state.active_tab = self.state.active_tab.next();
+                self.state.clear_inputs();
+                if self.state.active_tab == ActiveTab::Reports {
+                    // Auto-refresh reports on tab switch
+                    self.refresh_reports().await?;
+                }
+                if self.state.active_tab == ActiveTab::Reflection {
+                    self.refresh_reflections().await?;
+                }
+                if self.state.active_tab == ActiveTab::Settings {
+                    self.state.latest_income = self.db.get_latest_income().await?;
+                }
+                if self.state.active_tab == ActiveTab::AddExpense {
+                    self.state.payees = self.db.get_payees().await?;
+                }
+            }
+            Action::PrevTab => {
+                self.state.active_tab = self.state.active_tab.prev();
+                self.state.clear_inputs();
+                if self.state.active_tab == ActiveTab::Reports {
+                    self.refresh_reports().await?;
+                }
+                if self.state.active_tab == ActiveTab::Reflection {
+                    self.refresh_reflections().await?;
+                }
+                if self.state.active_tab == ActiveTab::Settings {
+                    self.state.latest_income = self.db.get_latest_income().await?;
+                }
+                if self.state.active_tab == ActiveTab::AddExpense {
+                    self.state.payees = self.db.get_payees().await?;
+                }
+            }
+            Action::EnterInsert => {
+                self.state.input_mode = InputMode::Insert;
+                if self.state.active_input == ActiveInput::None {
+                    self.state.active_input = self
+                        .state
+                        .form_fields()
+                        .first()
+                        .copied()
+                        .unwrap_or(ActiveInput::Amount);
+                }
+            }
+            Action::EnterNormal => {
+                self.state.input_mode = InputMode::Normal;
+            }
+            Action::ToggleHelp => {
+                self.state.show_help = !self.state.show_help;
+            }
+            Action::StartEditingCategory => {
+                if let Some(cat) = self.state.categories.get(self.state.selected_category) {
+                    self.state.input_mode = InputMode::Insert;
+                    self.state.active_input = ActiveInput::CategoryLimit;
+                    self.state.amount_input = match cat.fixed_amount {
+                        Some(fixed) => self.state.currency.format_input(fixed),
+                        None => cat.limit_percentage.to_string(),
+                    };
+                }
+            }
+            Action::ConfirmCategoryEdit => {
+                let currency = self.state.currency;
+                let input = self.state.amount_input.clone();
+                if let Some(cat) = self.state.categories.get_mut(self.state.selected_category) {
+                    if cat.fixed_amount.is_some() {
+                        match currency.parse_amount(&input) {
+                            Some(fixed) => cat.fixed_amount = Some(fixed),
+                            None => {
+                                self.state.set_status("Invalid amount format");
+                                return Ok(());
+                            }
+                        }
+                    } else if let Ok(limit) = Decimal::from_str(&input) {
+                        cat.limit_percentage = limit;
+                    } else {
+                        self.state.set_status("Invalid number format");
+                        return Ok(());
+                    }
+                    self.state.clear_inputs();
+                    // Note: We do NOT define self.state.categories from DB here logic
+                    // We keep it in memory until SaveSettings
+                }
+            }
+            Action::ToggleFixedBudget => {
+                if let Some(cat) = self.state.categories.get_mut(self.state.selected_category) {
+                    cat.fixed_amount = match cat.fixed_amount {
+                        Some(_) => None,
+                        None => Some(Decimal::ZERO),
+                    };
+                    let status = if cat.fixed_amount.is_some() {
+                        format!("{} now budgets a fixed amount (Enter to set it)", cat.name)
+                    } else {
+                        format!("{} now budgets a percentage (save to persist)", cat.name)
+                    };
+                    self.state.set_status(status);
+                }
+            }
+            Action::SaveSettings => {
+                // Limits and overflow targets are written in one transaction, and only if
+                // every overflow chain is valid and the fixed amounts fit the latest income
+                match self.db.save_category_settings(&self.state.categories).await {
+                    Ok(_) => {
+                        self.state.set_status("All settings saved successfully.");
+                        self.state.categories = self.db.get_categories().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Not saved: {}", e));
+                    }
+                }
+            }
+            Action::CycleCurrency => {
+                self.state.pending_confirm =
+                    Some(PendingConfirm::ChangeCurrency(self.state.currency.next()));
+            }
+            Action::StartPickOverflow => {
+                if let Some(cat) = self.state.categories.get(self.state.selected_category) {
+                    let current = cat.overflow_to_id;
+                    let index = self
+                        .state
+                        .overflow_candidates()
+                        .iter()
+                        .position(|c| c.map(|c| c.id) == current)
+                        .unwrap_or(0);
+                    self.state.overflow_picker = Some(index);
+                }
+            }
+            Action::ConfirmOverflowPick => {
+                let Some(index) = self.state.overflow_picker.take() else {
+                    return Ok(());
+                };
+                let target = self
+                    .state
+                    .overflow_candidates()
+                    .get(index)
+                    .and_then(|c| c.map(|c| c.id));
+                let selected = self.state.selected_category;
+
+                let mut categories = self.state.categories.clone();
+                let Some(cat) = categories.get_mut(selected) else {
+                    return Ok(());
+                };
+                cat.overflow_to_id = target;
+                let cat_id = cat.id;
+
+                // Refuse the pick right away instead of at save time
+                match overflow_chain(&categories, cat_id) {
+                    Ok(_) => {
+                        self.state.categories = categories;
+                        self.state
+                            .set_status("Overflow target changed (save to persist)");
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::StartNewCategory => {
+                self.state.clear_inputs();
+                self.state.selected_category = self.state.categories.len();
+                self.state.name_prompt = Some(NamePrompt::NewCategory);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::CategoryName;
+            }
+            Action::StartRenameCategory => {
+                if let Some(cat) = self.state.categories.get(self.state.selected_category) {
+                    self.state.name_prompt = Some(NamePrompt::RenameCategory(cat.id));
+                    self.state.name_input = cat.name.clone();
+                    self.state.input_mode = InputMode::Insert;
+                    self.state.active_input = ActiveInput::CategoryName;
+                }
+            }
+            Action::SubmitCategoryName => {
+                let name = self.state.name_input.clone();
+                let result = match self.state.name_prompt {
+                    Some(NamePrompt::NewCategory) => {
+                        self.db.create_category(&name).await.map(|_| {
+                            format!(
+                                "Created category {} (0%, set its allocation and save)",
+                                name.trim()
+                            )
+                        })
+                    }
+                    Some(NamePrompt::RenameCategory(id)) => self
+                        .db
+                        .rename_category(id, &name)
+                        .await
+                        .map(|_| format!("Renamed category to {}", name.trim())),
+                    Some(NamePrompt::NewAccount) | Some(NamePrompt::NewProfile) | None => {
+                        return Ok(());
+                    }
+                };
+
+                match result {
+                    Ok(msg) => {
+                        let selected = self.state.selected_category;
+                        self.state.clear_inputs();
+                        self.state.selected_category = selected;
+                        self.reload_categories().await?;
+                        self.state.set_status(msg);
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::ToggleArchiveCategory => {
+                if let Some(cat) = self.state.categories.get(self.state.selected_category) {
+                    let (id, archived, name) = (cat.id, !cat.archived, cat.name.clone());
+                    let percentage = cat.limit_percentage.normalize().to_string();
+                    let handed_over = cat.fixed_amount.is_none() && !cat.limit_percentage.is_zero();
+                    match self.db.set_category_archived(id, archived).await {
+                        Ok(_) => {
+                            self.reload_categories().await?;
+                            self.state.set_status(if archived && handed_over {
+                                format!(
+                                    "Archived {}; its {}% now goes to Savings",
+                                    name, percentage
+                                )
+                            } else if archived {
+                                format!("Archived {}", name)
+                            } else {
+                                format!("Restored {}", name)
+                            });
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::StartNewAccount => {
+                self.state.clear_inputs();
+                self.state.name_prompt = Some(NamePrompt::NewAccount);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::AccountName;
+            }
+            Action::SubmitAccountName => {
+                let name = self.state.name_input.clone();
+                match self.db.create_account(&name, AccountKind::default()).await {
+                    Ok(_) => {
+                        self.state.clear_inputs();
+                        self.state.accounts = self.db.get_accounts().await?;
+                        self.state.selected_account = self.state.accounts.len().saturating_sub(1);
+                        self.state.set_status(format!(
+                            "Created account {} (c to change its kind)",
+                            name.trim()
+                        ));
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::CycleAccountKind => {
+                if let Some(account) = self.state.accounts.get(self.state.selected_account) {
+                    let kind = account.kind.next();
+                    match self.db.set_account_kind(account.id, kind).await {
+                        Ok(_) => {
+                            self.state.accounts = self.db.get_accounts().await?;
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::StartTransfer => {
+                if self.state.accounts.len() < 2 {
+                    self.state
+                        .set_status("Transfers need at least two accounts");
+                    return Ok(());
+                }
+                self.state.clear_inputs();
+                self.state.transferring = true;
+                // Preselect the first account that isn't the source
+                self.state.transfer_target = usize::from(self.state.selected_account == 0);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Amount;
+            }
+            Action::SubmitTransfer => {
+                let (Some(from), Some(to)) = (
+                    self.state
+                        .accounts
+                        .get(self.state.selected_account)
+                        .cloned(),
+                    self.state.accounts.get(self.state.transfer_target).cloned(),
+                ) else {
+                    return Ok(());
+                };
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                let note = if self.state.description_input.is_empty() {
+                    None
+                } else {
+                    Some(self.state.description_input.clone())
+                };
+
+                match self.db.create_transfer(from.id, to.id, amount, note).await {
+                    Ok(_) => {
+                        self.state.set_status(format!(
+                            "Moved {} from {} to {}",
+                            self.state.currency.format(amount),
+                            from.name,
+                            to.name
+                        ));
+                        self.state.clear_inputs();
+                        self.state.accounts = self.db.get_accounts().await?;
+                        self.state.transfers = self.db.get_transfers(TRANSFER_HISTORY).await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::StartNewGoal => {
+                self.state.clear_inputs();
+                self.state.creating_goal = true;
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::GoalName;
+            }
+            Action::SubmitGoal => {
+                let Some(target) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid target amount");
+                    return Ok(());
+                };
+                let deadline = if self.state.date_input.trim().is_empty() {
+                    None
+                } else {
+                    match parse_deadline(&self.state.date_input) {
+                        Some(deadline) if deadline > Local::now() => Some(deadline),
+                        Some(_) => {
+                            self.state.set_status("Deadline must be in the future");
+                            return Ok(());
+                        }
+                        None => {
+                            self.state
+                                .set_status("Invalid deadline, use YYYY-MM or YYYY-MM-DD");
+                            return Ok(());
+                        }
+                    }
+                };
+
+                let name = self.state.name_input.trim().to_string();
+                match self.db.create_savings_goal(&name, target, deadline).await {
+                    Ok(_) => {
+                        self.state.set_status(format!(
+                            "Goal {} set for {}",
+                            name,
+                            self.state.currency.format(target)
+                        ));
+                        self.state.clear_inputs();
+                        self.refresh_goals().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::StartGoalWithdrawal => {
+                if self.state.savings_goals.is_empty() {
+                    self.state
+                        .set_status("No savings goals yet, press n to add one");
+                    return Ok(());
+                }
+                self.state.clear_inputs();
+                self.state.funding_goal = true;
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Amount;
+            }
+            Action::SubmitGoalWithdrawal => {
+                let Some(goal) = self
+                    .state
+                    .savings_goals
+                    .get(self.state.selected_goal)
+                    .cloned()
+                else {
+                    return Ok(());
+                };
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                let note = Some(self.state.description_input.clone()).filter(|n| !n.is_empty());
+
+                match self.db.withdraw_for_goal(goal.id, amount, note).await {
+                    Ok(_) => {
+                        self.state.set_status(format!(
+                            "Withdrew {} from Savings for {}",
+                            self.state.currency.format(amount),
+                            goal.name
+                        ));
+                        self.state.clear_inputs();
+                        self.state.balances = self.db.get_category_balances().await?;
+                        self.refresh_goals().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::RequestDeleteGoal => {
+                if let Some(goal) = self.state.savings_goals.get(self.state.selected_goal) {
+                    self.state.pending_confirm = Some(PendingConfirm::DeleteGoal(goal.id));
+                }
+            }
+            Action::Left if self.state.active_tab == ActiveTab::Reflection => {
+                self.state.selected_reflection = self.state.selected_reflection.saturating_sub(1);
+            }
+            Action::Right if self.state.active_tab == ActiveTab::Reflection => {
+                let last = self.state.reflections.len().saturating_sub(1);
+                self.state.selected_reflection = (self.state.selected_reflection + 1).min(last);
+            }
+            Action::StartEditReflection => {
+                let Some(reflection) = self
+                    .state
+                    .reflections
+                    .get(self.state.selected_reflection)
+                    .cloned()
+                else {
+                    self.state
+                        .set_status("Add funds first; each fund entry opens a period");
+                    return Ok(());
+                };
+                self.state.clear_inputs();
+                self.state.editing_reflection = true;
+                self.state.amount_input = reflection
+                    .savings_goal
+                    .map(|goal| self.state.currency.format_input(goal))
+                    .unwrap_or_default();
+                self.state.answer_inputs = reflection.answers;
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Amount;
+            }
+            Action::SubmitReflection => {
+                let Some(period) = self
+                    .state
+                    .reflections
+                    .get(self.state.selected_reflection)
+                    .map(|r| r.period.clone())
+                else {
+                    return Ok(());
+                };
+                let savings_goal = if self.state.amount_input.trim().is_empty() {
+                    None
+                } else {
+                    match self.state.currency.parse_amount(&self.state.amount_input) {
+                        Some(goal) => Some(goal),
+                        None => {
+                            self.state.set_status("Invalid savings goal");
+                            return Ok(());
+                        }
+                    }
+                };
+
+                match self
+                    .db
+                    .save_reflection(period.fund_id, savings_goal, &self.state.answer_inputs)
+                    .await
+                {
+                    Ok(_) => {
+                        self.state
+                            .set_status(format!("Saved reflection for {}", period.label()));
+                        self.state.clear_inputs();
+                        self.refresh_reflections().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            // Cheap in-memory check first; the scheduler only touches the DB when a rule is due
+            Action::Tick
+                if self.state.pending_confirm.is_none()
+                    && self.state.due_rule(Local::now()).is_some() =>
+            {
+                self.run_scheduler().await?;
+            }
+            Action::StartNewRule => {
+                self.state.clear_inputs();
+                self.state.creating_rule = true;
+                self.state.cadence_input = "monthly".to_string();
+                self.state.date_input = Local::now().format("%Y-%m-%d %H:%M").to_string();
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Amount;
+            }
+            Action::SubmitRule => {
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                let cadence = match Cadence::from_str(&self.state.cadence_input) {
+                    Ok(cadence) => cadence,
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                        return Ok(());
+                    }
+                };
+                let Some(starts_at) = parse_datetime_input(&self.state.date_input) else {
+                    self.state.set_status("Invalid date, use YYYY-MM-DD HH:MM");
+                    return Ok(());
+                };
+                let category_id = match self.state.rule_kind {
+                    RecurringKind::Expense => self
+                        .state
+                        .expense_categories()
+                        .get(self.state.selected_category)
+                        .map(|c| c.id),
+                    RecurringKind::Income => None,
+                };
+                let description = if self.state.description_input.is_empty() {
+                    None
+                } else {
+                    Some(self.state.description_input.clone())
+                };
+
+                let rule = RecurringRule {
+                    id: 0,
+                    kind: self.state.rule_kind,
+                    amount,
+                    category_id,
+                    account_id: self.state.form_account().map(|a| a.id),
+                    description,
+                    cadence,
+                    starts_at,
+                    next_due: starts_at,
+                    requires_confirmation: false,
+                    active: true,
+                };
+                match self.db.create_recurring_rule(&rule).await {
+                    Ok(id) => {
+                        self.state.clear_inputs();
+                        self.state.recurring_rules = self.db.get_recurring_rules().await?;
+                        // A past start is only an anchor; say when the first entry is posted
+                        let first = self
+                            .state
+                            .recurring_rules
+                            .iter()
+                            .find(|r| r.id == id && r.next_due != starts_at)
+                            .map(|r| {
+                                format!(
+                                    ", first on {}; earlier dates are not posted",
+                                    r.next_due.format("%Y-%m-%d")
+                                )
+                            })
+                            .unwrap_or_default();
+                        self.state.set_status(format!(
+                            "Scheduled {} {}{}",
+                            rule.label(),
+                            rule.cadence.describe(),
+                            first
+                        ));
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::ToggleRuleActive => {
+                if let Some(rule) = self.state.recurring_rules.get(self.state.selected_rule) {
+                    let (id, active, label) = (rule.id, !rule.active, rule.label());
+                    match self.db.set_recurring_active(id, active).await {
+                        Ok(_) => {
+                            self.state.recurring_rules = self.db.get_recurring_rules().await?;
+                            self.state.recurring_failed.retain(|failed| *failed != id);
+                            self.state.set_status(if active {
+                                format!("Resumed {}", label)
+                            } else {
+                                format!("Paused {}", label)
+                            });
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::ToggleRuleConfirmation => {
+                if let Some(rule) = self.state.recurring_rules.get(self.state.selected_rule) {
+                    let (id, required) = (rule.id, !rule.requires_confirmation);
+                    match self.db.set_recurring_confirmation(id, required).await {
+                        Ok(_) => {
+                            self.state.recurring_rules = self.db.get_recurring_rules().await?;
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
                 }
             }
-        }
-        Ok(None)
-    }
-
-    fn handle_normal_mode(&mut self, key: event::KeyEvent) -> Result<Option<Action>> {
-        match key.code {
-            KeyCode::Tab => {
-                if key.modifiers.contains(KeyModifiers::SHIFT) {
-                    Ok(Some(Action::PrevTab))
-                } else {
-                    Ok(Some(Action::NextTab))
+            Action::RequestDeleteRule => {
+                if let Some(rule) = self.state.recurring_rules.get(self.state.selected_rule) {
+                    self.state.pending_confirm = Some(PendingConfirm::DeleteRecurringRule(rule.id));
                 }
             }
-            KeyCode::Char('i') => Ok(Some(Action::EnterInsert)),
-            KeyCode::Up | KeyCode::Char('k') => Ok(Some(Action::Up)),
-            KeyCode::Down | KeyCode::Char('j') => Ok(Some(Action::Down)),
-            KeyCode::Enter => match self.state.active_tab {
-                ActiveTab::AddFunds => Ok(Some(Action::SubmitFunds)),
-                ActiveTab::AddExpense => Ok(Some(Action::SubmitTransaction)),
-                ActiveTab::Settings => {
-                    if self.state.selected_category == self.state.categories.len() {
-                        Ok(Some(Action::SaveSettings))
-                    } else {
-                        Ok(Some(Action::StartEditingCategory))
+            Action::StartNewImportProfile => {
+                self.state.clear_inputs();
+                self.state.editing_import_profile = Some(0);
+                self.state.mapping_inputs = [
+                    String::new(),
+                    ",".to_string(),
+                    "0".to_string(),
+                    "Date".to_string(),
+                    "%d/%m/%Y".to_string(),
+                    "Amount".to_string(),
+                    String::new(),
+                    "Description".to_string(),
+                    ".".to_string(),
+                ];
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Mapping(0);
+            }
+            Action::StartEditImportProfile => {
+                if let Some(profile) = self.state.selected_import_profile().cloned() {
+                    self.state.clear_inputs();
+                    self.state.editing_import_profile = Some(profile.id);
+                    self.state.mapping_inputs = [
+                        profile.name,
+                        match profile.delimiter {
+                            '\t' => "tab".to_string(),
+                            c => c.to_string(),
+                        },
+                        profile.skip_lines.to_string(),
+                        profile.date_column,
+                        profile.date_format,
+                        profile.amount_column,
+                        profile.second_column.unwrap_or_default(),
+                        profile.description_column,
+                        profile.decimal_separator.to_string(),
+                    ];
+                    self.state.sign_convention = profile.sign_convention;
+                    if let Some(i) = self
+                        .state
+                        .accounts
+                        .iter()
+                        .position(|a| Some(a.id) == profile.account_id)
+                    {
+                        self.state.form_account = i;
                     }
+                    self.state.selected_category = self
+                        .state
+                        .expense_categories()
+                        .iter()
+                        .position(|c| Some(c.id) == profile.category_id)
+                        .unwrap_or(0);
+                    self.state.input_mode = InputMode::Insert;
+                    self.state.active_input = ActiveInput::Mapping(0);
                 }
-                _ => Ok(None),
-            },
-            KeyCode::Left | KeyCode::Char('h') => {
-                if self.state.active_tab == ActiveTab::Reports {
-                    Ok(Some(Action::ChangeDateRange(
-                        self.state.report_date_range.next(),
-                    ))) // Cycling for now
+            }
+            Action::SubmitImportProfile => {
+                let [
+                    name,
+                    delimiter,
+                    skip_lines,
+                    date_column,
+                    date_format,
+                    amount_column,
+                    second_column,
+                    description_column,
+                    decimal_separator,
+                ] = self.state.mapping_inputs.clone();
+                let single_char = |text: &str| {
+                    let mut chars = text.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => None,
+                    }
+                };
+                let delimiter = match delimiter.trim() {
+                    "tab" | "\\t" => Some('\t'),
+                    _ if delimiter == " " => Some(' '),
+                    other => single_char(other),
+                };
+                let Some(delimiter) = delimiter else {
+                    self.state
+                        .set_status("Delimiter must be one character (or \"tab\")");
+                    return Ok(());
+                };
+                let Some(decimal_separator) = single_char(decimal_separator.trim()) else {
+                    self.state.set_status("Decimal separator must be . or ,");
+                    return Ok(());
+                };
+                let skip_lines = if skip_lines.trim().is_empty() {
+                    0
+                } else if let Ok(count) = skip_lines.trim().parse::<usize>() {
+                    count
                 } else {
-                    Ok(None)
+                    self.state.set_status("Skip lines must be a number");
+                    return Ok(());
+                };
+                let second_column = Some(second_column.trim().to_string())
+                    .filter(|c| !c.is_empty() && self.state.sign_convention.uses_second_column());
+
+                let profile = ImportProfile {
+                    id: self.state.editing_import_profile.unwrap_or(0),
+                    name: name.trim().to_string(),
+                    delimiter,
+                    skip_lines,
+                    date_column,
+                    date_format,
+                    amount_column,
+                    second_column,
+                    description_column,
+                    sign_convention: self.state.sign_convention,
+                    decimal_separator,
+                    account_id: self.state.form_account().map(|a| a.id),
+                    category_id: self
+                        .state
+                        .expense_categories()
+                        .get(self.state.selected_category)
+                        .map(|c| c.id),
+                };
+                match self.db.save_import_profile(&profile).await {
+                    Ok(id) => {
+                        self.state.clear_inputs();
+                        self.state.import_profiles = self.db.get_import_profiles().await?;
+                        self.state.selected_import_profile = self
+                            .state
+                            .import_profiles
+                            .iter()
+                            .position(|p| p.id == id)
+                            .unwrap_or(0);
+                        self.state
+                            .set_status(format!("Saved import profile {}", profile.name));
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
                 }
             }
-            KeyCode::Right | KeyCode::Char('l') => {
-                if self.state.active_tab == ActiveTab::Reports {
-                    Ok(Some(Action::ChangeDateRange(
-                        self.state.report_date_range.next(),
-                    )))
-                } else {
-                    Ok(None)
+            Action::RequestDeleteImportProfile => {
+                if let Some(profile) = self.state.selected_import_profile() {
+                    self.state.pending_confirm =
+                        Some(PendingConfirm::DeleteImportProfile(profile.id));
                 }
             }
-            _ => Ok(None),
-        }
-    }
+            Action::LoadImport => {
+                let profile = self.state.selected_import_profile().cloned();
+                let path = self.state.import_path_input.trim().to_string();
+                if path.is_empty() {
+                    self.state
+                        .set_status("Type the path of the statement file first");
+                    self.state.input_mode = InputMode::Insert;
+                    self.state.active_input = ActiveInput::ImportPath;
+                    return Ok(());
+                }
 
-    fn handle_insert_mode(&mut self, key: event::KeyEvent) -> Result<Option<Action>> {
-        match key.code {
-            KeyCode::Esc => {
-                if self.state.input_mode == InputMode::Insert
-                    && self.state.active_input == ActiveInput::CategoryLimit
-                {
-                    Ok(Some(Action::CancelInput))
-                } else {
-                    Ok(Some(Action::EnterNormal))
+                let path = expand_tilde(&PathBuf::from(path));
+                match import::read_statement(profile.as_ref(), &path) {
+                    Ok(mut rows) => {
+                        // OFX and QIF rows carry their own ids; the highlighted profile only
+                        // maps CSV files, so its account and category don't apply to them
+                        let profile =
+                            profile.filter(|_| rows.iter().all(|r| r.import_id.is_none()));
+                        if let Some(i) = profile
+                            .as_ref()
+                            .and_then(|p| p.account_id)
+                            .and_then(|id| self.state.accounts.iter().position(|a| a.id == id))
+                        {
+                            self.state.import_account = i;
+                        }
+                        self.state.import_account = self
+                            .state
+                            .import_account
+                            .min(self.state.accounts.len().saturating_sub(1));
+                        self.state.payees = self.db.get_payees().await?;
+                        import::assign_categories(
+                            &mut rows,
+                            &self.state.payees,
+                            &self.state.expense_categories(),
+                            profile.as_ref().and_then(|p| p.category_id),
+                        );
+                        let known = self.db.get_import_ids().await?;
+                        for row in &mut rows {
+                            if row.import_id.as_ref().is_some_and(|id| known.contains(id)) {
+                                row.status = RowStatus::AlreadyImported;
+                            }
+                        }
+                        let duplicates = match import::duplicate_search_range(&rows) {
+                            Some((start, end)) => {
+                                let existing = self.db.get_transactions_between(start, end).await?;
+                                import::mark_duplicates(&mut rows, &existing)
+                            }
+                            None => 0,
+                        };
+                        let count = |status: &RowStatus| {
+                            rows.iter().filter(|r| r.status == *status).count()
+                        };
+                        let (ready, income, seen) = (
+                            count(&RowStatus::Ready),
+                            count(&RowStatus::Income),
+                            count(&RowStatus::AlreadyImported),
+                        );
+                        let with = profile
+                            .as_ref()
+                            .map(|p| format!(" with {}", p.name))
+                            .unwrap_or_default();
+                        self.state.set_status(if rows.is_empty() {
+                            format!("No rows found in {}", path.display())
+                        } else {
+                            format!(
+                                "Read {} rows{}: {} expenses, {} possible duplicates, {} money in, {} already imported, {} unreadable",
+                                rows.len(),
+                                with,
+                                ready,
+                                duplicates,
+                                income,
+                                seen,
+                                rows.len() - ready - duplicates - income - seen
+                            )
+                        });
+                        self.state.import_rows = rows;
+                        self.state.import_table.select(Some(0));
+                        self.state.input_mode = InputMode::Normal;
+                        self.state.active_input = ActiveInput::None;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
                 }
             }
-            KeyCode::Enter => match self.state.active_tab {
-                ActiveTab::AddFunds => Ok(Some(Action::SubmitFunds)),
-                ActiveTab::AddExpense => Ok(Some(Action::SubmitTransaction)),
-                ActiveTab::Settings => Ok(Some(Action::ConfirmCategoryEdit)),
-                _ => Ok(Some(Action::EnterNormal)),
-            },
-            KeyCode::Tab => {
-                match self.state.active_input {
-                    ActiveInput::Amount => {
-                        self.state.active_input = ActiveInput::Description;
+            Action::ToggleImportRow => {
+                if let Some(row) = self
+                    .state
+                    .import_table
+                    .selected()
+                    .and_then(|i| self.state.import_rows.get_mut(i))
+                {
+                    match row.status {
+                        RowStatus::Ready => row.status = RowStatus::Skipped,
+                        RowStatus::Skipped | RowStatus::Failed(_) => row.status = RowStatus::Ready,
+                        RowStatus::Income => self
+                            .state
+                            .set_status("Money in is recorded on Add Funds, where it is allocated"),
+                        _ => {}
                     }
-                    ActiveInput::Description => {
-                        self.state.active_input = ActiveInput::Category;
+                }
+            }
+            Action::CycleImportAccount if !self.state.accounts.is_empty() => {
+                self.state.import_account =
+                    (self.state.import_account + 1) % self.state.accounts.len();
+            }
+            Action::RequestCommitImport => {
+                let Some(account_id) = self.state.import_account().map(|a| a.id) else {
+                    self.state.set_status("Create an account first");
+                    return Ok(());
+                };
+                let ready: Vec<(DateTime<Local>, i64, Decimal)> = self
+                    .state
+                    .import_rows
+                    .iter()
+                    .filter(|r| r.status == RowStatus::Ready)
+                    .filter_map(|r| Some((r.date?, r.category_id?, r.amount)))
+                    .collect();
+                if ready.is_empty() {
+                    self.state.set_status("No rows left to import");
+                    return Ok(());
+                }
+
+                // Dry-run like a split expense, so overflow and Savings use are shown up front
+                let (mut current, mut closed) = (Vec::new(), Vec::new());
+                let mut closed_at = Local::now();
+                for (at, category_id, amount) in &ready {
+                    if self.db.in_closed_period(*at).await? {
+                        closed.push((*category_id, *amount));
+                        closed_at = *at;
+                    } else {
+                        current.push((*category_id, *amount));
                     }
-                    ActiveInput::Category => {
-                        self.state.active_input = ActiveInput::Amount;
+                }
+                let mut allocations = Vec::new();
+                for (lines, at) in [(&current, Local::now()), (&closed, closed_at)] {
+                    if lines.is_empty() {
+                        continue;
                     }
-                    ActiveInput::None => {
-                        self.state.active_input = ActiveInput::Amount;
+                    match self.db.preview_split_transaction(lines, at).await {
+                        Ok(previews) => allocations.extend(previews.into_iter().flatten()),
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                            return Ok(());
+                        }
                     }
-                    ActiveInput::CategoryLimit => {} // No tab cycling in limit edit
                 }
-                Ok(None)
+                self.state.pending_confirm = Some(PendingConfirm::CommitImport {
+                    account_id,
+                    count: ready.len(),
+                    total: ready.iter().map(|(_, _, amount)| amount).sum(),
+                    closed_period: closed.len(),
+                    allocations: merge_allocations(allocations),
+                });
             }
-            KeyCode::Char(c) => Ok(Some(Action::InputChar(c))),
-            KeyCode::Backspace => Ok(Some(Action::InputBackspace)),
-            KeyCode::Delete => Ok(Some(Action::InputDelete)),
-            KeyCode::Up => Ok(Some(Action::Up)),
-            KeyCode::Down => Ok(Some(Action::Down)),
-            _ => Ok(None),
-        }
-    }
-
-    async fn update(&mut self, action: Action) -> Result<()> {
-        match action {
-            Action::Quit => {
-                self.should_quit = true;
+            Action::DiscardImport => {
+                self.state.import_rows.clear();
+                self.state.import_table.select(None);
             }
-            Action::NextTab => {
-                self.state.active_tab = self.state.active_tab.next();
-                self.state.clear_inputs();
-                if self.state.active_tab == ActiveTab::Reports {
-                    // Auto-refresh reports on tab switch
-                    self.state.transactions = self
-                        .db
-                        .get_transactions(self.state.report_date_range)
-                        .await?;
-                    self.state.summary_stats = Some(self.db.get_summary_stats().await?);
+            Action::ToggleReportView => {
+                self.state.report_view = match self.state.report_view {
+                    ReportView::Transactions => ReportView::Tags,
+                    ReportView::Tags => ReportView::Payees,
+                    ReportView::Payees => ReportView::Transactions,
+                };
+            }
+            Action::ExportCsv(range) => {
+                let dir = self.config.export_dir();
+                match export::export_csv(&self.db, range, &dir).await {
+                    Ok(summary) => {
+                        self.state.set_status(format!(
+                            "Exported {} transactions and {} fund entries to {}",
+                            summary.transactions,
+                            summary.funds,
+                            summary.dir.display()
+                        ));
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
                 }
             }
-            Action::PrevTab => {
-                self.state.active_tab = self.state.active_tab.prev();
+            Action::StartSearch => {
                 self.state.clear_inputs();
-                if self.state.active_tab == ActiveTab::Reports {
-                    self.state.transactions = self
-                        .db
-                        .get_transactions(self.state.report_date_range)
-                        .await?;
-                    self.state.summary_stats = Some(self.db.get_summary_stats().await?);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Search;
+            }
+            Action::ClearSearch => {
+                self.state.search_input.clear();
+                if self.state.active_input == ActiveInput::Search {
+                    self.state.clear_inputs();
                 }
+                self.refresh_reports().await?;
             }
-            Action::EnterInsert => {
-                self.state.input_mode = InputMode::Insert;
-                if self.state.active_input == ActiveInput::None {
-                    self.state.active_input = ActiveInput::Amount;
+            Action::OpenTagPicker => {
+                self.state.known_tags = self.db.get_tags().await?;
+                if self.state.known_tags.is_empty() {
+                    self.state
+                        .set_status("No tags yet, type #tag in the description");
+                } else {
+                    self.state.tag_picker = Some(0);
                 }
             }
-            Action::EnterNormal => {
-                self.state.input_mode = InputMode::Normal;
+            Action::ConfirmTagPick => {
+                let Some(tag) = self
+                    .state
+                    .tag_picker
+                    .take()
+                    .and_then(|i| self.state.known_tags.get(i).cloned())
+                else {
+                    return Ok(());
+                };
+                let desc = &mut self.state.description_input;
+                // Complete a partially typed `#ta`, otherwise append
+                let last_word_start = desc.rfind(' ').map_or(0, |i| i + 1);
+                if desc[last_word_start..].starts_with('#') {
+                    desc.truncate(last_word_start);
+                } else if !desc.is_empty() && !desc.ends_with(' ') {
+                    desc.push(' ');
+                }
+                desc.push_str(&format!("#{} ", tag));
             }
-            Action::ToggleHelp => {
-                self.state.show_help = !self.state.show_help;
+            Action::CloseTagPicker => {
+                self.state.tag_picker = None;
             }
-            Action::StartEditingCategory => {
-                if let Some(cat) = self.state.categories.get(self.state.selected_category) {
-                    self.state.input_mode = InputMode::Insert;
-                    self.state.active_input = ActiveInput::CategoryLimit;
-                    self.state.amount_input = cat.limit_percentage.to_string();
-                }
+            Action::CloseOverflowPicker => {
+                self.state.overflow_picker = None;
             }
-            Action::ConfirmCategoryEdit => {
-                if let Ok(limit) = Decimal::from_str(&self.state.amount_input) {
-                    if let Some(cat) = self.state.categories.get_mut(self.state.selected_category) {
-                        cat.limit_percentage = limit;
-                        self.state.clear_inputs();
-                        // Note: We do NOT define self.state.categories from DB here logic
-                        // We keep it in memory until SaveSettings
-                    }
+            Action::ConfirmPayeePick => {
+                let Some((name, last_category_id)) = self
+                    .state
+                    .payee_pick
+                    .take()
+                    .and_then(|i| self.state.payee_suggestions().get(i).copied())
+                    .map(|p| (p.name.clone(), p.last_category_id))
+                else {
+                    return Ok(());
+                };
+                // Keep any #tags already typed
+                let tags: Vec<&str> = self
+                    .state
+                    .description_input
+                    .split_whitespace()
+                    .filter(|word| word.starts_with('#'))
+                    .collect();
+                self.state.description_input = if tags.is_empty() {
+                    name
                 } else {
-                    self.state.set_status("Invalid number format");
+                    format!("{} {} ", name, tags.join(" "))
+                };
+                if let Some(index) = self
+                    .state
+                    .expense_categories()
+                    .iter()
+                    .position(|c| Some(c.id) == last_category_id)
+                {
+                    self.state.selected_category = index;
                 }
             }
-            Action::SaveSettings => {
-                let mut failed = false;
-                // Clone needed to iterate async? No, we can iterate indices or similar
-                // But update_category_limit is async.
-                // We'll iterate clones to avoid borrow check issues during async calls
-                let categories = self.state.categories.clone();
-
-                for cat in categories {
-                    if let Err(e) = self
-                        .db
-                        .update_category_limit(cat.id, cat.limit_percentage)
-                        .await
-                    {
-                        self.state
-                            .set_status(format!("Error saving {}: {}", cat.name, e));
-                        failed = true;
+            Action::OpenProfilePicker => {
+                self.state.clear_inputs();
+                match self.config.profiles() {
+                    Ok(profiles) => {
+                        let index = profiles
+                            .iter()
+                            .position(|p| *p == self.config.db_path)
+                            .unwrap_or(0);
+                        self.state.profiles = profiles;
+                        self.state.profile_picker = Some(index);
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
                     }
                 }
-
-                if !failed {
-                    self.state.set_status("All settings saved successfully.");
-                    // Refresh from DB to be sure? Not needed if we trust the loop
-                    self.state.categories = self.db.get_categories().await?;
+            }
+            Action::ConfirmProfilePick => {
+                let Some(index) = self.state.profile_picker.take() else {
+                    return Ok(());
+                };
+                if let Some(path) = self.state.profiles.get(index).cloned()
+                    && path != self.config.db_path
+                {
+                    self.switch_profile(path).await?;
                 }
             }
+            Action::StartNewProfile => {
+                self.state.name_input.clear();
+                self.state.name_prompt = Some(NamePrompt::NewProfile);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::ProfileName;
+            }
+            Action::SubmitProfileName => match self.config.profile_path(&self.state.name_input) {
+                Ok(path) => {
+                    self.switch_profile(path).await?;
+                }
+                Err(e) => {
+                    self.state.set_status(format!("Error: {}", e));
+                }
+            },
             Action::CancelInput => {
                 self.state.clear_inputs();
             }
+            Action::Up if !self.state.payee_suggestions().is_empty() => {
+                // Moving above the first suggestion goes back to typing
+                self.state.payee_pick = self.state.payee_pick.and_then(|i| i.checked_sub(1));
+            }
+            Action::Down if !self.state.payee_suggestions().is_empty() => {
+                let last = self.state.payee_suggestions().len() - 1;
+                self.state.payee_pick =
+                    Some(self.state.payee_pick.map_or(0, |i| (i + 1).min(last)));
+            }
+            Action::Up if self.state.tag_picker.is_some() => {
+                if let Some(i) = self.state.tag_picker.as_mut() {
+                    *i = i.saturating_sub(1);
+                }
+            }
+            Action::Down if self.state.tag_picker.is_some() => {
+                let last = self.state.known_tags.len().saturating_sub(1);
+                if let Some(i) = self.state.tag_picker.as_mut() {
+                    *i = (*i + 1).min(last);
+                }
+            }
+            Action::Up if self.state.profile_picker.is_some() => {
+                if let Some(i) = self.state.profile_picker.as_mut() {
+                    *i = i.saturating_sub(1);
+                }
+            }
+            Action::Down if self.state.profile_picker.is_some() => {
+                let last = self.state.profiles.len().saturating_sub(1);
+                if let Some(i) = self.state.profile_picker.as_mut() {
+                    *i = (*i + 1).min(last);
+                }
+            }
+            Action::Up if self.state.overflow_picker.is_some() => {
+                if let Some(i) = self.state.overflow_picker.as_mut() {
+                    *i = i.saturating_sub(1);
+                }
+            }
+            Action::Down if self.state.overflow_picker.is_some() => {
+                let last = self.state.overflow_candidates().len().saturating_sub(1);
+                if let Some(i) = self.state.overflow_picker.as_mut() {
+                    *i = (*i + 1).min(last);
+                }
+            }
+            Action::Up if self.state.active_input == ActiveInput::Account => {
+                self.state.form_account = self.state.form_account.saturating_sub(1);
+            }
+            Action::Down if self.state.active_input == ActiveInput::Account => {
+                let last = self.state.accounts.len().saturating_sub(1);
+                self.state.form_account = (self.state.form_account + 1).min(last);
+            }
+            Action::Up | Action::Down if self.state.active_input == ActiveInput::RuleKind => {
+                self.state.rule_kind = self.state.rule_kind.toggle();
+            }
+            Action::Up
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                self.state.selected_rule = self.state.selected_rule.saturating_sub(1);
+            }
+            Action::Down
+                if self.state.active_tab == ActiveTab::Recurring && !self.state.creating_rule =>
+            {
+                let last = self.state.recurring_rules.len().saturating_sub(1);
+                self.state.selected_rule = (self.state.selected_rule + 1).min(last);
+            }
+            Action::Up if self.state.active_input == ActiveInput::TransferTarget => {
+                self.state.transfer_target = self.state.transfer_target.saturating_sub(1);
+            }
+            Action::Down if self.state.active_input == ActiveInput::TransferTarget => {
+                let last = self.state.accounts.len().saturating_sub(1);
+                self.state.transfer_target = (self.state.transfer_target + 1).min(last);
+            }
+            Action::Up
+                if self.state.active_tab == ActiveTab::Accounts && !self.state.transferring =>
+            {
+                self.state.selected_account = self.state.selected_account.saturating_sub(1);
+            }
+            Action::Down
+                if self.state.active_tab == ActiveTab::Accounts && !self.state.transferring =>
+            {
+                let last = self.state.accounts.len().saturating_sub(1);
+                self.state.selected_account = (self.state.selected_account + 1).min(last);
+            }
+            Action::Up if self.state.active_input == ActiveInput::SignConvention => {
+                self.state.sign_convention = self.state.sign_convention.prev();
+            }
+            Action::Down if self.state.active_input == ActiveInput::SignConvention => {
+                self.state.sign_convention = self.state.sign_convention.next();
+            }
+            Action::Up if self.previewing_import() => {
+                self.state.import_table.select_previous();
+            }
+            Action::Down if self.previewing_import() => {
+                let has_next = self
+                    .state
+                    .import_table
+                    .selected()
+                    .is_some_and(|i| i + 1 < self.state.import_rows.len());
+                if has_next {
+                    self.state.import_table.select_next();
+                }
+            }
+            Action::Left | Action::Right if self.previewing_import() => {
+                let ids: Vec<i64> = self
+                    .state
+                    .expense_categories()
+                    .iter()
+                    .map(|c| c.id)
+                    .collect();
+                if let Some(row) = self
+                    .state
+                    .import_table
+                    .selected()
+                    .and_then(|i| self.state.import_rows.get_mut(i))
+                    .filter(|r| {
+                        matches!(
+                            r.status,
+                            RowStatus::Ready | RowStatus::Skipped | RowStatus::Failed(_)
+                        )
+                    })
+                    .filter(|_| !ids.is_empty())
+                {
+                    let current = ids.iter().position(|id| Some(*id) == row.category_id);
+                    let next = match (action, current) {
+                        (Action::Left, Some(i)) => (i + ids.len() - 1) % ids.len(),
+                        (_, Some(i)) => (i + 1) % ids.len(),
+                        (_, None) => 0,
+                    };
+                    row.category_id = Some(ids[next]);
+                }
+            }
+            Action::Up if self.import_idle() => {
+                self.state.selected_import_profile =
+                    self.state.selected_import_profile.saturating_sub(1);
+            }
+            Action::Down if self.import_idle() => {
+                let last = self.state.import_profiles.len().saturating_sub(1);
+                self.state.selected_import_profile =
+                    (self.state.selected_import_profile + 1).min(last);
+            }
+            Action::Up if self.dashboard_idle() => {
+                self.state.selected_goal = self.state.selected_goal.saturating_sub(1);
+            }
+            Action::Down if self.dashboard_idle() => {
+                let last = self.state.savings_goals.len().saturating_sub(1);
+                self.state.selected_goal = (self.state.selected_goal + 1).min(last);
+            }
+            Action::Up | Action::Down
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.refunding.is_some() =>
+            {
+                // Pick which line of a split expense the refund is for
+                let Some(lines) = self.state.selected_entry().map(<[_]>::to_vec) else {
+                    return Ok(());
+                };
+                let Some(current) = lines
+                    .iter()
+                    .position(|t| Some(t.id) == self.state.refunding)
+                else {
+                    return Ok(());
+                };
+                let next = if action == Action::Up {
+                    (current + lines.len() - 1) % lines.len()
+                } else {
+                    (current + 1) % lines.len()
+                };
+                if next != current {
+                    let line = &lines[next];
+                    let refundable = line.amount - self.db.get_refunded_total(line.id).await?;
+                    self.fill_refund_form(line, refundable.max(Decimal::ZERO));
+                }
+            }
             Action::Up => {
-                if self.state.active_tab == ActiveTab::Settings {
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.active_input != ActiveInput::Category
+                {
+                    if self.state.editing_transaction.is_none() && self.state.refunding.is_none() {
+                        self.state.transaction_list.select_previous();
+                    }
+                } else if self.state.active_tab == ActiveTab::Settings {
                     if self.state.selected_category > 0 {
                         self.state.selected_category -= 1;
                     }
@@ -842,15 +4821,28 @@ impl App {
                 }
             }
             Action::Down => {
-                let max_idx = self
-                    .state
-                    .categories
-                    .iter()
-                    .filter(|c| {
-                        c.name != CategoryName::Savings
-                            || self.state.active_tab == ActiveTab::Settings
-                    })
-                    .count();
+                if self.state.active_tab == ActiveTab::Reports
+                    && self.state.active_input != ActiveInput::Category
+                {
+                    let has_next = self
+                        .state
+                        .transaction_list
+                        .selected()
+                        .is_some_and(|i| i + 1 < self.state.report_entries().len());
+                    if self.state.editing_transaction.is_none()
+                        && self.state.refunding.is_none()
+                        && has_next
+                    {
+                        self.state.transaction_list.select_next();
+                    }
+                    return Ok(());
+                }
+
+                let max_idx = if self.state.active_tab == ActiveTab::Settings {
+                    self.state.categories.len()
+                } else {
+                    self.state.expense_categories().len()
+                };
                 // If in Settings, we allow one more index (the Save button), so max_idx = count
                 // If not in Settings, max_idx = count - 1 (saturating_sub(1))
 
@@ -868,13 +4860,55 @@ impl App {
                 }
             }
             Action::InputChar(c) => match self.state.active_input {
-                ActiveInput::Amount | ActiveInput::CategoryLimit => {
-                    if c.is_ascii_digit() || c == '.' {
-                        self.state.amount_input.push(c);
-                    }
+                ActiveInput::Amount if self.state.currency.accepts_input_char(c) => {
+                    self.state.amount_input.push(c);
+                }
+                ActiveInput::CategoryLimit
+                    if self.state.selected_is_fixed()
+                        && self.state.currency.accepts_input_char(c) =>
+                {
+                    self.state.amount_input.push(c);
+                }
+                ActiveInput::CategoryLimit
+                    if !self.state.selected_is_fixed() && (c.is_ascii_digit() || c == '.') =>
+                {
+                    self.state.amount_input.push(c);
+                }
+                ActiveInput::Description => {
+                    self.state.description_input.push(c);
+                    self.state.payee_pick = None;
+                }
+                ActiveInput::LineAmount if self.state.currency.accepts_input_char(c) => {
+                    self.state.line_amount_input.push(c);
+                }
+                ActiveInput::Source => {
+                    self.state.source_input.push(c);
+                }
+                ActiveInput::CategoryName
+                | ActiveInput::AccountName
+                | ActiveInput::ProfileName
+                | ActiveInput::GoalName => {
+                    self.state.name_input.push(c);
+                }
+                ActiveInput::Date => {
+                    self.state.date_input.push(c);
+                }
+                ActiveInput::Cadence => {
+                    self.state.cadence_input.push(c);
+                }
+                ActiveInput::Answer(i) => {
+                    self.state.answer_inputs[i].push(c);
+                }
+                ActiveInput::ImportPath => {
+                    self.state.import_path_input.push(c);
                 }
-                ActiveInput::Description => {
-                    self.state.description_input.push(c);
+                ActiveInput::Mapping(i) => {
+                    self.state.mapping_inputs[i].push(c);
+                }
+                ActiveInput::Search => {
+                    self.state.search_input.push(c);
+                    self.state.transaction_list.select(Some(0));
+                    self.refresh_reports().await?;
                 }
                 _ => {}
             },
@@ -884,6 +4918,39 @@ impl App {
                 }
                 ActiveInput::Description => {
                     self.state.description_input.pop();
+                    self.state.payee_pick = None;
+                }
+                ActiveInput::LineAmount => {
+                    self.state.line_amount_input.pop();
+                }
+                ActiveInput::Source => {
+                    self.state.source_input.pop();
+                }
+                ActiveInput::CategoryName
+                | ActiveInput::AccountName
+                | ActiveInput::ProfileName
+                | ActiveInput::GoalName => {
+                    self.state.name_input.pop();
+                }
+                ActiveInput::Date => {
+                    self.state.date_input.pop();
+                }
+                ActiveInput::Cadence => {
+                    self.state.cadence_input.pop();
+                }
+                ActiveInput::Answer(i) => {
+                    self.state.answer_inputs[i].pop();
+                }
+                ActiveInput::ImportPath => {
+                    self.state.import_path_input.pop();
+                }
+                ActiveInput::Mapping(i) => {
+                    self.state.mapping_inputs[i].pop();
+                }
+                ActiveInput::Search => {
+                    self.state.search_input.pop();
+                    self.state.transaction_list.select(Some(0));
+                    self.refresh_reports().await?;
                 }
                 _ => {}
             },
@@ -893,20 +4960,83 @@ impl App {
                 }
                 ActiveInput::Description => {
                     self.state.description_input.pop();
+                    self.state.payee_pick = None;
+                }
+                ActiveInput::LineAmount => {
+                    self.state.line_amount_input.pop();
+                }
+                ActiveInput::Source => {
+                    self.state.source_input.pop();
+                }
+                ActiveInput::CategoryName
+                | ActiveInput::AccountName
+                | ActiveInput::ProfileName
+                | ActiveInput::GoalName => {
+                    self.state.name_input.pop();
+                }
+                ActiveInput::Date => {
+                    self.state.date_input.pop();
+                }
+                ActiveInput::Cadence => {
+                    self.state.cadence_input.pop();
+                }
+                ActiveInput::Answer(i) => {
+                    self.state.answer_inputs[i].pop();
+                }
+                ActiveInput::ImportPath => {
+                    self.state.import_path_input.pop();
+                }
+                ActiveInput::Mapping(i) => {
+                    self.state.mapping_inputs[i].pop();
+                }
+                ActiveInput::Search => {
+                    self.state.search_input.pop();
+                    self.state.transaction_list.select(Some(0));
+                    self.refresh_reports().await?;
                 }
                 _ => {}
             },
             Action::SubmitFunds => {
-                if let Ok(amount) = Decimal::from_str(&self.state.amount_input) {
+                let Some(account) = self.state.form_account().cloned() else {
+                    self.state.set_status("Create an account first");
+                    return Ok(());
+                };
+                let Some(added_at) = self.state.entry_date() else {
+                    self.state
+                        .set_status("Invalid date, use y, -3d or YYYY-MM-DD [HH:MM]");
+                    return Ok(());
+                };
+                if let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input) {
                     if amount > Decimal::ZERO {
-                        match self.db.add_funds(amount).await {
-                            Ok(_) => {
+                        let source = Some(self.state.source_input.trim().to_string())
+                            .filter(|s| !s.is_empty());
+                        let note = Some(self.state.description_input.clone())
+                            .filter(|n| !n.trim().is_empty());
+                        match self
+                            .db
+                            .add_funds(amount, account.id, source.clone(), note, added_at)
+                            .await
+                        {
+                            Ok(overdrawn) => {
+                                let overdrawn = if overdrawn > Decimal::ZERO {
+                                    format!(
+                                        "; expenses dated after it overdraw Savings by {}",
+                                        self.state.currency.format(overdrawn)
+                                    )
+                                } else {
+                                    String::new()
+                                };
                                 self.state.set_status(format!(
-                                    "Added IDR {} in funds",
-                                    format_idr(amount)
+                                    "Added {} in funds to {}{}{}",
+                                    self.state.currency.format(amount),
+                                    account.name,
+                                    source.map(|s| format!(" from {}", s)).unwrap_or_default(),
+                                    overdrawn
                                 ));
                                 self.state.clear_inputs();
                                 self.state.balances = self.db.get_category_balances().await?;
+                                self.state.accounts = self.db.get_accounts().await?;
+                                self.refresh_goals().await?;
                             }
                             Err(e) => {
                                 self.state.set_status(format!("Error: {}", e));
@@ -919,43 +5049,227 @@ impl App {
                     self.state.set_status("Invalid amount format");
                 }
             }
-            Action::SubmitTransaction => {
-                if let Ok(amount) = Decimal::from_str(&self.state.amount_input) {
-                    if amount > Decimal::ZERO {
-                        let selectable_cats: Vec<&crate::models::Category> = self
-                            .state
-                            .categories
-                            .iter()
-                            .filter(|c| c.name != CategoryName::Savings)
-                            .collect();
+            Action::SubmitTransaction if self.state.splitting => {
+                let lines = self.state.split_lines.clone();
+                if lines.len() < 2 {
+                    self.state.set_status(
+                        "Add at least two lines, or Ctrl+S to record a single category",
+                    );
+                    return Ok(());
+                }
+                let assigned: Decimal = lines.iter().map(|(_, amount)| amount).sum();
+                if !self.state.amount_input.trim().is_empty() {
+                    let Some(total) = self.state.currency.parse_amount(&self.state.amount_input)
+                    else {
+                        self.state.set_status("Invalid amount format");
+                        return Ok(());
+                    };
+                    if total != assigned {
+                        self.state.set_status(format!(
+                            "Lines add up to {} but the total is {}",
+                            self.state.currency.format(assigned),
+                            self.state.currency.format(total)
+                        ));
+                        return Ok(());
+                    }
+                }
+                let Some(account_id) = self.state.form_account().map(|a| a.id) else {
+                    self.state.set_status("Create an account first");
+                    return Ok(());
+                };
+                let Some(created_at) = self.state.entry_date() else {
+                    self.state
+                        .set_status("Invalid date, use y, -3d or YYYY-MM-DD [HH:MM]");
+                    return Ok(());
+                };
+                let desc = if self.state.description_input.is_empty() {
+                    None
+                } else {
+                    Some(self.state.description_input.clone())
+                };
+                if self
+                    .hold_duplicate(assigned, created_at, desc.as_deref())
+                    .await?
+                {
+                    return Ok(());
+                }
 
-                        if let Some(cat) = selectable_cats.get(self.state.selected_category) {
-                            let desc = if self.state.description_input.is_empty() {
-                                None
-                            } else {
-                                Some(self.state.description_input.clone())
-                            };
-
-                            match self.db.create_transaction(cat.name, amount, desc).await {
-                                Ok(_) => {
-                                    self.state.set_status(format!(
-                                        "Added IDR {} expense to {}",
-                                        format_idr(amount),
-                                        cat.name
-                                    ));
-                                    self.state.clear_inputs();
-                                    self.state.balances = self.db.get_category_balances().await?;
-                                }
-                                Err(e) => {
-                                    self.state.set_status(format!("Error: {}", e));
-                                }
-                            }
+                // Same dry-run as a single expense, one plan per line
+                match self.db.preview_split_transaction(&lines, created_at).await {
+                    Ok(plans)
+                        if plans
+                            .iter()
+                            .zip(&lines)
+                            .any(|(plan, (cat, _))| plan.iter().any(|a| a.category_id != *cat)) =>
+                    {
+                        let allocations = merge_allocations(plans.into_iter().flatten());
+                        self.state.input_mode = InputMode::Normal;
+                        self.state.pending_confirm = Some(PendingConfirm::OverflowSplit {
+                            lines,
+                            description: desc,
+                            account_id,
+                            created_at,
+                            closed_period: self.db.in_closed_period(created_at).await?,
+                            allocations,
+                        });
+                    }
+                    Ok(_) => {
+                        self.commit_split(&lines, desc, account_id, created_at)
+                            .await?
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::ToggleSplit => {
+                self.state.splitting = !self.state.splitting;
+                self.state.split_lines.clear();
+                self.state.line_amount_input.clear();
+                if !self.state.form_fields().contains(&self.state.active_input) {
+                    self.state.active_input = ActiveInput::Amount;
+                }
+                self.state.set_status(if self.state.splitting {
+                    "Split mode: pick a category, type its amount, Ctrl+N to add the line"
+                } else {
+                    "Split mode off"
+                });
+            }
+            Action::AddSplitLine => {
+                let Some(category_id) = self
+                    .state
+                    .expense_categories()
+                    .get(self.state.selected_category)
+                    .map(|c| c.id)
+                else {
+                    return Ok(());
+                };
+                let assigned: Decimal = self.state.split_lines.iter().map(|(_, a)| a).sum();
+                let amount = if self.state.line_amount_input.trim().is_empty() {
+                    // The remainder of the total, so the last line never needs typing
+                    match self.state.currency.parse_amount(&self.state.amount_input) {
+                        Some(total) => total - assigned,
+                        None => {
+                            self.state
+                                .set_status("Type a line amount, or a total to take the rest of");
+                            return Ok(());
                         }
-                    } else {
-                        self.state.set_status("Amount must be positive");
                     }
                 } else {
+                    match self
+                        .state
+                        .currency
+                        .parse_amount(&self.state.line_amount_input)
+                    {
+                        Some(amount) => amount,
+                        None => {
+                            self.state.set_status("Invalid amount format");
+                            return Ok(());
+                        }
+                    }
+                };
+                if amount <= Decimal::ZERO {
+                    self.state.set_status("Line amount must be positive");
+                    return Ok(());
+                }
+                match self
+                    .state
+                    .split_lines
+                    .iter_mut()
+                    .find(|(cat, _)| *cat == category_id)
+                {
+                    Some((_, existing)) => *existing += amount,
+                    None => self.state.split_lines.push((category_id, amount)),
+                }
+                self.state.line_amount_input.clear();
+            }
+            Action::RemoveSplitLine => {
+                self.state.split_lines.pop();
+            }
+            Action::SkipDuplicate => {
+                let date = self
+                    .state
+                    .duplicate_warning()
+                    .and_then(|entry| entry.first())
+                    .map(|t| t.created_at.format("%Y-%m-%d").to_string());
+                if let Some(date) = date {
+                    self.state.clear_inputs();
+                    self.state
+                        .set_status(format!("Skipped, already recorded on {}", date));
+                }
+            }
+            Action::SubmitTransaction => {
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
                     self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                if amount <= Decimal::ZERO {
+                    self.state.set_status("Amount must be positive");
+                    return Ok(());
+                }
+                let Some((category_id, category_name)) = self
+                    .state
+                    .expense_categories()
+                    .get(self.state.selected_category)
+                    .map(|c| (c.id, c.name.clone()))
+                else {
+                    return Ok(());
+                };
+                let Some(account_id) = self.state.form_account().map(|a| a.id) else {
+                    self.state.set_status("Create an account first");
+                    return Ok(());
+                };
+                let Some(created_at) = self.state.entry_date() else {
+                    self.state
+                        .set_status("Invalid date, use y, -3d or YYYY-MM-DD [HH:MM]");
+                    return Ok(());
+                };
+                let desc = if self.state.description_input.is_empty() {
+                    None
+                } else {
+                    Some(self.state.description_input.clone())
+                };
+                if self
+                    .hold_duplicate(amount, created_at, desc.as_deref())
+                    .await?
+                {
+                    return Ok(());
+                }
+
+                // Dry-run first so overflow never happens silently
+                match self
+                    .db
+                    .preview_transaction(category_id, amount, created_at)
+                    .await
+                {
+                    Ok(allocations) if allocations.iter().any(|a| a.category_id != category_id) => {
+                        self.state.input_mode = InputMode::Normal;
+                        self.state.pending_confirm = Some(PendingConfirm::OverflowExpense {
+                            category_id,
+                            category_name,
+                            amount,
+                            description: desc,
+                            account_id,
+                            created_at,
+                            closed_period: self.db.in_closed_period(created_at).await?,
+                            allocations,
+                        });
+                    }
+                    Ok(_) => {
+                        self.commit_expense(
+                            category_id,
+                            &category_name,
+                            amount,
+                            desc,
+                            account_id,
+                            created_at,
+                        )
+                        .await?
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
                 }
             }
             Action::RefreshBalances => {
@@ -966,14 +5280,655 @@ impl App {
             }
             Action::ChangeDateRange(range) => {
                 self.state.report_date_range = range;
-                self.state.transactions = self.db.get_transactions(range).await?;
-                // Summary stats are global for now
-                self.state.summary_stats = Some(self.db.get_summary_stats().await?);
+                self.refresh_reports().await?;
+            }
+            Action::StartEditTransaction => {
+                if self
+                    .state
+                    .selected_transaction()
+                    .is_some_and(|t| t.is_refund())
+                {
+                    self.state
+                        .set_status("Refunds can't be edited; delete it and record it again");
+                    return Ok(());
+                }
+                if self
+                    .state
+                    .selected_transaction()
+                    .is_some_and(|t| t.split_id.is_some())
+                {
+                    self.state.set_status(
+                        "Split expenses can't be edited; delete it and record it again",
+                    );
+                    return Ok(());
+                }
+                if let Some(t) = self.state.selected_transaction().cloned() {
+                    self.state.clear_inputs();
+                    self.state.editing_transaction = Some(t.id);
+                    self.state.amount_input = self.state.currency.format_input(t.amount);
+                    self.state.description_input = t.description.unwrap_or_default();
+                    self.state.date_input = t.created_at.format("%Y-%m-%d %H:%M").to_string();
+                    self.state.selected_category = self
+                        .state
+                        .expense_categories()
+                        .iter()
+                        .position(|c| c.id == t.category_id)
+                        .unwrap_or(0);
+                    self.state.input_mode = InputMode::Insert;
+                    self.state.active_input = ActiveInput::Amount;
+                }
+            }
+            Action::SubmitTransactionEdit => {
+                let Some(id) = self.state.editing_transaction else {
+                    return Ok(());
+                };
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                if amount <= Decimal::ZERO {
+                    self.state.set_status("Amount must be positive");
+                    return Ok(());
+                }
+                let Some(created_at) = self.state.entry_date() else {
+                    self.state
+                        .set_status("Invalid date, use y, -3d or YYYY-MM-DD [HH:MM]");
+                    return Ok(());
+                };
+                let Some(category_id) = self
+                    .state
+                    .expense_categories()
+                    .get(self.state.selected_category)
+                    .map(|c| c.id)
+                else {
+                    return Ok(());
+                };
+                let desc = if self.state.description_input.is_empty() {
+                    None
+                } else {
+                    Some(self.state.description_input.clone())
+                };
+
+                match self
+                    .db
+                    .update_transaction(id, category_id, amount, desc, created_at)
+                    .await
+                {
+                    Ok(_) => {
+                        self.state.set_status("Expense updated");
+                        self.state.clear_inputs();
+                        self.state.balances = self.db.get_category_balances().await?;
+                        self.state.accounts = self.db.get_accounts().await?;
+                        self.refresh_reports().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
+            }
+            Action::StartRefund => {
+                let Some(entry) = self.state.selected_entry().map(<[_]>::to_vec) else {
+                    return Ok(());
+                };
+                if entry.first().is_some_and(|t| t.is_refund()) {
+                    self.state.set_status("That is already a refund");
+                    return Ok(());
+                }
+                // A split expense is refunded one line at a time, starting with the first
+                // line that has anything left to refund
+                let mut open_line = None;
+                for line in entry {
+                    let refundable = line.amount - self.db.get_refunded_total(line.id).await?;
+                    if refundable > Decimal::ZERO {
+                        open_line = Some((line, refundable));
+                        break;
+                    }
+                }
+                let Some((line, refundable)) = open_line else {
+                    self.state
+                        .set_status("This expense was already refunded in full");
+                    return Ok(());
+                };
+                self.state.clear_inputs();
+                self.fill_refund_form(&line, refundable);
+                self.state.input_mode = InputMode::Insert;
+                self.state.active_input = ActiveInput::Amount;
+            }
+            Action::SubmitRefund => {
+                let Some(original_id) = self.state.refunding else {
+                    return Ok(());
+                };
+                let Some(amount) = self.state.currency.parse_amount(&self.state.amount_input)
+                else {
+                    self.state.set_status("Invalid amount format");
+                    return Ok(());
+                };
+                let Some(created_at) = self.state.entry_date() else {
+                    self.state
+                        .set_status("Invalid date, try y, -3d or 2026-10-01");
+                    return Ok(());
+                };
+                let desc = Some(self.state.description_input.clone()).filter(|d| !d.is_empty());
+
+                match self
+                    .db
+                    .create_refund(original_id, amount, desc, created_at)
+                    .await
+                {
+                    Ok(_) => {
+                        self.state.set_status(format!(
+                            "Refund of {} recorded",
+                            self.state.currency.format(amount)
+                        ));
+                        self.state.clear_inputs();
+                        self.state.balances = self.db.get_category_balances().await?;
+                        self.state.accounts = self.db.get_accounts().await?;
+                        self.refresh_reports().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                }
             }
+            Action::RequestDeleteTransaction => {
+                if let Some(t) = self.state.selected_transaction() {
+                    self.state.pending_confirm = Some(PendingConfirm::DeleteTransaction(t.id));
+                }
+            }
+            Action::ConfirmYes => match self.state.pending_confirm.take() {
+                Some(PendingConfirm::DeleteTransaction(id)) => {
+                    match self.db.delete_transaction(id).await {
+                        Ok(_) => {
+                            self.state.set_status("Expense deleted");
+                            self.state.balances = self.db.get_category_balances().await?;
+                            self.state.accounts = self.db.get_accounts().await?;
+                            self.refresh_reports().await?;
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+                Some(PendingConfirm::OverflowExpense {
+                    category_id,
+                    category_name,
+                    amount,
+                    description,
+                    account_id,
+                    created_at,
+                    ..
+                }) => {
+                    self.commit_expense(
+                        category_id,
+                        &category_name,
+                        amount,
+                        description,
+                        account_id,
+                        created_at,
+                    )
+                    .await?;
+                }
+                Some(PendingConfirm::OverflowSplit {
+                    lines,
+                    description,
+                    account_id,
+                    created_at,
+                    ..
+                }) => {
+                    self.commit_split(&lines, description, account_id, created_at)
+                        .await?;
+                }
+                Some(PendingConfirm::RecurringOccurrence(rule)) => {
+                    match self.db.post_recurring_occurrence(&rule).await {
+                        Ok(_) => {
+                            self.state.set_status(format!("Posted {}", rule.label()));
+                        }
+                        Err(e) => {
+                            self.state.recurring_failed.push(rule.id);
+                            self.state.set_status(format!(
+                                "Could not post {}: {}",
+                                rule.label(),
+                                e
+                            ));
+                        }
+                    }
+                    self.reload_after_posting().await?;
+                    self.run_scheduler().await?;
+                }
+                Some(PendingConfirm::DeleteGoal(id)) => match self.db.delete_savings_goal(id).await
+                {
+                    Ok(_) => {
+                        self.state.set_status("Savings goal deleted");
+                        self.refresh_goals().await?;
+                    }
+                    Err(e) => {
+                        self.state.set_status(format!("Error: {}", e));
+                    }
+                },
+                Some(PendingConfirm::ChangeCurrency(currency)) => {
+                    match self.db.set_currency(currency).await {
+                        Ok(_) => {
+                            self.state.currency = currency;
+                            self.state
+                                .set_status(format!("Ledger currency set to {}", currency.code));
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+                Some(PendingConfirm::DeleteImportProfile(id)) => {
+                    match self.db.delete_import_profile(id).await {
+                        Ok(_) => {
+                            self.state.set_status("Import profile deleted");
+                            self.state.import_profiles = self.db.get_import_profiles().await?;
+                            let last = self.state.import_profiles.len().saturating_sub(1);
+                            self.state.selected_import_profile =
+                                self.state.selected_import_profile.min(last);
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+                Some(PendingConfirm::CommitImport { account_id, .. }) => {
+                    self.commit_import(account_id).await?;
+                }
+                Some(PendingConfirm::DeleteRecurringRule(id)) => {
+                    match self.db.delete_recurring_rule(id).await {
+                        Ok(_) => {
+                            self.state.set_status("Recurring rule deleted");
+                            self.state.recurring_rules = self.db.get_recurring_rules().await?;
+                            let last = self.state.recurring_rules.len().saturating_sub(1);
+                            self.state.selected_rule = self.state.selected_rule.min(last);
+                        }
+                        Err(e) => {
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                }
+                None => {}
+            },
+            Action::ConfirmNo => match self.state.pending_confirm.take() {
+                Some(
+                    PendingConfirm::OverflowExpense { .. } | PendingConfirm::OverflowSplit { .. },
+                ) => {
+                    self.state.set_status("Expense cancelled");
+                }
+                Some(PendingConfirm::RecurringOccurrence(rule)) => {
+                    match self.db.skip_recurring_occurrence(&rule).await {
+                        Ok(_) => {
+                            self.state.set_status(format!(
+                                "Skipped {} due {}",
+                                rule.label(),
+                                rule.next_due.format("%Y-%m-%d")
+                            ));
+                        }
+                        Err(e) => {
+                            self.state.recurring_failed.push(rule.id);
+                            self.state.set_status(format!("Error: {}", e));
+                        }
+                    }
+                    self.state.recurring_rules = self.db.get_recurring_rules().await?;
+                    self.run_scheduler().await?;
+                }
+                _ => {}
+            },
             _ => {}
         }
         Ok(())
     }
+
+    /// Reload categories from the DB, keeping limit edits that haven't been saved yet
+    async fn reload_categories(&mut self) -> Result<()> {
+        let mut categories = self.db.get_categories().await?;
+        for cat in &mut categories {
+            if let Some(edited) = self.state.categories.iter().find(|c| c.id == cat.id) {
+                cat.limit_percentage = edited.limit_percentage;
+                cat.fixed_amount = edited.fixed_amount;
+            }
+        }
+        self.state.categories = categories;
+        self.state.balances = self.db.get_category_balances().await?;
+        Ok(())
+    }
+
+    async fn commit_expense(
+        &mut self,
+        category_id: i64,
+        category_name: &str,
+        amount: Decimal,
+        description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
+    ) -> Result<()> {
+        match self
+            .db
+            .create_transaction(category_id, amount, description, account_id, created_at)
+            .await
+        {
+            Ok(_) => {
+                self.state.set_status(format!(
+                    "Added {} expense to {}",
+                    self.state.currency.format(amount),
+                    category_name
+                ));
+                self.state.clear_inputs();
+                self.state.balances = self.db.get_category_balances().await?;
+                self.state.accounts = self.db.get_accounts().await?;
+                self.state.payees = self.db.get_payees().await?;
+            }
+            Err(e) => {
+                self.state.set_status(format!("Error: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hold back an Add Expense entry that looks like an expense already recorded, showing
+    /// the match on the form. Submitting the same entry again records it anyway.
+    async fn hold_duplicate(
+        &mut self,
+        amount: Decimal,
+        created_at: DateTime<Local>,
+        description: Option<&str>,
+    ) -> Result<bool> {
+        let existing = self
+            .db
+            .get_transactions_between(
+                created_at - ENTRY_DUPLICATE_WINDOW,
+                created_at + ENTRY_DUPLICATE_WINDOW,
+            )
+            .await?;
+        let Some(entry) = find_duplicate(
+            &existing,
+            amount,
+            created_at,
+            description,
+            ENTRY_DUPLICATE_WINDOW,
+        ) else {
+            return Ok(false);
+        };
+        let warned = self
+            .state
+            .duplicate_warning()
+            .and_then(|warned| warned.first())
+            .map(|t| t.id);
+        if warned == entry.first().map(|t| t.id) {
+            return Ok(false);
+        }
+
+        self.state.possible_duplicate = Some(DuplicateWarning {
+            entry: entry.to_vec(),
+            inputs: self.state.duplicate_inputs(),
+        });
+        self.state
+            .set_status("Possible duplicate: Enter records it anyway, Ctrl+X skips it");
+        Ok(true)
+    }
+
+    async fn commit_split(
+        &mut self,
+        lines: &[(i64, Decimal)],
+        description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
+    ) -> Result<()> {
+        match self
+            .db
+            .create_split_transaction(lines, description, account_id, created_at)
+            .await
+        {
+            Ok(_) => {
+                let total: Decimal = lines.iter().map(|(_, amount)| amount).sum();
+                self.state.set_status(format!(
+                    "Added {} expense split across {} categories",
+                    self.state.currency.format(total),
+                    lines.len()
+                ));
+                self.state.clear_inputs();
+                self.state.balances = self.db.get_category_balances().await?;
+                self.state.accounts = self.db.get_accounts().await?;
+                self.state.payees = self.db.get_payees().await?;
+            }
+            Err(e) => {
+                self.state.set_status(format!("Error: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the Ready rows of the import preview oldest first, each like an expense typed
+    /// on Add Expense. Rows that fail are marked and the rest still go in.
+    async fn commit_import(&mut self, account_id: i64) -> Result<()> {
+        let mut order: Vec<usize> = (0..self.state.import_rows.len())
+            .filter(|i| self.state.import_rows[*i].status == RowStatus::Ready)
+            .collect();
+        order.sort_by_key(|i| self.state.import_rows[*i].date);
+
+        let (mut imported, mut failed) = (0, 0);
+        for i in order {
+            let row = &self.state.import_rows[i];
+            let (Some(created_at), Some(category_id)) = (row.date, row.category_id) else {
+                continue;
+            };
+            let description = Some(row.description.clone()).filter(|d| !d.is_empty());
+            let posted = match &row.import_id {
+                Some(import_id) => {
+                    self.db
+                        .import_transaction(
+                            category_id,
+                            row.amount,
+                            description,
+                            account_id,
+                            created_at,
+                            import_id,
+                        )
+                        .await
+                }
+                None => self
+                    .db
+                    .create_transaction(
+                        category_id,
+                        row.amount,
+                        description,
+                        account_id,
+                        created_at,
+                    )
+                    .await
+                    .map(|_| true),
+            };
+            let status = match posted {
+                Ok(true) => {
+                    imported += 1;
+                    RowStatus::Imported
+                }
+                Ok(false) => RowStatus::AlreadyImported,
+                Err(e) => {
+                    failed += 1;
+                    RowStatus::Failed(e.to_string())
+                }
+            };
+            self.state.import_rows[i].status = status;
+        }
+
+        self.state.set_status(if failed > 0 {
+            format!(
+                "Imported {} expenses; {} failed, see the preview",
+                imported, failed
+            )
+        } else {
+            format!("Imported {} expenses", imported)
+        });
+        self.reload_after_posting().await
+    }
+
+    /// Post every recurring occurrence that is due. Rules that ask first get a confirmation
+    /// modal, one occurrence at a time, once the user is idle; the rest are posted right away.
+    async fn run_scheduler(&mut self) -> Result<()> {
+        let mut posted = 0;
+        while self.state.pending_confirm.is_none() {
+            let Some(rule) = self.state.due_rule(Local::now()).cloned() else {
+                break;
+            };
+            if rule.requires_confirmation {
+                self.state.pending_confirm = Some(PendingConfirm::RecurringOccurrence(rule));
+                break;
+            }
+
+            match self.db.post_recurring_occurrence(&rule).await {
+                Ok(_) => posted += 1,
+                Err(e) => {
+                    self.state.recurring_failed.push(rule.id);
+                    self.state
+                        .set_status(format!("Could not post {}: {}", rule.label(), e));
+                }
+            }
+            self.state.recurring_rules = self.db.get_recurring_rules().await?;
+        }
+
+        if posted > 0 {
+            self.state
+                .set_status(format!("Posted {} recurring entries", posted));
+            self.reload_after_posting().await?;
+        }
+        Ok(())
+    }
+
+    /// Refresh everything a posted expense or fund entry can change
+    async fn reload_after_posting(&mut self) -> Result<()> {
+        self.state.balances = self.db.get_category_balances().await?;
+        self.state.accounts = self.db.get_accounts().await?;
+        self.state.recurring_rules = self.db.get_recurring_rules().await?;
+        self.state.payees = self.db.get_payees().await?;
+        self.refresh_reports().await
+    }
+
+    /// Reports transaction list with no popup open, where row keys apply
+    fn reports_idle(&self) -> bool {
+        self.state.active_tab == ActiveTab::Reports
+            && self.state.editing_transaction.is_none()
+            && self.state.refunding.is_none()
+            && self.state.report_view == ReportView::Transactions
+    }
+
+    /// Point the refund form at `line` (one line of a split), offering what is left of it
+    fn fill_refund_form(&mut self, line: &crate::models::Transaction, refundable: Decimal) {
+        self.state.refunding = Some(line.id);
+        self.state.amount_input = self.state.currency.format_input(refundable);
+        self.state.description_input = match &line.description {
+            Some(desc) if !desc.is_empty() => format!("Refund: {}", desc),
+            _ => "Refund".to_string(),
+        };
+    }
+
+    /// Import tab listing profiles (no form, no preview), where profile keys apply
+    fn import_idle(&self) -> bool {
+        self.state.active_tab == ActiveTab::Import
+            && self.state.editing_import_profile.is_none()
+            && self.state.import_rows.is_empty()
+    }
+
+    /// Import tab showing a loaded statement, where row keys apply
+    fn previewing_import(&self) -> bool {
+        self.state.active_tab == ActiveTab::Import
+            && self.state.editing_import_profile.is_none()
+            && !self.state.import_rows.is_empty()
+    }
+
+    /// Dashboard with no goal form open, where goal keys apply
+    fn dashboard_idle(&self) -> bool {
+        self.state.active_tab == ActiveTab::Dashboard
+            && !self.state.creating_goal
+            && !self.state.funding_goal
+    }
+
+    /// Reload savings goals and the period length their projections use
+    async fn refresh_goals(&mut self) -> Result<()> {
+        self.state.savings_goals = self.db.get_savings_goals().await?;
+        self.state.period_length = self.db.get_typical_period_length().await?;
+        let last = self.state.savings_goals.len().saturating_sub(1);
+        self.state.selected_goal = self.state.selected_goal.min(last);
+        Ok(())
+    }
+
+    /// Reload the Reflection tab, keeping the selection in range
+    async fn refresh_reflections(&mut self) -> Result<()> {
+        self.state.reflections = self.db.get_reflections().await?;
+        let last = self.state.reflections.len().saturating_sub(1);
+        self.state.selected_reflection = self.state.selected_reflection.min(last);
+        Ok(())
+    }
+
+    /// Reload the Reports tab data for the selected date range
+    async fn refresh_reports(&mut self) -> Result<()> {
+        self.state.transactions = self
+            .db
+            .get_transactions(self.state.report_date_range, &self.state.search_terms())
+            .await?;
+        self.state.summary_stats = Some(
+            self.db
+                .get_summary_stats(self.state.report_date_range)
+                .await?,
+        );
+
+        let last = self.state.report_entries().len().checked_sub(1);
+        let selected = self.state.transaction_list.selected().unwrap_or(0);
+        self.state
+            .transaction_list
+            .select(last.map(|last| selected.min(last)));
+        Ok(())
+    }
+}
+
+/// Sum allocations per category, keeping the order each category first appears in
+fn merge_allocations(allocations: impl IntoIterator<Item = Allocation>) -> Vec<Allocation> {
+    let mut merged: Vec<Allocation> = Vec::new();
+    for a in allocations {
+        match merged.iter_mut().find(|m| m.category_id == a.category_id) {
+            Some(m) => m.amount += a.amount,
+            None => merged.push(a),
+        }
+    }
+    merged
+}
+
+/// Split `text` into spans, styling every case-insensitive occurrence of a search term
+fn highlight_terms(text: &str, terms: &[String], base: Style, hit: Style) -> Vec<Span<'static>> {
+    let lower: Vec<(usize, char)> = text
+        .char_indices()
+        .map(|(i, c)| (i, c.to_lowercase().next().unwrap_or(c)))
+        .collect();
+    let mut spans = Vec::new();
+    let (mut plain_start, mut pos) = (0, 0);
+    while pos < lower.len() {
+        let hit_len = terms
+            .iter()
+            .map(|term| term.chars().collect::<Vec<_>>())
+            .filter(|term| {
+                lower.len() - pos >= term.len()
+                    && term.iter().zip(&lower[pos..]).all(|(a, (_, b))| a == b)
+            })
+            .map(|term| term.len())
+            .max();
+        let Some(hit_len) = hit_len.filter(|len| *len > 0) else {
+            pos += 1;
+            continue;
+        };
+        let start = lower[pos].0;
+        let end = lower.get(pos + hit_len).map_or(text.len(), |(i, _)| *i);
+        if plain_start < start {
+            spans.push(Span::styled(text[plain_start..start].to_string(), base));
+        }
+        spans.push(Span::styled(text[start..end].to_string(), hit));
+        plain_start = end;
+        pos += hit_len;
+    }
+    if plain_start < text.len() {
+        spans.push(Span::styled(text[plain_start..].to_string(), base));
+    }
+    spans
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
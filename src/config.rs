@@ -0,0 +1,204 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{AppError, Result};
+
+/// Environment variable that overrides the database location
+const DB_ENV_VAR: &str = "EBISU_DB";
+
+/// Directory name used under the XDG base directories
+const APP_DIR: &str = "ebisu";
+
+/// Extension of ledger files picked up as profiles
+const PROFILE_EXT: &str = "db";
+
+/// Runtime configuration resolved at startup
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Location of the SQLite ledger
+    pub db_path: PathBuf,
+}
+
+/// On-disk configuration file (`$XDG_CONFIG_HOME/ebisu/config.json`)
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    db_path: Option<PathBuf>,
+}
+
+impl Config {
+    /// Resolve the configuration from CLI flags, environment and config file.
+    ///
+    /// Precedence: `--db <path>` > `EBISU_DB` > config file > `$XDG_DATA_HOME/ebisu/ebisu.db`.
+    pub fn load() -> Result<Self> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let file = ConfigFile::load()?;
+
+        let db_path = match db_flag(&args)? {
+            Some(path) => path,
+            None => match env::var_os(DB_ENV_VAR).filter(|v| !v.is_empty()) {
+                Some(path) => PathBuf::from(path),
+                None => match file.db_path {
+                    Some(path) => path,
+                    None => data_dir()?.join("ebisu.db"),
+                },
+            },
+        };
+
+        let db_path = expand_tilde(&db_path);
+        if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Ok(Self { db_path })
+    }
+
+    /// Ledger files offered as profiles: every `*.db` next to the open ledger, plus the
+    /// open ledger itself. Sorted by name.
+    pub fn profiles(&self) -> Result<Vec<PathBuf>> {
+        let mut profiles = vec![self.db_path.clone()];
+        if let Ok(entries) = std::fs::read_dir(self.profile_dir()) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == PROFILE_EXT)
+                    && !profiles.contains(&path)
+                {
+                    profiles.push(path);
+                }
+            }
+        }
+        profiles.sort_by_key(|p| profile_name(p).to_lowercase());
+        Ok(profiles)
+    }
+
+    /// Location of a new profile called `name`, next to the open ledger
+    pub fn profile_path(&self, name: &str) -> Result<PathBuf> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Profile name can't be empty".into()));
+        }
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(AppError::Validation(format!(
+                "{} is not a valid profile name",
+                name
+            )));
+        }
+
+        let path = self.profile_dir().join(format!("{}.{}", name, PROFILE_EXT));
+        if path.exists() {
+            return Err(AppError::Validation(format!(
+                "A profile named {} already exists",
+                name
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Fresh folder for a CSV export from the TUI: `exports/<profile>-<timestamp>` next to
+    /// the open ledger
+    pub fn export_dir(&self) -> PathBuf {
+        self.profile_dir().join("exports").join(format!(
+            "{}-{}",
+            profile_name(&self.db_path),
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    }
+
+    fn profile_dir(&self) -> PathBuf {
+        match self.db_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+}
+
+/// Display name of a profile: the ledger file name without extension
+pub fn profile_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+impl ConfigFile {
+    fn load() -> Result<Self> {
+        let Ok(dir) = config_dir() else {
+            return Ok(Self::default());
+        };
+        let path = dir.join("config.json");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Extract the value of `--db <path>` or `--db=<path>` from the argument list
+fn db_flag(args: &[String]) -> Result<Option<PathBuf>> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--db" {
+            return iter
+                .next()
+                .map(|p| Some(PathBuf::from(p)))
+                .ok_or_else(|| AppError::Config("--db requires a path".into()));
+        }
+        if let Some(path) = arg.strip_prefix("--db=") {
+            return Ok(Some(PathBuf::from(path)));
+        }
+    }
+    Ok(None)
+}
+
+/// The arguments left once `--db <path>`/`--db=<path>` is taken out, wherever it was given:
+/// the subcommand and its options
+pub fn without_db_flag(args: &[String]) -> Vec<String> {
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--db" {
+            iter.next();
+        } else if !arg.starts_with("--db=") {
+            rest.push(arg.clone());
+        }
+    }
+    rest
+}
+
+fn home_dir() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::Config("HOME is not set".into()))
+}
+
+/// `$XDG_DATA_HOME/ebisu`, falling back to `~/.local/share/ebisu`
+fn data_dir() -> Result<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".local").join("share"),
+    };
+    Ok(base.join(APP_DIR))
+}
+
+/// `$XDG_CONFIG_HOME/ebisu`, falling back to `~/.config/ebisu`
+fn config_dir() -> Result<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".config"),
+    };
+    Ok(base.join(APP_DIR))
+}
+
+/// `~/...` relative to the home directory; other paths unchanged
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home_dir()
+            .map(|h| h.join(rest))
+            .unwrap_or(path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
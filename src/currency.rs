@@ -0,0 +1,147 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::str::FromStr;
+
+/// Display and input rules for a ledger currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Currency {
+    /// ISO 4217 code, stored in the `settings` table
+    pub code: &'static str,
+    pub symbol: &'static str,
+    pub thousands_separator: char,
+    pub decimal_separator: char,
+    /// Digits after the decimal separator (0 for IDR and JPY)
+    pub minor_units: u32,
+}
+
+/// Currencies a ledger can be kept in. The first entry is the default.
+pub const CURRENCIES: &[Currency] = &[
+    Currency {
+        code: "IDR",
+        symbol: "Rp ",
+        thousands_separator: '.',
+        decimal_separator: ',',
+        minor_units: 0,
+    },
+    Currency {
+        code: "USD",
+        symbol: "$",
+        thousands_separator: ',',
+        decimal_separator: '.',
+        minor_units: 2,
+    },
+    Currency {
+        code: "JPY",
+        symbol: "¥",
+        thousands_separator: ',',
+        decimal_separator: '.',
+        minor_units: 0,
+    },
+    Currency {
+        code: "EUR",
+        symbol: "€",
+        thousands_separator: '.',
+        decimal_separator: ',',
+        minor_units: 2,
+    },
+    Currency {
+        code: "SGD",
+        symbol: "S$",
+        thousands_separator: ',',
+        decimal_separator: '.',
+        minor_units: 2,
+    },
+    Currency {
+        code: "MYR",
+        symbol: "RM ",
+        thousands_separator: ',',
+        decimal_separator: '.',
+        minor_units: 2,
+    },
+];
+
+impl Default for Currency {
+    fn default() -> Self {
+        CURRENCIES[0]
+    }
+}
+
+impl Currency {
+    pub fn from_code(code: &str) -> Option<Self> {
+        CURRENCIES
+            .iter()
+            .find(|c| c.code.eq_ignore_ascii_case(code))
+            .copied()
+    }
+
+    /// The currency after this one in `CURRENCIES`, wrapping around
+    pub fn next(&self) -> Self {
+        let index = CURRENCIES.iter().position(|c| c == self).unwrap_or(0);
+        CURRENCIES[(index + 1) % CURRENCIES.len()]
+    }
+
+    /// Amount with symbol, e.g. `Rp 1.000.000`, `$1,234.50`, `-¥500`
+    pub fn format(&self, amount: Decimal) -> String {
+        let number = self.format_number(amount);
+        match number.strip_prefix('-') {
+            Some(abs) => format!("-{}{}", self.symbol, abs),
+            None => format!("{}{}", self.symbol, number),
+        }
+    }
+
+    /// Amount without symbol, grouped and rounded to the minor unit (e.g. `1.000.000`)
+    pub fn format_number(&self, amount: Decimal) -> String {
+        let rounded =
+            amount.round_dp_with_strategy(self.minor_units, RoundingStrategy::MidpointAwayFromZero);
+        let is_negative = rounded.is_sign_negative() && !rounded.is_zero();
+        let text = rounded.abs().to_string();
+        let (int_part, frac_part) = text.split_once('.').unwrap_or((&text, ""));
+
+        let mut grouped = String::new();
+        for (i, c) in int_part.chars().rev().enumerate() {
+            if i > 0 && i % 3 == 0 {
+                grouped.push(self.thousands_separator);
+            }
+            grouped.push(c);
+        }
+        let mut result: String = grouped.chars().rev().collect();
+
+        if self.minor_units > 0 {
+            result.push(self.decimal_separator);
+            result.push_str(&format!(
+                "{:0<width$}",
+                frac_part,
+                width = self.minor_units as usize
+            ));
+        }
+
+        if is_negative {
+            format!("-{}", result)
+        } else {
+            result
+        }
+    }
+
+    /// Plain editable form of an amount (no grouping), e.g. `1234,5` for EUR
+    pub fn format_input(&self, amount: Decimal) -> String {
+        amount
+            .normalize()
+            .to_string()
+            .replace('.', &self.decimal_separator.to_string())
+    }
+
+    /// Whether `c` may be typed into an amount field
+    pub fn accepts_input_char(&self, c: char) -> bool {
+        c.is_ascii_digit() || (self.minor_units > 0 && c == self.decimal_separator)
+    }
+
+    /// Parse a typed amount using this currency's decimal separator. Rejects more
+    /// fractional digits than the currency has.
+    pub fn parse_amount(&self, input: &str) -> Option<Decimal> {
+        let normalized = input
+            .trim()
+            .replace(self.decimal_separator, ".")
+            .replace(' ', "");
+        let amount = Decimal::from_str(&normalized).ok()?;
+        (amount.normalize().scale() <= self.minor_units).then_some(amount)
+    }
+}
@@ -1,19 +1,31 @@
+use crate::currency::Currency;
 use crate::error::{AppError, Result};
-use crate::models::{Category, CategoryBalance, CategoryName};
-use chrono::Local;
+use crate::migrations;
+use crate::models::{
+    Account, AccountKind, Allocation, BudgetPeriod, Cadence, Category, CategoryBalance, DateRange,
+    Fund, ImportProfile, Payee, RecurringKind, RecurringRule, Reflection, SavingsGoal,
+    SignConvention, SourceTotal, Transfer, allocate_income, match_payee, overflow_chain,
+    parse_tags, typical_period_length, validate_overflow_chains,
+};
+use chrono::{DateTime, Local, TimeDelta};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
+use std::collections::HashSet;
+use std::path::Path;
 use std::str::FromStr;
 
+/// Upper bound for open-ended RFC3339 range queries; sorts after any timestamp. Must not look
+/// numeric, or the DATETIME column affinity turns it into a number that sorts first.
+const OPEN_END: &str = "9999-12-31T23:59:59Z";
+
 #[derive(Debug)]
 pub struct DB {
     conn: libsql::Connection,
 }
 
 impl DB {
-    #[allow(dead_code)]
-    pub async fn new() -> Result<Self> {
-        let db = libsql::Builder::new_local("ebisu.db").build().await?;
+    pub async fn new(path: &Path) -> Result<Self> {
+        let db = libsql::Builder::new_local(path).build().await?;
         let conn = db.connect()?;
 
         let db_instance = Self { conn };
@@ -23,12 +35,40 @@ impl DB {
     }
 
     async fn initialize_schema(&self) -> Result<()> {
-        let schema = include_str!("../schema.sql");
-        self.conn.execute_batch(schema).await?;
+        let from = migrations::run(&self.conn).await?;
+        if from < migrations::PAYEES_VERSION {
+            self.backfill_payees().await?;
+        }
         self.seed_categories().await?;
         Ok(())
     }
 
+    /// Give the expenses recorded before payees existed the payee `sync_payee` would have,
+    /// oldest first, so later descriptions file under the payees earlier ones registered
+    async fn backfill_payees(&self) -> Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+        let mut rows = tx
+            .query(
+                "SELECT id, description FROM transactions
+                 WHERE refund_of_id IS NULL AND payee_id IS NULL AND description <> ''
+                 ORDER BY id",
+                (),
+            )
+            .await?;
+        let mut expenses: Vec<(i64, String)> = Vec::new();
+        while let Some(row) = rows.next().await? {
+            expenses.push((row.get(0)?, row.get(1)?));
+        }
+        for (id, description) in expenses {
+            sync_payee(&tx, id, &description).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     async fn seed_categories(&self) -> Result<()> {
         let count: i64 = self
             .conn
@@ -47,36 +87,36 @@ impl DB {
             // Savings
 
             let savings_id = self
-                .insert_category(
-                    CategoryName::Savings,
-                    Decimal::from_f64(50.0).unwrap(),
-                    None,
-                )
+                .insert_category("Savings", Decimal::from_f64(50.0).unwrap(), None, true)
                 .await?;
             let unexpected_id = self
                 .insert_category(
-                    CategoryName::Unexpected,
+                    "Unexpected",
                     Decimal::from_f64(10.0).unwrap(),
                     Some(savings_id),
+                    false,
                 )
                 .await?;
 
             self.insert_category(
-                CategoryName::Needs,
+                "Needs",
                 Decimal::from_f64(30.0).unwrap(),
                 Some(unexpected_id),
+                false,
             )
             .await?;
             self.insert_category(
-                CategoryName::Wants,
+                "Wants",
                 Decimal::from_f64(5.0).unwrap(),
                 Some(unexpected_id),
+                false,
             )
             .await?;
             self.insert_category(
-                CategoryName::Culture,
+                "Culture",
                 Decimal::from_f64(5.0).unwrap(),
                 Some(unexpected_id),
+                false,
             )
             .await?;
         }
@@ -85,19 +125,26 @@ impl DB {
 
     async fn insert_category(
         &self,
-        name: CategoryName,
+        name: &str,
         limit: Decimal,
         overflow_to: Option<i64>,
+        is_savings: bool,
     ) -> Result<i64> {
+        let is_savings = if is_savings { "1" } else { "0" };
         let (sql, params): (&str, Vec<String>) = if let Some(oid) = overflow_to {
             (
-                "INSERT INTO categories (name, limit_percentage, overflow_to_id) VALUES (?, ?, ?) RETURNING id",
-                vec![name.to_string(), limit.to_string(), oid.to_string()],
+                "INSERT INTO categories (name, limit_percentage, overflow_to_id, is_savings) VALUES (?, ?, ?, ?) RETURNING id",
+                vec![
+                    name.to_string(),
+                    limit.to_string(),
+                    oid.to_string(),
+                    is_savings.to_string(),
+                ],
             )
         } else {
             (
-                "INSERT INTO categories (name, limit_percentage, overflow_to_id) VALUES (?, ?, NULL) RETURNING id",
-                vec![name.to_string(), limit.to_string()],
+                "INSERT INTO categories (name, limit_percentage, overflow_to_id, is_savings) VALUES (?, ?, NULL, ?) RETURNING id",
+                vec![name.to_string(), limit.to_string(), is_savings.to_string()],
             )
         };
 
@@ -126,7 +173,7 @@ impl DB {
         let mut rows = self
             .conn
             .query(
-                "SELECT id, name, limit_percentage, overflow_to_id FROM categories",
+                "SELECT id, name, limit_percentage, fixed_amount, overflow_to_id, is_savings, archived FROM categories",
                 (),
             )
             .await?;
@@ -138,16 +185,207 @@ impl DB {
 
             categories.push(Category {
                 id: cat_row.id,
-                name: CategoryName::from_str(&cat_row.name).map_err(|_| {
-                    AppError::Validation(format!("Invalid category name: {}", cat_row.name))
-                })?,
+                name: cat_row.name,
                 limit_percentage: cat_row.limit_percentage,
+                fixed_amount: cat_row
+                    .fixed_amount
+                    .and_then(|amount| Decimal::from_str(&amount).ok()),
                 overflow_to_id: cat_row.overflow_to_id,
+                is_savings: cat_row.is_savings != 0,
+                archived: cat_row.archived != 0,
             });
         }
         Ok(categories)
     }
 
+    /// Add a user-defined category, overflowing into the Savings category by default.
+    pub async fn create_category(&self, name: &str) -> Result<i64> {
+        let name = self.validate_category_name(name, None).await?;
+        let savings_id = self
+            .get_categories()
+            .await?
+            .into_iter()
+            .find(|c| c.is_savings)
+            .map(|c| c.id);
+        self.insert_category(&name, Decimal::ZERO, savings_id, false)
+            .await
+    }
+
+    pub async fn rename_category(&self, category_id: i64, name: &str) -> Result<()> {
+        let name = self.validate_category_name(name, Some(category_id)).await?;
+        self.conn
+            .execute(
+                "UPDATE categories SET name = ? WHERE id = ?",
+                [name, category_id.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Archived categories keep their history but get no allocation and can't take expenses.
+    /// Archiving hands the category's percentage to Savings; a restored one starts at 0%.
+    pub async fn set_category_archived(&self, category_id: i64, archived: bool) -> Result<()> {
+        let categories = self.get_categories().await?;
+        let cat = categories
+            .iter()
+            .find(|c| c.id == category_id)
+            .ok_or(AppError::CategoryNotFound(category_id.to_string()))?;
+        if archived && cat.is_savings {
+            return Err(AppError::Validation(
+                "The Savings category can't be archived".into(),
+            ));
+        }
+        if cat.archived == archived {
+            return Ok(());
+        }
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+        if archived && cat.fixed_amount.is_none() && cat.limit_percentage > Decimal::ZERO {
+            tx.execute(
+                "UPDATE categories SET limit_percentage = limit_percentage + ? WHERE is_savings = 1",
+                [cat.limit_percentage.to_string()],
+            )
+            .await?;
+        }
+        tx.execute(
+            "UPDATE categories SET archived = ?, limit_percentage = 0 WHERE id = ?",
+            [i64::from(archived), category_id],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Trimmed, non-empty and unique (case-insensitively) among other categories
+    async fn validate_category_name(&self, name: &str, except_id: Option<i64>) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Category name can't be empty".into()));
+        }
+        let taken = self
+            .get_categories()
+            .await?
+            .iter()
+            .any(|c| Some(c.id) != except_id && c.name.eq_ignore_ascii_case(name));
+        if taken {
+            return Err(AppError::Validation(format!(
+                "A category named {} already exists",
+                name
+            )));
+        }
+        Ok(name.to_string())
+    }
+
+    /// Persist limits and overflow targets for all categories at once, refusing broken chains,
+    /// percentages that don't total 100% and fixed amounts the latest income couldn't cover.
+    pub async fn save_category_settings(&self, categories: &[Category]) -> Result<()> {
+        validate_overflow_chains(categories)?;
+        let percentages: Vec<Decimal> = categories
+            .iter()
+            .filter(|c| !c.archived && c.fixed_amount.is_none())
+            .map(|c| c.limit_percentage)
+            .collect();
+        let percent_total: Decimal = percentages.iter().sum();
+        if !percentages.is_empty() && percent_total != Decimal::ONE_HUNDRED {
+            return Err(AppError::Validation(format!(
+                "Percentages add up to {}%, they must total 100%",
+                percent_total.normalize()
+            )));
+        }
+        if categories
+            .iter()
+            .any(|c| c.fixed_amount.is_some_and(|f| f < Decimal::ZERO))
+        {
+            return Err(AppError::Validation(
+                "Fixed amounts can't be negative".into(),
+            ));
+        }
+        let fixed_total: Decimal = categories
+            .iter()
+            .filter(|c| !c.archived)
+            .filter_map(|c| c.fixed_amount)
+            .sum();
+        if let Some(income) = self.get_latest_income().await?
+            && fixed_total > income
+        {
+            return Err(AppError::Validation(format!(
+                "Fixed budgets need {} but the latest income is only {}",
+                fixed_total, income
+            )));
+        }
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+        for cat in categories {
+            tx.execute(
+                "UPDATE categories SET limit_percentage = ?, fixed_amount = ?, overflow_to_id = ? WHERE id = ?",
+                libsql::params![
+                    cat.limit_percentage.to_string(),
+                    cat.fixed_amount.map(|f| f.to_string()),
+                    cat.overflow_to_id,
+                    cat.id
+                ],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Amount of the newest fund entry, the income fixed budgets are checked against
+    pub async fn get_latest_income(&self) -> Result<Option<Decimal>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT amount FROM funds ORDER BY added_at DESC, id DESC LIMIT 1",
+                (),
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(decimal_column(&row, 0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let mut rows = self
+            .conn
+            .query("SELECT value FROM settings WHERE key = ?", [key])
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                [key, value],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Currency this ledger is kept in
+    pub async fn get_currency(&self) -> Result<Currency> {
+        match self.get_setting("currency").await? {
+            Some(code) => Currency::from_code(&code)
+                .ok_or_else(|| AppError::Config(format!("Unknown currency: {}", code))),
+            None => Ok(Currency::default()),
+        }
+    }
+
+    pub async fn set_currency(&self, currency: Currency) -> Result<()> {
+        self.set_setting("currency", currency.code).await
+    }
+
     pub async fn update_category_limit(&self, category_id: i64, limit: Decimal) -> Result<()> {
         self.conn
             .execute(
@@ -159,16 +397,73 @@ impl DB {
     }
 
     // FUND ALLOCATION LOGIC
-    #[allow(dead_code)]
-    pub async fn add_funds(&self, amount: Decimal) -> Result<()> {
-        let balances = self.get_category_balances().await?;
+    /// Record income received at `added_at`.
+    ///
+    /// The newest entry starts a budget period: leftovers roll into Savings, balances are
+    /// re-allocated, and expenses already logged after `added_at` move into the new period.
+    /// An entry dated inside an earlier, closed period goes straight to Savings, where that
+    /// period's leftover was swept.
+    ///
+    /// Returns how far the moved expenses overdrew Savings: they were already paid, so a new
+    /// period too small for them is reported rather than rejected.
+    pub async fn add_funds(
+        &self,
+        amount: Decimal,
+        account_id: i64,
+        source: Option<String>,
+        note: Option<String>,
+        added_at: DateTime<Local>,
+    ) -> Result<Decimal> {
+        self.insert_funds(amount, account_id, source, note, added_at, None)
+            .await
+    }
+
+    /// `add_funds`, also advancing a recurring rule's `next_due` in the same transaction
+    async fn insert_funds(
+        &self,
+        amount: Decimal,
+        account_id: i64,
+        source: Option<String>,
+        note: Option<String>,
+        added_at: DateTime<Local>,
+        rule: Option<&RecurringRule>,
+    ) -> Result<Decimal> {
+        validate_not_future(added_at)?;
+        let period_start = self.current_period_start().await?;
+        let categories = self.get_categories().await?;
+        let mut balances = self.get_category_balances().await?;
+        let closed_period = !in_period(added_at, period_start);
+
+        // Take expenses dated after a backdated payday out of the old period first, so they
+        // don't count against its rollover
+        let moved = if closed_period {
+            Vec::new()
+        } else {
+            self.get_transactions_since(added_at).await?
+        };
+        for transaction in &moved {
+            for (cat_id, amount) in reversal_for(&categories, &balances, transaction) {
+                if let Some(bal) = balances.iter_mut().find(|b| b.category_id == cat_id) {
+                    bal.spent -= amount;
+                }
+            }
+        }
+
+        // Fixed budgets come off the top, scaled down when the income can't cover them
+        let allocations = if closed_period {
+            Vec::new()
+        } else {
+            allocate_income(&categories, amount)
+        };
 
         let mut total_rollover = Decimal::ZERO;
-        for bal in &balances {
-            if bal.category_name != CategoryName::Savings {
-                let remaining = bal.allocated - bal.spent;
-                if remaining > Decimal::ZERO {
-                    total_rollover += remaining;
+        if !closed_period {
+            for bal in &balances {
+                if !bal.is_savings {
+                    let remaining = bal.allocated - bal.spent;
+                    if remaining > Decimal::ZERO {
+                        total_rollover += remaining;
+                    }
                 }
             }
         }
@@ -180,33 +475,41 @@ impl DB {
             .await?;
 
         tx.execute(
-            "INSERT INTO funds (amount, remaining_balance_rolled, added_at) VALUES (?, ?, ?)",
-            [
+            "INSERT INTO funds (amount, remaining_balance_rolled, added_at, account_id, source, note) VALUES (?, ?, ?, ?, ?, ?)",
+            libsql::params![
                 amount.to_string(),
                 total_rollover.to_string(),
-                Local::now().to_rfc3339(),
+                added_at.to_rfc3339(),
+                account_id,
+                source.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+                note
             ],
         )
         .await?;
 
-        let categories = self.get_categories().await?;
-        for cat in categories {
-            let portion = amount * (cat.limit_percentage / Decimal::from_i32(100).unwrap());
+        if let Some(rule) = rule {
+            advance_rule(&tx, rule).await?;
+        }
+        if closed_period {
+            adjust_savings(&tx, &mut balances, amount).await?;
+            tx.commit().await?;
+            return Ok(Decimal::ZERO);
+        }
+
+        for (cat, (_, portion)) in categories.iter().zip(allocations) {
             let mut new_allocation = portion;
             let mut new_available = portion;
 
-            if cat.name == CategoryName::Savings {
+            let Some(bal) = balances.iter_mut().find(|b| b.category_id == cat.id) else {
+                continue;
+            };
+            if cat.is_savings {
                 new_allocation += total_rollover;
-                let current_savings_bal = balances
-                    .iter()
-                    .find(|b| b.category_name == CategoryName::Savings);
-                let previous_savings = if let Some(b) = current_savings_bal {
-                    b.available - b.spent
-                } else {
-                    Decimal::ZERO
-                };
-                new_available = previous_savings + new_allocation;
+                new_available = bal.available - bal.spent + new_allocation;
             }
+            bal.available = new_available;
+            bal.allocated = new_allocation;
+            bal.spent = Decimal::ZERO;
 
             tx.execute(
                 "UPDATE category_balances SET available = ?, allocated = ?, spent = '0', last_updated = CURRENT_TIMESTAMP WHERE category_id = ?",
@@ -214,12 +517,67 @@ impl DB {
             ).await?;
         }
 
+        // Charge the moved expenses to the new period, oldest first. Refunds credit their
+        // expense's new allocation if it moved too, otherwise Savings.
+        let mut replanned: Vec<crate::models::Transaction> = Vec::new();
+        let mut overdrawn = Decimal::ZERO;
+        for transaction in &moved {
+            let updates = match transaction.refund_of_id {
+                Some(original_id) => match replanned.iter().find(|t| t.id == original_id) {
+                    Some(original) => {
+                        let refunded = replanned
+                            .iter()
+                            .filter(|t| t.refund_of_id == Some(original_id))
+                            .flat_map(|t| &t.allocations)
+                            .map(|a| (a.category_id, -a.amount))
+                            .collect::<Vec<_>>();
+                        plan_refund(
+                            &categories,
+                            &balances,
+                            original,
+                            &refunded,
+                            -transaction.amount,
+                        )?
+                    }
+                    None => savings_credit(&balances, -transaction.amount)?,
+                },
+                None => {
+                    let (updates, shortfall) = plan_moved_expense(
+                        &categories,
+                        &balances,
+                        transaction.category_id,
+                        transaction.amount,
+                    )?;
+                    overdrawn += shortfall;
+                    updates
+                }
+            };
+            replanned.push(crate::models::Transaction {
+                allocations: updates
+                    .iter()
+                    .map(|(category_id, amount)| Allocation {
+                        category_id: *category_id,
+                        category_name: None,
+                        amount: *amount,
+                    })
+                    .collect(),
+                ..transaction.clone()
+            });
+            apply_spent(&tx, &mut balances, &updates, Decimal::ONE).await?;
+            tx.execute(
+                "DELETE FROM transaction_allocations WHERE transaction_id = ?",
+                [transaction.id],
+            )
+            .await?;
+            insert_allocations(&tx, transaction.id, &updates).await?;
+        }
+
         tx.commit().await?;
-        Ok(())
+        Ok(overdrawn)
     }
 
     #[allow(dead_code)]
-    pub async fn get_category_by_name(&self, name: CategoryName) -> Result<Category> {
+    pub async fn get_category_by_name(&self, name: &str) -> Result<Category> {
         let cats = self.get_categories().await?;
         cats.into_iter()
             .find(|c| c.name == name)
@@ -231,7 +589,7 @@ impl DB {
         let rows = self
             .conn
             .query(
-                r#"SELECT cb.category_id, c.name as category_name, cb.available, cb.allocated, cb.spent, cb.last_updated 
+                r#"SELECT cb.category_id, c.name as category_name, c.is_savings, cb.available, cb.allocated, cb.spent, cb.last_updated 
                 FROM category_balances cb 
                 JOIN categories c ON cb.category_id = c.id"#,
                 (),
@@ -247,8 +605,8 @@ impl DB {
 
             balances.push(CategoryBalance {
                 category_id: bal_row.category_id,
-                category_name: CategoryName::from_str(&bal_row.category_name)
-                    .unwrap_or(CategoryName::Unexpected),
+                category_name: bal_row.category_name,
+                is_savings: bal_row.is_savings != 0,
                 available: bal_row.available,
                 allocated: bal_row.allocated,
                 spent: bal_row.spent,
@@ -258,182 +616,2199 @@ impl DB {
         Ok(balances)
     }
 
-    #[allow(dead_code)]
+    /// Record an expense made at `created_at`. Inside the current period it is deducted along
+    /// the overflow chain; in a closed period it comes out of Savings, which holds what that
+    /// period had left.
     pub async fn create_transaction(
         &self,
-        category_name: CategoryName,
+        category_id: i64,
         amount: Decimal,
         description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
     ) -> Result<()> {
-        let categories = self.get_categories().await?;
-        let target_cat = categories
-            .iter()
-            .find(|c| c.name == category_name)
-            .ok_or(AppError::CategoryNotFound(category_name.to_string()))?;
+        self.create_split_transaction(
+            &[(category_id, amount)],
+            description,
+            account_id,
+            created_at,
+        )
+        .await
+    }
 
-        let mut balances = self.get_category_balances().await?;
+    /// Record one receipt split across categories, e.g. groceries under Needs and snacks under
+    /// Wants. Each `(category_id, amount)` line is deducted like `create_transaction`, in
+    /// order, and either every line is written or none is. Lines share a `split_id`.
+    pub async fn create_split_transaction(
+        &self,
+        lines: &[(i64, Decimal)],
+        description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
+    ) -> Result<()> {
+        self.insert_expense(lines, description, account_id, created_at, None, None)
+            .await
+            .map(|_| ())
+    }
 
-        let mut remaining_amount_to_cover = amount;
-        let mut current_cat_id = target_cat.id;
-        let mut updates: Vec<(i64, Decimal)> = Vec::new();
+    /// Record an expense from a bank statement like `create_transaction`, keeping the
+    /// statement's transaction id. Returns false, recording nothing, when an expense with that
+    /// id was imported before.
+    pub async fn import_transaction(
+        &self,
+        category_id: i64,
+        amount: Decimal,
+        description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
+        import_id: &str,
+    ) -> Result<bool> {
+        self.insert_expense(
+            &[(category_id, amount)],
+            description,
+            account_id,
+            created_at,
+            Some(import_id),
+            None,
+        )
+        .await
+    }
 
-        loop {
-            let cat_bal = balances
-                .iter_mut()
-                .find(|b| b.category_id == current_cat_id)
-                .ok_or(AppError::Config("Balance sync error".into()))?;
-            let current_remaining = cat_bal.available - cat_bal.spent;
-
-            if current_remaining >= remaining_amount_to_cover {
-                updates.push((current_cat_id, remaining_amount_to_cover));
-                // remaining_amount_to_cover = Decimal::ZERO; // Removed unnecessary assignment
-                break;
-            } else {
-                if current_remaining > Decimal::ZERO {
-                    updates.push((current_cat_id, current_remaining));
-                    remaining_amount_to_cover -= current_remaining;
-                }
+    /// Statement transaction ids already recorded, for marking them in the import preview
+    pub async fn get_import_ids(&self) -> Result<HashSet<String>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT import_id FROM transactions WHERE import_id IS NOT NULL",
+                (),
+            )
+            .await?;
 
-                let current_cat_config =
-                    categories.iter().find(|c| c.id == current_cat_id).unwrap();
-                if let Some(next_id) = current_cat_config.overflow_to_id {
-                    current_cat_id = next_id;
-                } else {
-                    return Err(AppError::InsufficientFunds);
-                }
-            }
+        let mut ids = HashSet::new();
+        while let Some(row) = rows.next().await? {
+            ids.insert(row.get(0)?);
+        }
+        Ok(ids)
+    }
+
+    /// Shared by `create_split_transaction`, `import_transaction` and
+    /// `post_recurring_occurrence`. The import id goes on the first line; false when it is
+    /// already taken. A recurring rule's `next_due` is advanced in the same transaction.
+    async fn insert_expense(
+        &self,
+        lines: &[(i64, Decimal)],
+        description: Option<String>,
+        account_id: i64,
+        created_at: DateTime<Local>,
+        import_id: Option<&str>,
+        rule: Option<&RecurringRule>,
+    ) -> Result<bool> {
+        validate_not_future(created_at)?;
+        if lines.is_empty() {
+            return Err(AppError::Validation(
+                "An expense needs at least one line".into(),
+            ));
+        }
+        let categories = self.get_categories().await?;
+        for (category_id, _) in lines {
+            validate_expense_category(&categories, *category_id)?;
         }
 
+        let mut balances = self.get_category_balances().await?;
+        let closed_period = !in_period(created_at, self.current_period_start().await?);
+
         // Use transaction for atomic writes
         let tx = self
             .conn
             .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
             .await?;
 
-        tx.execute(
-            "INSERT INTO transactions (category_id, amount, description, overflow_from_id, created_at) VALUES (?, ?, ?, NULL, ?)",
-            [
-                target_cat.id.to_string(),
-                amount.to_string(),
-                description.unwrap_or_default(),
-                Local::now().to_rfc3339(),
-            ]
-        ).await?;
+        if let Some(import_id) = import_id
+            && tx
+                .query(
+                    "SELECT 1 FROM transactions WHERE import_id = ?",
+                    [import_id],
+                )
+                .await?
+                .next()
+                .await?
+                .is_some()
+        {
+            return Ok(false);
+        }
 
-        for (cat_id, deducted) in updates {
-            tx.execute(
-                "UPDATE category_balances SET spent = spent + ? WHERE category_id = ?",
-                [deducted.to_string(), cat_id.to_string()],
+        let mut split_id: Option<i64> = None;
+        for (category_id, amount) in lines {
+            // Planned against the balances the previous lines left
+            let updates = if closed_period {
+                plan_from_savings(&balances, *amount)?
+            } else {
+                plan_deductions(&categories, &balances, *category_id, *amount)?
+            };
+
+            let transaction_id: i64 = tx
+                .query(
+                    "INSERT INTO transactions (category_id, amount, description, overflow_from_id, created_at, account_id, split_id, import_id) VALUES (?, ?, ?, NULL, ?, ?, ?, ?) RETURNING id",
+                    libsql::params![
+                        *category_id,
+                        amount.to_string(),
+                        description.clone().unwrap_or_default(),
+                        created_at.to_rfc3339(),
+                        account_id,
+                        split_id,
+                        import_id.filter(|_| split_id.is_none())
+                    ],
+                )
+                .await?
+                .next()
+                .await?
+                .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+                .get(0)?;
+            if split_id.is_none() && lines.len() > 1 {
+                tx.execute(
+                    "UPDATE transactions SET split_id = id WHERE id = ?",
+                    [transaction_id],
+                )
+                .await?;
+                split_id = Some(transaction_id);
+            }
+
+            if closed_period {
+                adjust_savings(&tx, &mut balances, -*amount).await?;
+            } else {
+                apply_spent(&tx, &mut balances, &updates, Decimal::ONE).await?;
+            }
+            insert_allocations(&tx, transaction_id, &updates).await?;
+            sync_tags(
+                &tx,
+                transaction_id,
+                description.as_deref().unwrap_or_default(),
+            )
+            .await?;
+            sync_payee(
+                &tx,
+                transaction_id,
+                description.as_deref().unwrap_or_default(),
             )
             .await?;
         }
+        if let Some(rule) = rule {
+            advance_rule(&tx, rule).await?;
+        }
 
         tx.commit().await?;
-        Ok(())
+        Ok(true)
     }
 
-    pub async fn get_summary_stats(&self) -> Result<crate::models::SummaryStats> {
-        // Total funds added - CAST to TEXT to ensure safe string retrieval for Decimal parsing
-        let total_funds: Option<String> = self
-            .conn
-            .query("SELECT CAST(SUM(amount) AS TEXT) FROM funds", ())
-            .await?
-            .next()
-            .await?
-            .and_then(|row| row.get(0).ok());
-
-        let total_funds = total_funds
-            .and_then(|s| Decimal::from_str(&s).ok())
-            .unwrap_or(Decimal::ZERO);
-
-        // Total spent - CAST to TEXT
-        let total_spent: Option<String> = self
-            .conn
-            .query("SELECT CAST(SUM(spent) AS TEXT) FROM category_balances", ())
-            .await?
-            .next()
+    /// Dry-run of `create_transaction`: which categories would cover `amount` and how much.
+    pub async fn preview_transaction(
+        &self,
+        category_id: i64,
+        amount: Decimal,
+        created_at: DateTime<Local>,
+    ) -> Result<Vec<Allocation>> {
+        Ok(self
+            .preview_split_transaction(&[(category_id, amount)], created_at)
             .await?
-            .and_then(|row| row.get(0).ok());
-
-        let total_spent = total_spent
-            .and_then(|s| Decimal::from_str(&s).ok())
-            .unwrap_or(Decimal::ZERO);
+            .pop()
+            .unwrap_or_default())
+    }
 
+    /// Dry-run of `create_split_transaction`: the covering categories of each line, with
+    /// every line planned against what the lines before it would leave.
+    pub async fn preview_split_transaction(
+        &self,
+        lines: &[(i64, Decimal)],
+        created_at: DateTime<Local>,
+    ) -> Result<Vec<Vec<Allocation>>> {
         let categories = self.get_categories().await?;
-        let balances = self.get_category_balances().await?;
+        let mut balances = self.get_category_balances().await?;
+        let closed_period = !in_period(created_at, self.current_period_start().await?);
 
-        Ok(crate::models::SummaryStats {
-            total_funds_added: total_funds,
-            total_spent,
-            current_settings: categories,
-            balances,
-        })
+        let mut previews = Vec::new();
+        for (category_id, amount) in lines {
+            let target_cat = categories
+                .iter()
+                .find(|c| c.id == *category_id)
+                .ok_or(AppError::CategoryNotFound(category_id.to_string()))?;
+            let plan = if closed_period {
+                plan_from_savings(&balances, *amount)?
+            } else {
+                plan_deductions(&categories, &balances, target_cat.id, *amount)?
+            };
+            for (cat_id, covered) in &plan {
+                if let Some(bal) = balances.iter_mut().find(|b| b.category_id == *cat_id) {
+                    bal.spent += *covered;
+                }
+            }
+            previews.push(
+                plan.into_iter()
+                    .map(|(category_id, amount)| Allocation {
+                        category_id,
+                        category_name: categories
+                            .iter()
+                            .find(|c| c.id == category_id)
+                            .map(|c| c.name.clone()),
+                        amount,
+                    })
+                    .collect(),
+            );
+        }
+        Ok(previews)
     }
 
-    pub async fn get_transactions(
+    /// Rewrite an expense, reversing its old deduction and applying the new one atomically.
+    /// Closed-period sides of the change are settled against Savings, as in `create_transaction`.
+    /// Once refunded, only its amount (down to what was refunded) and description can change.
+    pub async fn update_transaction(
         &self,
-        date_range: crate::models::DateRange,
-    ) -> Result<Vec<crate::models::Transaction>> {
-        let (start_date, end_date) = date_range.get_dates();
-
-        let mut rows = self
+        id: i64,
+        category_id: i64,
+        amount: Decimal,
+        description: Option<String>,
+        created_at: DateTime<Local>,
+    ) -> Result<()> {
+        let tx = self
             .conn
-            .query(
-                "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name 
-                 FROM transactions t 
-                 JOIN categories c ON t.category_id = c.id
-                 WHERE t.created_at >= ? AND t.created_at <= ?
-                 ORDER BY t.created_at DESC",
-                [start_date.to_rfc3339(), end_date.to_rfc3339()],
-            )
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
             .await?;
 
-        let mut transactions = Vec::new();
-
-        while let Some(row) = rows.next().await? {
-            let id: i64 = row.get(0)?;
-            let category_id: i64 = row.get(1)?;
-            let amount_str: String = row.get(2)?;
-            let amount = Decimal::from_str(&amount_str).unwrap_or_default();
-            let description: Option<String> = row.get(3)?;
-            let created_at_str: String = row.get(4)?;
-            let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Local))
-                .unwrap_or_else(|_| Local::now());
-            let overflow_from_id: Option<i64> = row.get(5)?;
-            let category_name_str: String = row.get(6)?;
-            let category_name = CategoryName::from_str(&category_name_str).ok();
-
-            transactions.push(crate::models::Transaction {
-                id,
-                category_id,
-                amount,
-                description,
-                created_at,
-                overflow_from_id,
-                category_name,
-            });
+        let original = self.get_transaction(id).await?;
+        if original.is_refund() {
+            return Err(AppError::Validation(
+                "Refunds can't be edited; delete it and record it again".into(),
+            ));
         }
+        if original.split_id.is_some() {
+            return Err(AppError::Validation(
+                "Split expenses can't be edited; delete it and record it again".into(),
+            ));
+        }
+        let refunded = self.get_refunded_total(id).await?;
+        let has_refunds = refunded > Decimal::ZERO;
+        if amount < refunded {
+            let currency = self.get_currency().await?;
+            return Err(AppError::Validation(format!(
+                "{} of this expense was already refunded",
+                currency.format(refunded)
+            )));
+        }
+        // The form edits to the minute, so a date within the same minute is unchanged
+        let moved = (created_at - original.created_at).abs() >= TimeDelta::minutes(1);
+        if has_refunds && (category_id != original.category_id || moved) {
+            return Err(AppError::Validation(
+                "This expense has refunds; only its amount and description can change".into(),
+            ));
+        }
+        let created_at = if moved {
+            created_at
+        } else {
+            original.created_at
+        };
+        let categories = self.get_categories().await?;
+        validate_expense_category(&categories, category_id)?;
+        let mut balances = self.get_category_balances().await?;
+        let period_start = self.current_period_start().await?;
 
-        Ok(transactions)
-    }
-}
-
-// Add strict-compatible inner structs if needed for other methods, or keep them separate?
-// For get_transactions we did manual extraction which is safer heavily.
-
-#[derive(serde::Deserialize)]
-struct CategoryRow {
-    id: i64,
-    name: String,
-    limit_percentage: Decimal,
-    overflow_to_id: Option<i64>,
-}
+        validate_not_future(created_at)?;
+        let updates = if has_refunds && in_period(original.created_at, period_start) {
+            // The refunds were credited against the recorded allocations, so those stay and
+            // only the change in amount is charged or given back
+            let mut allocations = reversal_for(&categories, &balances, &original);
+            let changes = if amount > original.amount {
+                plan_deductions(
+                    &categories,
+                    &balances,
+                    category_id,
+                    amount - original.amount,
+                )?
+            } else if amount < original.amount {
+                let refunded = self.get_refunded_allocations(id).await?;
+                plan_refund(
+                    &categories,
+                    &balances,
+                    &original,
+                    &refunded,
+                    original.amount - amount,
+                )?
+            } else {
+                Vec::new()
+            };
+            apply_spent(&tx, &mut balances, &changes, Decimal::ONE).await?;
+            for (cat_id, change) in changes {
+                match allocations.iter_mut().find(|(id, _)| *id == cat_id) {
+                    Some((_, allocated)) => *allocated += change,
+                    None => allocations.push((cat_id, change)),
+                }
+            }
+            allocations.retain(|(_, allocated)| *allocated > Decimal::ZERO);
+            allocations
+        } else {
+            if in_period(original.created_at, period_start) {
+                let reversal = reversal_for(&categories, &balances, &original);
+                apply_spent(&tx, &mut balances, &reversal, Decimal::NEGATIVE_ONE).await?;
+            } else {
+                adjust_savings(&tx, &mut balances, original.amount).await?;
+            }
+            if in_period(created_at, period_start) {
+                let updates = plan_deductions(&categories, &balances, category_id, amount)?;
+                apply_spent(&tx, &mut balances, &updates, Decimal::ONE).await?;
+                updates
+            } else {
+                let updates = plan_from_savings(&balances, amount)?;
+                adjust_savings(&tx, &mut balances, -amount).await?;
+                updates
+            }
+        };
+        tx.execute(
+            "DELETE FROM transaction_allocations WHERE transaction_id = ?",
+            [id],
+        )
+        .await?;
+        insert_allocations(&tx, id, &updates).await?;
+
+        tx.execute(
+            "UPDATE transactions SET category_id = ?, amount = ?, description = ?, created_at = ? WHERE id = ?",
+            [
+                category_id.to_string(),
+                amount.to_string(),
+                description.clone().unwrap_or_default(),
+                created_at.to_rfc3339(),
+                id.to_string(),
+            ],
+        )
+        .await?;
+        sync_tags(&tx, id, description.as_deref().unwrap_or_default()).await?;
+        sync_payee(&tx, id, description.as_deref().unwrap_or_default()).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Remove an expense and give its deduction back to the overflow chain (or to Savings when
+    /// it was made in a closed period). Deleting a refund takes its credit back the same way;
+    /// deleting any line of a split expense removes all of its lines.
+    pub async fn delete_transaction(&self, id: i64) -> Result<()> {
+        // A split expense goes as a whole
+        let original = self.get_transaction(id).await?;
+        let lines = match original.split_id {
+            Some(split_id) => self.get_split_lines(split_id).await?,
+            None => vec![original],
+        };
+        for line in &lines {
+            if self.get_refunded_total(line.id).await? > Decimal::ZERO {
+                return Err(AppError::Validation(
+                    "This expense has refunds; delete them first".into(),
+                ));
+            }
+        }
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+
+        let period_start = self.current_period_start().await?;
+        let categories = self.get_categories().await?;
+        let mut balances = self.get_category_balances().await?;
+        for line in &lines {
+            if in_period(line.created_at, period_start) {
+                let reversal = reversal_for(&categories, &balances, line);
+                apply_spent(&tx, &mut balances, &reversal, Decimal::NEGATIVE_ONE).await?;
+            } else {
+                adjust_savings(&tx, &mut balances, line.amount).await?;
+            }
+
+            tx.execute(
+                "DELETE FROM transaction_allocations WHERE transaction_id = ?",
+                [line.id],
+            )
+            .await?;
+            tx.execute(
+                "DELETE FROM transaction_tags WHERE transaction_id = ?",
+                [line.id],
+            )
+            .await?;
+        }
+        // Later lines reference the first one through split_id
+        for line in lines.iter().rev() {
+            tx.execute("DELETE FROM transactions WHERE id = ?", [line.id])
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record `amount` coming back for expense `original_id` (a return, a cancelled order).
+    ///
+    /// The refund is stored as a negative transaction linked to the expense. Inside the current
+    /// period it credits `spent` of the categories the expense actually drained, last hop of the
+    /// overflow chain first; when either side is in a closed period the money goes to Savings.
+    /// A split expense is refunded line by line: `original_id` is the line the money is for.
+    pub async fn create_refund(
+        &self,
+        original_id: i64,
+        amount: Decimal,
+        description: Option<String>,
+        created_at: DateTime<Local>,
+    ) -> Result<()> {
+        validate_not_future(created_at)?;
+        if amount <= Decimal::ZERO {
+            return Err(AppError::Validation(
+                "Refund amount must be positive".into(),
+            ));
+        }
+        let original = self.get_transaction(original_id).await?;
+        if original.is_refund() {
+            return Err(AppError::Validation("A refund can't be refunded".into()));
+        }
+        if created_at < original.created_at {
+            return Err(AppError::Validation(
+                "A refund can't be dated before its expense".into(),
+            ));
+        }
+        let refundable = original.amount - self.get_refunded_total(original_id).await?;
+        if amount > refundable {
+            return Err(AppError::Validation(format!(
+                "Only {} of this expense is left to refund",
+                refundable
+            )));
+        }
+
+        let categories = self.get_categories().await?;
+        let mut balances = self.get_category_balances().await?;
+        let period_start = self.current_period_start().await?;
+        let closed_period = !in_period(created_at, period_start);
+        let credits = if !closed_period && in_period(original.created_at, period_start) {
+            let refunded = self.get_refunded_allocations(original_id).await?;
+            plan_refund(&categories, &balances, &original, &refunded, amount)?
+        } else {
+            savings_credit(&balances, amount)?
+        };
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+
+        let refund_id: i64 = tx
+            .query(
+                "INSERT INTO transactions (category_id, amount, description, overflow_from_id, created_at, account_id, refund_of_id) VALUES (?, ?, ?, NULL, ?, ?, ?) RETURNING id",
+                libsql::params![
+                    original.category_id,
+                    (-amount).to_string(),
+                    description.clone().unwrap_or_default(),
+                    created_at.to_rfc3339(),
+                    original.account_id,
+                    original_id
+                ],
+            )
+            .await?
+            .next()
+            .await?
+            .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+            .get(0)?;
+
+        if closed_period {
+            adjust_savings(&tx, &mut balances, amount).await?;
+        } else {
+            apply_spent(&tx, &mut balances, &credits, Decimal::ONE).await?;
+        }
+        insert_allocations(&tx, refund_id, &credits).await?;
+        sync_tags(&tx, refund_id, description.as_deref().unwrap_or_default()).await?;
+        tx.execute(
+            "UPDATE transactions SET payee_id = (SELECT payee_id FROM transactions WHERE id = ?) WHERE id = ?",
+            [original_id, refund_id],
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// How much of expense `original_id` has been refunded so far (positive)
+    pub async fn get_refunded_total(&self, original_id: i64) -> Result<Decimal> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT amount FROM transactions WHERE refund_of_id = ?",
+                [original_id],
+            )
+            .await?;
+        let mut total = Decimal::ZERO;
+        while let Some(row) = rows.next().await? {
+            total -= decimal_column(&row, 0)?;
+        }
+        Ok(total)
+    }
+
+    /// What earlier refunds of `original_id` credited, per category and refund (positive)
+    async fn get_refunded_allocations(&self, original_id: i64) -> Result<Vec<(i64, Decimal)>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT a.category_id, a.amount FROM transaction_allocations a
+                 JOIN transactions t ON t.id = a.transaction_id
+                 WHERE t.refund_of_id = ?",
+                [original_id],
+            )
+            .await?;
+        let mut refunded = Vec::new();
+        while let Some(row) = rows.next().await? {
+            refunded.push((row.get(0)?, -decimal_column(&row, 1)?));
+        }
+        Ok(refunded)
+    }
+
+    /// Whether `at` falls before the latest fund entry, in a period whose balances are closed
+    pub async fn in_closed_period(&self, at: DateTime<Local>) -> Result<bool> {
+        Ok(!in_period(at, self.current_period_start().await?))
+    }
+
+    /// Timestamp of the latest fund entry; `spent` was reset at that moment.
+    async fn current_period_start(&self) -> Result<Option<DateTime<Local>>> {
+        let added_at: Option<String> = self
+            .conn
+            .query("SELECT MAX(added_at) FROM funds", ())
+            .await?
+            .next()
+            .await?
+            .and_then(|row| row.get(0).ok());
+
+        Ok(added_at.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Local))
+                .ok()
+        }))
+    }
+
+    /// Accounts with their balances: funds in, expenses out, transfers moved between them.
+    pub async fn get_accounts(&self) -> Result<Vec<Account>> {
+        let mut rows = self
+            .conn
+            .query("SELECT id, name, kind FROM accounts ORDER BY id", ())
+            .await?;
+        let mut accounts = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let kind: String = row.get(2)?;
+            accounts.push(Account {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: AccountKind::from_str(&kind).unwrap_or_default(),
+                balance: Decimal::ZERO,
+            });
+        }
+
+        let mut adjust = |account_id: i64, delta: Decimal| {
+            if let Some(account) = accounts.iter_mut().find(|a| a.id == account_id) {
+                account.balance += delta;
+            }
+        };
+
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT account_id, amount FROM funds WHERE account_id IS NOT NULL",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            adjust(row.get(0)?, decimal_column(&row, 1)?);
+        }
+
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT account_id, amount FROM transactions WHERE account_id IS NOT NULL",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            adjust(row.get(0)?, -decimal_column(&row, 1)?);
+        }
+
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT from_account_id, to_account_id, amount FROM transfers",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let amount = decimal_column(&row, 2)?;
+            adjust(row.get(0)?, -amount);
+            adjust(row.get(1)?, amount);
+        }
+
+        Ok(accounts)
+    }
+
+    pub async fn create_account(&self, name: &str, kind: AccountKind) -> Result<i64> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Account name can't be empty".into()));
+        }
+        if self
+            .get_accounts()
+            .await?
+            .iter()
+            .any(|a| a.name.eq_ignore_ascii_case(name))
+        {
+            return Err(AppError::Validation(format!(
+                "An account named {} already exists",
+                name
+            )));
+        }
+
+        let id: i64 = self
+            .conn
+            .query(
+                "INSERT INTO accounts (name, kind) VALUES (?, ?) RETURNING id",
+                [name.to_string(), kind.to_string()],
+            )
+            .await?
+            .next()
+            .await?
+            .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+            .get(0)?;
+        Ok(id)
+    }
+
+    pub async fn set_account_kind(&self, account_id: i64, kind: AccountKind) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE accounts SET kind = ? WHERE id = ?",
+                [kind.to_string(), account_id.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Move money between accounts. Category balances are not affected.
+    pub async fn create_transfer(
+        &self,
+        from_account_id: i64,
+        to_account_id: i64,
+        amount: Decimal,
+        note: Option<String>,
+    ) -> Result<()> {
+        if from_account_id == to_account_id {
+            return Err(AppError::Validation(
+                "Transfer needs two different accounts".into(),
+            ));
+        }
+        if amount <= Decimal::ZERO {
+            return Err(AppError::Validation("Amount must be positive".into()));
+        }
+
+        self.conn
+            .execute(
+                "INSERT INTO transfers (from_account_id, to_account_id, amount, note, created_at) VALUES (?, ?, ?, ?, ?)",
+                libsql::params![
+                    from_account_id,
+                    to_account_id,
+                    amount.to_string(),
+                    note,
+                    Local::now().to_rfc3339()
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Most recent transfers first
+    pub async fn get_transfers(&self, limit: i64) -> Result<Vec<Transfer>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, from_account_id, to_account_id, amount, note, created_at
+                 FROM transfers ORDER BY created_at DESC LIMIT ?",
+                [limit],
+            )
+            .await?;
+
+        let mut transfers = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let created_at: String = row.get(5)?;
+            transfers.push(Transfer {
+                id: row.get(0)?,
+                from_account_id: row.get(1)?,
+                to_account_id: row.get(2)?,
+                amount: decimal_column(&row, 3)?,
+                note: row.get(4)?,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Local))
+                    .unwrap_or_else(|_| Local::now()),
+            });
+        }
+        Ok(transfers)
+    }
+
+    /// Recurring rules, soonest due first
+    pub async fn get_recurring_rules(&self) -> Result<Vec<RecurringRule>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, kind, amount, category_id, account_id, description, cadence,
+                        starts_at, next_due, requires_confirmation, active
+                 FROM recurring_rules ORDER BY next_due",
+                (),
+            )
+            .await?;
+
+        let parse_date = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Local))
+                .map_err(|e| AppError::Deserialization(e.to_string()))
+        };
+
+        let mut rules = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let kind: String = row.get(1)?;
+            let cadence: String = row.get(6)?;
+            let requires_confirmation: i64 = row.get(9)?;
+            let active: i64 = row.get(10)?;
+            rules.push(RecurringRule {
+                id: row.get(0)?,
+                kind: RecurringKind::from_str(&kind).unwrap_or_default(),
+                amount: decimal_column(&row, 2)?,
+                category_id: row.get(3)?,
+                account_id: row.get(4)?,
+                description: row.get(5)?,
+                cadence: Cadence::from_str(&cadence)
+                    .map_err(|e| AppError::Deserialization(e.to_string()))?,
+                starts_at: parse_date(row.get(7)?)?,
+                next_due: parse_date(row.get(8)?)?,
+                requires_confirmation: requires_confirmation != 0,
+                active: active != 0,
+            });
+        }
+        Ok(rules)
+    }
+
+    /// Store a new rule. `next_due` starts at `starts_at`, or at the first occurrence after now
+    /// when that is in the past: a past start only anchors the cadence, and the occurrences
+    /// before now are not posted. The rule's `id` and `next_due` are ignored.
+    pub async fn create_recurring_rule(&self, rule: &RecurringRule) -> Result<i64> {
+        if rule.amount <= Decimal::ZERO {
+            return Err(AppError::Validation("Amount must be positive".into()));
+        }
+        if rule.kind == RecurringKind::Expense && rule.category_id.is_none() {
+            return Err(AppError::Validation(
+                "Recurring expenses need a category".into(),
+            ));
+        }
+        let next_due = rule
+            .cadence
+            .next_after(rule.starts_at, Local::now())
+            .ok_or_else(|| {
+                AppError::Validation(format!("{} has no further occurrences", rule.label()))
+            })?;
+
+        let id: i64 = self
+            .conn
+            .query(
+                "INSERT INTO recurring_rules
+                    (kind, amount, category_id, account_id, description, cadence, starts_at,
+                     next_due, requires_confirmation, active)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1) RETURNING id",
+                libsql::params![
+                    rule.kind.to_string(),
+                    rule.amount.to_string(),
+                    rule.category_id,
+                    rule.account_id,
+                    rule.description.clone(),
+                    rule.cadence.to_string(),
+                    rule.starts_at.to_rfc3339(),
+                    next_due.to_rfc3339(),
+                    i64::from(rule.requires_confirmation)
+                ],
+            )
+            .await?
+            .next()
+            .await?
+            .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+            .get(0)?;
+        Ok(id)
+    }
+
+    pub async fn set_recurring_active(&self, rule_id: i64, active: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE recurring_rules SET active = ? WHERE id = ?",
+                [i64::from(active), rule_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_recurring_confirmation(&self, rule_id: i64, required: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE recurring_rules SET requires_confirmation = ? WHERE id = ?",
+                [i64::from(required), rule_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_recurring_rule(&self, rule_id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM recurring_rules WHERE id = ?", [rule_id])
+            .await?;
+        Ok(())
+    }
+
+    /// Post the rule's due occurrence through the regular expense/funds paths and move
+    /// `next_due` to the following occurrence, atomically: either both happen or neither.
+    pub async fn post_recurring_occurrence(&self, rule: &RecurringRule) -> Result<()> {
+        let account_id = rule
+            .account_id
+            .ok_or_else(|| AppError::Validation(format!("{} has no account", rule.label())))?;
+
+        // The entry and the new `next_due` are written in one transaction, so an occurrence
+        // is never posted twice. It is dated when it fell due, even if the app was closed then.
+        let posted_at = rule.next_due.min(Local::now());
+        match rule.kind {
+            RecurringKind::Expense => {
+                let category_id = rule.category_id.ok_or_else(|| {
+                    AppError::Validation(format!("{} has no category", rule.label()))
+                })?;
+                self.insert_expense(
+                    &[(category_id, rule.amount)],
+                    rule.description.clone(),
+                    account_id,
+                    posted_at,
+                    None,
+                    Some(rule),
+                )
+                .await
+                .map(|_| ())
+            }
+            RecurringKind::Income => self
+                .insert_funds(
+                    rule.amount,
+                    account_id,
+                    rule.description.clone(),
+                    None,
+                    posted_at,
+                    Some(rule),
+                )
+                .await
+                .map(|_| ()),
+        }
+    }
+
+    /// Move `next_due` past the current occurrence without posting it
+    pub async fn skip_recurring_occurrence(&self, rule: &RecurringRule) -> Result<()> {
+        advance_rule(&self.conn, rule).await
+    }
+
+    /// CSV import profiles, by name
+    pub async fn get_import_profiles(&self) -> Result<Vec<ImportProfile>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, name, delimiter, skip_lines, date_column, date_format, amount_column,
+                        second_column, description_column, sign_convention, decimal_separator,
+                        account_id, category_id
+                 FROM import_profiles ORDER BY name",
+                (),
+            )
+            .await?;
+
+        let first_char = |s: String, default: char| s.chars().next().unwrap_or(default);
+        let mut profiles = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let skip_lines: i64 = row.get(3)?;
+            let sign_convention: String = row.get(9)?;
+            profiles.push(ImportProfile {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                delimiter: first_char(row.get(2)?, ','),
+                skip_lines: usize::try_from(skip_lines).unwrap_or_default(),
+                date_column: row.get(4)?,
+                date_format: row.get(5)?,
+                amount_column: row.get(6)?,
+                second_column: row.get(7)?,
+                description_column: row.get(8)?,
+                sign_convention: SignConvention::from_str(&sign_convention).unwrap_or_default(),
+                decimal_separator: first_char(row.get(10)?, '.'),
+                account_id: row.get(11)?,
+                category_id: row.get(12)?,
+            });
+        }
+        Ok(profiles)
+    }
+
+    /// Create the profile when its `id` is 0, otherwise overwrite it. Returns its id.
+    pub async fn save_import_profile(&self, profile: &ImportProfile) -> Result<i64> {
+        let name = profile.name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Profile name can't be empty".into()));
+        }
+        if self
+            .get_import_profiles()
+            .await?
+            .iter()
+            .any(|p| p.id != profile.id && p.name.eq_ignore_ascii_case(name))
+        {
+            return Err(AppError::Validation(format!(
+                "An import profile named {} already exists",
+                name
+            )));
+        }
+        if !profile.delimiter.is_ascii() || profile.delimiter.is_ascii_alphanumeric() {
+            return Err(AppError::Validation(
+                "Delimiter must be a single symbol such as , ; or |".into(),
+            ));
+        }
+        if !matches!(profile.decimal_separator, '.' | ',') {
+            return Err(AppError::Validation(
+                "Decimal separator must be . or ,".into(),
+            ));
+        }
+        if [
+            &profile.date_column,
+            &profile.amount_column,
+            &profile.description_column,
+        ]
+        .iter()
+        .any(|column| column.trim().is_empty())
+        {
+            return Err(AppError::Validation(
+                "Date, amount and description columns are required".into(),
+            ));
+        }
+        if profile.sign_convention == SignConvention::DebitCreditColumns
+            && profile.second_column.is_none()
+        {
+            return Err(AppError::Validation(
+                "Debit/credit columns need the credit column".into(),
+            ));
+        }
+        if profile.date_format.trim().is_empty()
+            || chrono::format::StrftimeItems::new(&profile.date_format)
+                .any(|item| matches!(item, chrono::format::Item::Error))
+        {
+            return Err(AppError::Validation(format!(
+                "{} is not a valid date format, try %d/%m/%Y",
+                profile.date_format
+            )));
+        }
+
+        let params = libsql::params![
+            name.to_string(),
+            profile.delimiter.to_string(),
+            i64::try_from(profile.skip_lines).unwrap_or_default(),
+            profile.date_column.trim().to_string(),
+            profile.date_format.trim().to_string(),
+            profile.amount_column.trim().to_string(),
+            profile.second_column.clone(),
+            profile.description_column.trim().to_string(),
+            profile.sign_convention.to_string(),
+            profile.decimal_separator.to_string(),
+            profile.account_id,
+            profile.category_id,
+            profile.id
+        ];
+        if profile.id != 0 {
+            self.conn
+                .execute(
+                    "UPDATE import_profiles
+                     SET name = ?1, delimiter = ?2, skip_lines = ?3, date_column = ?4,
+                         date_format = ?5, amount_column = ?6, second_column = ?7,
+                         description_column = ?8, sign_convention = ?9, decimal_separator = ?10,
+                         account_id = ?11, category_id = ?12
+                     WHERE id = ?13",
+                    params,
+                )
+                .await?;
+            return Ok(profile.id);
+        }
+
+        let id: i64 = self
+            .conn
+            .query(
+                "INSERT INTO import_profiles
+                    (name, delimiter, skip_lines, date_column, date_format, amount_column,
+                     second_column, description_column, sign_convention, decimal_separator,
+                     account_id, category_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12) RETURNING id",
+                params,
+            )
+            .await?
+            .next()
+            .await?
+            .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+            .get(0)?;
+        Ok(id)
+    }
+
+    pub async fn delete_import_profile(&self, profile_id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM import_profiles WHERE id = ?", [profile_id])
+            .await?;
+        Ok(())
+    }
+
+    /// Summary stats of the Reports date range: fund entries and expenses dated in it
+    pub async fn get_summary_stats(
+        &self,
+        date_range: DateRange,
+    ) -> Result<crate::models::SummaryStats> {
+        let (start, last_second) = date_range.get_dates();
+        // The range includes the last second of today
+        let end = last_second + TimeDelta::seconds(1);
+        let income_by_source = self.get_income_by_source(Some(start), Some(end)).await?;
+        let total_spent = self
+            .total_spent_between(start.to_rfc3339(), end.to_rfc3339())
+            .await?;
+
+        Ok(crate::models::SummaryStats {
+            total_funds_added: income_by_source.iter().map(|s| s.total).sum(),
+            total_spent,
+            income_by_source,
+            current_settings: self.get_categories().await?,
+            balances: self.get_category_balances().await?,
+        })
+    }
+
+    /// Sum of the expenses dated in `[start, end)`, given as RFC3339 bounds
+    async fn total_spent_between(&self, start: String, end: String) -> Result<Decimal> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT amount FROM transactions WHERE created_at >= ? AND created_at < ?",
+                [start, end],
+            )
+            .await?;
+        let mut total_spent = Decimal::ZERO;
+        while let Some(row) = rows.next().await? {
+            total_spent += decimal_column(&row, 0)?;
+        }
+        Ok(total_spent)
+    }
+
+    /// Funds per source added in `[start, end)`, largest first. Sums are taken as Decimal;
+    /// SQLite SUM would go through floats.
+    async fn get_income_by_source(
+        &self,
+        start: Option<DateTime<Local>>,
+        end: Option<DateTime<Local>>,
+    ) -> Result<Vec<SourceTotal>> {
+        let mut income_by_source: Vec<SourceTotal> = Vec::new();
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT COALESCE(NULLIF(TRIM(source), ''), 'Unlabeled'), amount FROM funds
+                 WHERE added_at >= ? AND added_at < ?",
+                [
+                    start.map(|s| s.to_rfc3339()).unwrap_or_default(),
+                    end.map(|e| e.to_rfc3339())
+                        .unwrap_or_else(|| OPEN_END.to_string()),
+                ],
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let source: String = row.get(0)?;
+            let amount = decimal_column(&row, 1)?;
+            match income_by_source
+                .iter_mut()
+                .find(|s| s.source.eq_ignore_ascii_case(&source))
+            {
+                Some(entry) => entry.total += amount,
+                None => income_by_source.push(SourceTotal {
+                    source,
+                    total: amount,
+                }),
+            }
+        }
+        income_by_source.sort_by_key(|s| std::cmp::Reverse(s.total));
+        Ok(income_by_source)
+    }
+
+    /// Fund entries in the range (all of them for `None`), oldest first
+    pub async fn get_funds(&self, date_range: Option<DateRange>) -> Result<Vec<Fund>> {
+        let (start_date, end_date) = range_bounds(date_range);
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, amount, added_at, remaining_balance_rolled, account_id, source, note
+                 FROM funds
+                 WHERE added_at >= ? AND added_at <= ?
+                 ORDER BY added_at ASC, id ASC",
+                [start_date, end_date],
+            )
+            .await?;
+
+        let mut funds = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let added_at: String = row.get(2)?;
+            funds.push(Fund {
+                id: row.get(0)?,
+                amount: decimal_column(&row, 1)?,
+                added_at: DateTime::parse_from_rfc3339(&added_at)
+                    .map(|dt| dt.with_timezone(&Local))
+                    .map_err(|e| AppError::Deserialization(e.to_string()))?,
+                remaining_balance_rolled: decimal_column(&row, 3)?,
+                account_id: row.get(4)?,
+                source: row.get(5)?,
+                note: row.get(6)?,
+            });
+        }
+        Ok(funds)
+    }
+
+    /// Budget periods, newest first. Each fund entry opens one.
+    pub async fn get_budget_periods(&self) -> Result<Vec<BudgetPeriod>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, added_at FROM funds ORDER BY added_at DESC, id DESC",
+                (),
+            )
+            .await?;
+        let mut periods: Vec<BudgetPeriod> = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let added_at: String = row.get(1)?;
+            let start = DateTime::parse_from_rfc3339(&added_at)
+                .map(|dt| dt.with_timezone(&Local))
+                .map_err(|e| AppError::Deserialization(e.to_string()))?;
+            periods.push(BudgetPeriod {
+                fund_id: row.get(0)?,
+                start,
+                end: periods.last().map(|newer| newer.start),
+            });
+        }
+        Ok(periods)
+    }
+
+    /// Kakeibo reflections of every budget period, newest first. Periods without a saved
+    /// reflection come back with empty answers and their figures filled in.
+    pub async fn get_reflections(&self) -> Result<Vec<Reflection>> {
+        let mut reflections: Vec<Reflection> = self
+            .get_budget_periods()
+            .await?
+            .into_iter()
+            .map(|period| Reflection {
+                period,
+                received: Decimal::ZERO,
+                spent: Decimal::ZERO,
+                savings_goal: None,
+                answers: Default::default(),
+            })
+            .collect();
+
+        let parse_date = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Local))
+                .map_err(|e| AppError::Deserialization(e.to_string()))
+        };
+
+        // One pass over every fund entry and expense, each summed into its period
+        let mut rows = self
+            .conn
+            .query("SELECT added_at, amount FROM funds", ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let added_at = parse_date(row.get(0)?)?;
+            if let Some(reflection) = reflection_at(&mut reflections, added_at) {
+                reflection.received += decimal_column(&row, 1)?;
+            }
+        }
+        let mut rows = self
+            .conn
+            .query("SELECT created_at, amount FROM transactions", ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let created_at = parse_date(row.get(0)?)?;
+            if let Some(reflection) = reflection_at(&mut reflections, created_at) {
+                reflection.spent += decimal_column(&row, 1)?;
+            }
+        }
+
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT fund_id, savings_goal, answer_received, answer_save, answer_spent, answer_improve
+                 FROM reflections",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let fund_id: i64 = row.get(0)?;
+            let Some(reflection) = reflections.iter_mut().find(|r| r.period.fund_id == fund_id)
+            else {
+                continue;
+            };
+            let goal: Option<String> = row.get(1)?;
+            reflection.savings_goal = goal.and_then(|g| Decimal::from_str(&g).ok());
+            for (i, answer) in reflection.answers.iter_mut().enumerate() {
+                *answer = row.get(i as i32 + 2)?;
+            }
+        }
+        Ok(reflections)
+    }
+
+    /// Create or replace the reflection of the period opened by `fund_id`
+    pub async fn save_reflection(
+        &self,
+        fund_id: i64,
+        savings_goal: Option<Decimal>,
+        answers: &[String; 4],
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO reflections (fund_id, savings_goal, answer_received, answer_save, answer_spent, answer_improve, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(fund_id) DO UPDATE SET
+                    savings_goal = excluded.savings_goal,
+                    answer_received = excluded.answer_received,
+                    answer_save = excluded.answer_save,
+                    answer_spent = excluded.answer_spent,
+                    answer_improve = excluded.answer_improve,
+                    updated_at = excluded.updated_at",
+                libsql::params![
+                    fund_id,
+                    savings_goal.map(|g| g.to_string()),
+                    answers[0].trim(),
+                    answers[1].trim(),
+                    answers[2].trim(),
+                    answers[3].trim(),
+                    Local::now().to_rfc3339()
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Average budget period length, used to project what goals need per period
+    pub async fn get_typical_period_length(&self) -> Result<chrono::TimeDelta> {
+        Ok(typical_period_length(&self.get_budget_periods().await?))
+    }
+
+    /// Savings goals with what has been withdrawn for each, nearest deadline first
+    pub async fn get_savings_goals(&self) -> Result<Vec<SavingsGoal>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, name, target, deadline, created_at FROM savings_goals
+                 ORDER BY deadline IS NULL, deadline, id",
+                (),
+            )
+            .await?;
+
+        let parse_date = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Local))
+                .map_err(|e| AppError::Deserialization(e.to_string()))
+        };
+
+        let mut goals = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let deadline: Option<String> = row.get(3)?;
+            goals.push(SavingsGoal {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                target: decimal_column(&row, 2)?,
+                deadline: deadline.map(parse_date).transpose()?,
+                created_at: parse_date(row.get(4)?)?,
+                saved: Decimal::ZERO,
+            });
+        }
+
+        let mut rows = self
+            .conn
+            .query("SELECT goal_id, amount FROM goal_withdrawals", ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let goal_id: i64 = row.get(0)?;
+            if let Some(goal) = goals.iter_mut().find(|g| g.id == goal_id) {
+                goal.saved += decimal_column(&row, 1)?;
+            }
+        }
+        Ok(goals)
+    }
+
+    pub async fn create_savings_goal(
+        &self,
+        name: &str,
+        target: Decimal,
+        deadline: Option<DateTime<Local>>,
+    ) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Goal name cannot be empty".into()));
+        }
+        if target <= Decimal::ZERO {
+            return Err(AppError::Validation("Goal target must be positive".into()));
+        }
+
+        self.conn
+            .execute(
+                "INSERT INTO savings_goals (name, target, deadline, created_at) VALUES (?, ?, ?, ?)",
+                libsql::params![
+                    name,
+                    target.to_string(),
+                    deadline.map(|d| d.to_rfc3339()),
+                    Local::now().to_rfc3339()
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Take `amount` out of the Savings category and count it towards the goal
+    pub async fn withdraw_for_goal(
+        &self,
+        goal_id: i64,
+        amount: Decimal,
+        note: Option<String>,
+    ) -> Result<()> {
+        if amount <= Decimal::ZERO {
+            return Err(AppError::Validation("Amount must be positive".into()));
+        }
+        let mut balances = self.get_category_balances().await?;
+        let updates = plan_from_savings(&balances, amount)?;
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+        // The goal may have been deleted since the form was opened
+        if tx
+            .query("SELECT 1 FROM savings_goals WHERE id = ?", [goal_id])
+            .await?
+            .next()
+            .await?
+            .is_none()
+        {
+            return Err(AppError::Validation(
+                "That savings goal no longer exists".into(),
+            ));
+        }
+        apply_spent(&tx, &mut balances, &updates, Decimal::ONE).await?;
+        tx.execute(
+            "INSERT INTO goal_withdrawals (goal_id, amount, note, created_at) VALUES (?, ?, ?, ?)",
+            libsql::params![
+                goal_id,
+                amount.to_string(),
+                note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+                Local::now().to_rfc3339()
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Remove a goal and its withdrawal history. Withdrawn money stays withdrawn.
+    pub async fn delete_savings_goal(&self, goal_id: i64) -> Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+        tx.execute("DELETE FROM goal_withdrawals WHERE goal_id = ?", [goal_id])
+            .await?;
+        tx.execute("DELETE FROM savings_goals WHERE id = ?", [goal_id])
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Transactions in the range, newest first. When `search` holds terms, only rows whose
+    /// description matches every term (as a word prefix) are returned.
+    pub async fn get_transactions(
+        &self,
+        date_range: DateRange,
+        search: &[String],
+    ) -> Result<Vec<crate::models::Transaction>> {
+        let (start_date, end_date) = range_bounds(Some(date_range));
+        self.query_transactions(start_date, end_date, search).await
+    }
+
+    /// Transactions in the range (all of them for `None`), newest first
+    pub async fn get_transactions_in(
+        &self,
+        date_range: Option<DateRange>,
+    ) -> Result<Vec<crate::models::Transaction>> {
+        let (start_date, end_date) = range_bounds(date_range);
+        self.query_transactions(start_date, end_date, &[]).await
+    }
+
+    /// Transactions from `start` to `end` inclusive, newest first
+    pub async fn get_transactions_between(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Vec<crate::models::Transaction>> {
+        self.query_transactions(start.to_rfc3339(), end.to_rfc3339(), &[])
+            .await
+    }
+
+    async fn query_transactions(
+        &self,
+        start_date: String,
+        end_date: String,
+        search: &[String],
+    ) -> Result<Vec<crate::models::Transaction>> {
+        let mut rows = if search.is_empty() {
+            self.conn
+                .query(
+                    "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name, t.account_id, t.refund_of_id, t.split_id, p.name as payee_name 
+                     FROM transactions t 
+                     JOIN categories c ON t.category_id = c.id
+                     LEFT JOIN payees p ON t.payee_id = p.id
+                     WHERE t.created_at >= ? AND t.created_at <= ?
+                     ORDER BY t.created_at DESC, t.id DESC",
+                    [start_date, end_date],
+                )
+                .await?
+        } else {
+            self.conn
+                .query(
+                    "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name, t.account_id, t.refund_of_id, t.split_id, p.name as payee_name 
+                     FROM transactions t 
+                     JOIN categories c ON t.category_id = c.id
+                     LEFT JOIN payees p ON t.payee_id = p.id
+                     WHERE t.created_at >= ? AND t.created_at <= ?
+                       AND t.id IN (SELECT rowid FROM transactions_fts WHERE transactions_fts MATCH ?)
+                     ORDER BY t.created_at DESC, t.id DESC",
+                    [start_date, end_date, fts_query(search)],
+                )
+                .await?
+        };
+
+        let mut transactions = Vec::new();
+
+        while let Some(row) = rows.next().await? {
+            transactions.push(transaction_from_row(&row)?);
+        }
+
+        self.attach_allocations(&mut transactions).await?;
+        self.attach_tags(&mut transactions).await?;
+        Ok(transactions)
+    }
+
+    pub async fn get_transaction(&self, id: i64) -> Result<crate::models::Transaction> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name, t.account_id, t.refund_of_id, t.split_id, p.name as payee_name 
+                 FROM transactions t 
+                 JOIN categories c ON t.category_id = c.id
+                 LEFT JOIN payees p ON t.payee_id = p.id
+                 WHERE t.id = ?",
+                [id],
+            )
+            .await?;
+
+        let row = rows.next().await?.ok_or(AppError::Validation(format!(
+            "Transaction {} not found",
+            id
+        )))?;
+        let mut transaction = transaction_from_row(&row)?;
+        self.attach_tags(std::slice::from_mut(&mut transaction))
+            .await?;
+        self.attach_allocations(std::slice::from_mut(&mut transaction))
+            .await?;
+        Ok(transaction)
+    }
+
+    /// Lines of a split expense, in the order they were entered
+    async fn get_split_lines(&self, split_id: i64) -> Result<Vec<crate::models::Transaction>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name, t.account_id, t.refund_of_id, t.split_id, p.name as payee_name 
+                 FROM transactions t 
+                 JOIN categories c ON t.category_id = c.id
+                 LEFT JOIN payees p ON t.payee_id = p.id
+                 WHERE t.split_id = ?
+                 ORDER BY t.id ASC",
+                [split_id],
+            )
+            .await?;
+
+        let mut lines = Vec::new();
+        while let Some(row) = rows.next().await? {
+            lines.push(transaction_from_row(&row)?);
+        }
+        self.attach_allocations(&mut lines).await?;
+        Ok(lines)
+    }
+
+    /// Expenses dated at or after `since`, oldest first
+    async fn get_transactions_since(
+        &self,
+        since: DateTime<Local>,
+    ) -> Result<Vec<crate::models::Transaction>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT t.id, t.category_id, t.amount, t.description, t.created_at, t.overflow_from_id, c.name as category_name, t.account_id, t.refund_of_id, t.split_id, p.name as payee_name 
+                 FROM transactions t 
+                 JOIN categories c ON t.category_id = c.id
+                 LEFT JOIN payees p ON t.payee_id = p.id
+                 WHERE t.created_at >= ?
+                 ORDER BY t.created_at ASC",
+                [since.to_rfc3339()],
+            )
+            .await?;
+
+        let mut transactions = Vec::new();
+        while let Some(row) = rows.next().await? {
+            transactions.push(transaction_from_row(&row)?);
+        }
+        self.attach_allocations(&mut transactions).await?;
+        Ok(transactions)
+    }
+
+    /// Fill in the recorded overflow breakdown of each transaction
+    async fn attach_allocations(
+        &self,
+        transactions: &mut [crate::models::Transaction],
+    ) -> Result<()> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+
+        // IDs are integers from our own rows, so inlining them is safe
+        let ids = transactions
+            .iter()
+            .map(|t| t.id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT a.transaction_id, a.category_id, c.name, a.amount
+                     FROM transaction_allocations a
+                     JOIN categories c ON a.category_id = c.id
+                     WHERE a.transaction_id IN ({})
+                     ORDER BY a.id",
+                    ids
+                ),
+                (),
+            )
+            .await?;
+
+        while let Some(row) = rows.next().await? {
+            let transaction_id: i64 = row.get(0)?;
+            let category_name: String = row.get(2)?;
+            let amount: String = row.get(3)?;
+            let allocation = Allocation {
+                category_id: row.get(1)?,
+                category_name: Some(category_name),
+                amount: Decimal::from_str(&amount).unwrap_or_default(),
+            };
+            if let Some(t) = transactions.iter_mut().find(|t| t.id == transaction_id) {
+                t.allocations.push(allocation);
+            }
+        }
+        Ok(())
+    }
+
+    async fn attach_tags(&self, transactions: &mut [crate::models::Transaction]) -> Result<()> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+
+        let ids = transactions
+            .iter()
+            .map(|t| t.id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT tt.transaction_id, g.name
+                     FROM transaction_tags tt
+                     JOIN tags g ON tt.tag_id = g.id
+                     WHERE tt.transaction_id IN ({})
+                     ORDER BY g.name",
+                    ids
+                ),
+                (),
+            )
+            .await?;
+
+        while let Some(row) = rows.next().await? {
+            let transaction_id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            if let Some(t) = transactions.iter_mut().find(|t| t.id == transaction_id) {
+                t.tags.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Payees with at least one expense, most used first (for Description autocomplete)
+    pub async fn get_payees(&self) -> Result<Vec<Payee>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT p.id, p.name, COUNT(DISTINCT COALESCE(t.split_id, t.id)),
+                        (SELECT l.category_id FROM transactions l
+                         WHERE l.payee_id = p.id AND l.refund_of_id IS NULL
+                         ORDER BY l.created_at DESC, l.id DESC LIMIT 1)
+                 FROM payees p
+                 JOIN transactions t ON t.payee_id = p.id AND t.refund_of_id IS NULL
+                 GROUP BY p.id
+                 ORDER BY COUNT(DISTINCT COALESCE(t.split_id, t.id)) DESC, p.name",
+                (),
+            )
+            .await?;
+
+        let mut payees = Vec::new();
+        while let Some(row) = rows.next().await? {
+            payees.push(Payee {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                uses: row.get(2)?,
+                last_category_id: row.get(3)?,
+            });
+        }
+        Ok(payees)
+    }
+
+    /// Tags in use, most used first (for the tag picker)
+    pub async fn get_tags(&self) -> Result<Vec<String>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT g.name FROM tags g
+                 JOIN transaction_tags tt ON tt.tag_id = g.id
+                 GROUP BY g.id
+                 ORDER BY COUNT(*) DESC, g.name",
+                (),
+            )
+            .await?;
+
+        let mut tags = Vec::new();
+        while let Some(row) = rows.next().await? {
+            tags.push(row.get(0)?);
+        }
+        Ok(tags)
+    }
+}
+
+fn transaction_from_row(row: &libsql::Row) -> Result<crate::models::Transaction> {
+    let id: i64 = row.get(0)?;
+    let category_id: i64 = row.get(1)?;
+    let amount_str: String = row.get(2)?;
+    let amount = Decimal::from_str(&amount_str).unwrap_or_default();
+    let description: Option<String> = row.get(3)?;
+    let created_at_str: String = row.get(4)?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Local))
+        .unwrap_or_else(|_| Local::now());
+    let overflow_from_id: Option<i64> = row.get(5)?;
+    let category_name: Option<String> = row.get(6)?;
+    let account_id: Option<i64> = row.get(7)?;
+    let refund_of_id: Option<i64> = row.get(8)?;
+    let split_id: Option<i64> = row.get(9)?;
+    let payee: Option<String> = row.get(10)?;
+
+    Ok(crate::models::Transaction {
+        id,
+        category_id,
+        amount,
+        description,
+        created_at,
+        overflow_from_id,
+        account_id,
+        refund_of_id,
+        split_id,
+        category_name,
+        payee,
+        allocations: Vec::new(),
+        tags: Vec::new(),
+    })
+}
+
+/// FTS5 query matching every term as a word prefix. Terms are quoted so user input
+/// can never be read as query syntax.
+fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Monetary columns are stored as TEXT; tolerate numeric values written by older builds.
+fn decimal_column(row: &libsql::Row, index: i32) -> Result<Decimal> {
+    Ok(match row.get_value(index)? {
+        libsql::Value::Text(s) => Decimal::from_str(&s).unwrap_or_default(),
+        libsql::Value::Integer(i) => Decimal::from(i),
+        libsql::Value::Real(f) => Decimal::from_f64(f).unwrap_or_default(),
+        _ => Decimal::ZERO,
+    })
+}
+
+/// RFC3339 bounds for a `created_at`/`added_at` range query; `None` covers all time
+fn range_bounds(date_range: Option<DateRange>) -> (String, String) {
+    match date_range {
+        Some(range) => {
+            let (start, end) = range.get_dates();
+            (start.to_rfc3339(), end.to_rfc3339())
+        }
+        None => (String::new(), OPEN_END.to_string()),
+    }
+}
+
+/// Reflection of the period `at` falls in; `reflections` are newest first, as periods are
+fn reflection_at(reflections: &mut [Reflection], at: DateTime<Local>) -> Option<&mut Reflection> {
+    let index = reflections.partition_point(|r| r.period.start > at);
+    reflections
+        .get_mut(index)
+        .filter(|r| r.period.end.is_none_or(|end| at < end))
+}
+
+fn in_period(at: DateTime<Local>, period_start: Option<DateTime<Local>>) -> bool {
+    period_start.is_none_or(|start| at >= start)
+}
+
+/// An expense can be filed under any active category except Savings, which only pays
+/// through overflow
+fn validate_expense_category(categories: &[Category], category_id: i64) -> Result<()> {
+    let category = categories
+        .iter()
+        .find(|c| c.id == category_id)
+        .ok_or_else(|| AppError::CategoryNotFound(category_id.to_string()))?;
+    if category.is_savings {
+        return Err(AppError::Validation(format!(
+            "Expenses can't be filed under {}; it only pays through overflow",
+            category.name
+        )));
+    }
+    if category.archived {
+        return Err(AppError::Validation(format!(
+            "{} is archived",
+            category.name
+        )));
+    }
+    Ok(())
+}
+
+fn validate_not_future(at: DateTime<Local>) -> Result<()> {
+    if at > Local::now() {
+        return Err(AppError::Validation(format!(
+            "{} is in the future",
+            at.format("%Y-%m-%d %H:%M")
+        )));
+    }
+    Ok(())
+}
+
+/// Pay `amount` from Savings alone, never further down its overflow chain. Expenses in a
+/// closed period are paid this way: that period's leftover was swept there.
+fn plan_from_savings(balances: &[CategoryBalance], amount: Decimal) -> Result<Vec<(i64, Decimal)>> {
+    let savings = balances
+        .iter()
+        .find(|b| b.is_savings)
+        .ok_or(AppError::Config("No savings category".into()))?;
+    let remaining = savings.available - savings.spent;
+    if remaining < amount {
+        return Err(AppError::InsufficientFunds {
+            category: savings.category_name.clone(),
+            requested: amount,
+            available: remaining.max(Decimal::ZERO),
+        });
+    }
+    Ok(vec![(savings.category_id, amount)])
+}
+
+/// A refund whose expense sits in a closed period goes back to Savings, which paid for that
+/// period's leftovers. Recorded against Savings `spent`, like any current-period credit.
+fn savings_credit(balances: &[CategoryBalance], amount: Decimal) -> Result<Vec<(i64, Decimal)>> {
+    let savings = balances
+        .iter()
+        .find(|b| b.is_savings)
+        .ok_or(AppError::Config("No savings category".into()))?;
+    Ok(vec![(savings.category_id, -amount)])
+}
+
+/// Credits for refunding `amount` of `original`, as negative `spent` changes.
+///
+/// Walks the categories the expense drained in reverse (last hop of the overflow chain
+/// first), each up to what it covered minus what earlier refunds (`refunded`) gave back.
+/// Legacy rows without allocations fall back to `plan_reversal`.
+fn plan_refund(
+    categories: &[Category],
+    balances: &[CategoryBalance],
+    original: &crate::models::Transaction,
+    refunded: &[(i64, Decimal)],
+    amount: Decimal,
+) -> Result<Vec<(i64, Decimal)>> {
+    let credits = if original.allocations.is_empty() {
+        plan_reversal(categories, balances, original.category_id, amount)
+    } else {
+        let mut remaining = amount;
+        let mut credits = Vec::new();
+        for allocation in original.allocations.iter().rev() {
+            let already: Decimal = refunded
+                .iter()
+                .filter(|(id, _)| *id == allocation.category_id)
+                .map(|(_, credit)| *credit)
+                .sum();
+            let credit = (allocation.amount - already).min(remaining);
+            if credit > Decimal::ZERO {
+                credits.push((allocation.category_id, credit));
+                remaining -= credit;
+            }
+        }
+        credits
+    };
+
+    let covered: Decimal = credits.iter().map(|(_, credit)| *credit).sum();
+    if covered < amount {
+        return Err(AppError::Validation(format!(
+            "Only {} of this expense is left to refund",
+            covered
+        )));
+    }
+    Ok(credits
+        .into_iter()
+        .map(|(cat_id, credit)| (cat_id, -credit))
+        .collect())
+}
+
+/// Add `delta` to what Savings holds, settling a change to a closed period.
+async fn adjust_savings(
+    conn: &libsql::Connection,
+    balances: &mut [CategoryBalance],
+    delta: Decimal,
+) -> Result<()> {
+    let savings = balances
+        .iter_mut()
+        .find(|b| b.is_savings)
+        .ok_or(AppError::Config("No savings category".into()))?;
+    savings.available += delta;
+
+    conn.execute(
+        "UPDATE category_balances SET available = ? WHERE category_id = ?",
+        [
+            savings.available.to_string(),
+            savings.category_id.to_string(),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Walk the overflow chain from `category_id`, returning how much each category covers.
+fn plan_deductions(
+    categories: &[Category],
+    balances: &[CategoryBalance],
+    category_id: i64,
+    amount: Decimal,
+) -> Result<Vec<(i64, Decimal)>> {
+    // Fails up front on a cyclic chain instead of walking it forever
+    overflow_chain(categories, category_id)?;
+
+    let mut remaining_amount_to_cover = amount;
+    let mut current_cat_id = category_id;
+    let mut updates: Vec<(i64, Decimal)> = Vec::new();
+
+    loop {
+        let cat_bal = balances
+            .iter()
+            .find(|b| b.category_id == current_cat_id)
+            .ok_or(AppError::Config("Balance sync error".into()))?;
+        let current_remaining = cat_bal.available - cat_bal.spent;
+
+        if current_remaining >= remaining_amount_to_cover {
+            updates.push((current_cat_id, remaining_amount_to_cover));
+            break;
+        } else {
+            if current_remaining > Decimal::ZERO {
+                updates.push((current_cat_id, current_remaining));
+                remaining_amount_to_cover -= current_remaining;
+            }
+
+            let current_cat_config = categories
+                .iter()
+                .find(|c| c.id == current_cat_id)
+                .ok_or(AppError::CategoryNotFound(current_cat_id.to_string()))?;
+            if let Some(next_id) = current_cat_config.overflow_to_id {
+                current_cat_id = next_id;
+            } else {
+                let category = categories
+                    .iter()
+                    .find(|c| c.id == category_id)
+                    .map(|c| c.name.to_string())
+                    .unwrap_or_default();
+                return Err(AppError::InsufficientFunds {
+                    category,
+                    requested: amount,
+                    available: amount - remaining_amount_to_cover,
+                });
+            }
+        }
+    }
+
+    Ok(updates)
+}
+
+/// Charges for an expense moved into a new period by a backdated payday. It was already
+/// paid, so nothing is rejected: whatever its overflow chain can't cover is charged to
+/// Savings, which may go negative. Returns the charges and how far Savings was overdrawn.
+fn plan_moved_expense(
+    categories: &[Category],
+    balances: &[CategoryBalance],
+    category_id: i64,
+    amount: Decimal,
+) -> Result<(Vec<(i64, Decimal)>, Decimal)> {
+    let mut updates: Vec<(i64, Decimal)> = Vec::new();
+    let mut left = amount;
+    for cat_id in overflow_chain(categories, category_id)? {
+        if left <= Decimal::ZERO {
+            break;
+        }
+        let bal = balances
+            .iter()
+            .find(|b| b.category_id == cat_id)
+            .ok_or(AppError::Config("Balance sync error".into()))?;
+        let covered = (bal.available - bal.spent).min(left);
+        if covered > Decimal::ZERO {
+            updates.push((cat_id, covered));
+            left -= covered;
+        }
+    }
+    if left <= Decimal::ZERO {
+        return Ok((updates, Decimal::ZERO));
+    }
+
+    let savings = balances
+        .iter()
+        .find(|b| b.is_savings)
+        .ok_or(AppError::Config("No savings category".into()))?;
+    let savings_left = match updates
+        .iter_mut()
+        .find(|(id, _)| *id == savings.category_id)
+    {
+        Some((_, charge)) => {
+            *charge += left;
+            Decimal::ZERO
+        }
+        None => {
+            updates.push((savings.category_id, left));
+            (savings.available - savings.spent).max(Decimal::ZERO)
+        }
+    };
+    Ok((updates, (left - savings_left).max(Decimal::ZERO)))
+}
+
+/// Amounts to credit back when undoing `transaction`: its recorded allocations, or a
+/// best-effort walk of the overflow chain for rows written before they were tracked.
+fn reversal_for(
+    categories: &[Category],
+    balances: &[CategoryBalance],
+    transaction: &crate::models::Transaction,
+) -> Vec<(i64, Decimal)> {
+    if transaction.allocations.is_empty() {
+        plan_reversal(
+            categories,
+            balances,
+            transaction.category_id,
+            transaction.amount,
+        )
+    } else {
+        transaction
+            .allocations
+            .iter()
+            .map(|a| (a.category_id, a.amount))
+            .collect()
+    }
+}
+
+/// Credit `amount` back along the overflow chain of `category_id`.
+///
+/// Used for legacy rows without allocations: gives back to the last hop of the chain first
+/// (the reverse of the order it was drained in) and never takes `spent` below zero.
+fn plan_reversal(
+    categories: &[Category],
+    balances: &[CategoryBalance],
+    category_id: i64,
+    amount: Decimal,
+) -> Vec<(i64, Decimal)> {
+    let mut chain = vec![category_id];
+    let mut current = category_id;
+    while let Some(next) = categories
+        .iter()
+        .find(|c| c.id == current)
+        .and_then(|c| c.overflow_to_id)
+    {
+        if chain.contains(&next) {
+            break;
+        }
+        chain.push(next);
+        current = next;
+    }
+
+    let mut remaining = amount;
+    let mut credits = Vec::new();
+    for cat_id in chain.into_iter().rev() {
+        if remaining <= Decimal::ZERO {
+            break;
+        }
+        let spent = balances
+            .iter()
+            .find(|b| b.category_id == cat_id)
+            .map(|b| b.spent)
+            .unwrap_or_default();
+        let credit = spent.min(remaining);
+        if credit > Decimal::ZERO {
+            credits.push((cat_id, credit));
+            remaining -= credit;
+        }
+    }
+    credits
+}
+
+/// Replace a transaction's tags with the `#tags` found in its description
+async fn sync_tags(
+    conn: &libsql::Connection,
+    transaction_id: i64,
+    description: &str,
+) -> Result<()> {
+    conn.execute(
+        "DELETE FROM transaction_tags WHERE transaction_id = ?",
+        [transaction_id],
+    )
+    .await?;
+    for tag in parse_tags(description) {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (?)",
+            [tag.clone()],
+        )
+        .await?;
+        conn.execute(
+            "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+             SELECT ?, id FROM tags WHERE name = ?",
+            libsql::params![transaction_id, tag],
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Point an expense (and its refunds) at the payee named by its description, registering
+/// the payee on first use
+async fn sync_payee(
+    conn: &libsql::Connection,
+    transaction_id: i64,
+    description: &str,
+) -> Result<()> {
+    let mut rows = conn.query("SELECT name FROM payees", ()).await?;
+    let mut known: Vec<String> = Vec::new();
+    while let Some(row) = rows.next().await? {
+        known.push(row.get(0)?);
+    }
+    let payee_id: Option<i64> = match match_payee(description, known.iter().map(String::as_str)) {
+        Some(name) => {
+            conn.execute(
+                "INSERT OR IGNORE INTO payees (name) VALUES (?)",
+                [name.clone()],
+            )
+            .await?;
+            conn.query("SELECT id FROM payees WHERE name = ?", [name])
+                .await?
+                .next()
+                .await?
+                .map(|row| row.get(0))
+                .transpose()?
+        }
+        None => None,
+    };
+    conn.execute(
+        "UPDATE transactions SET payee_id = ?1 WHERE id = ?2 OR refund_of_id = ?2",
+        libsql::params![payee_id, transaction_id],
+    )
+    .await?;
+    Ok(())
+}
+
+async fn insert_allocations(
+    conn: &libsql::Connection,
+    transaction_id: i64,
+    allocations: &[(i64, Decimal)],
+) -> Result<()> {
+    for (cat_id, amount) in allocations {
+        conn.execute(
+            "INSERT INTO transaction_allocations (transaction_id, category_id, amount) VALUES (?, ?, ?)",
+            [transaction_id.to_string(), cat_id.to_string(), amount.to_string()],
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Move a recurring rule's `next_due` past the occurrence it points at
+async fn advance_rule(conn: &libsql::Connection, rule: &RecurringRule) -> Result<()> {
+    let next_due = rule
+        .cadence
+        .next_after(rule.starts_at, rule.next_due)
+        .ok_or_else(|| {
+            AppError::Validation(format!("{} has no further occurrences", rule.label()))
+        })?;
+
+    conn.execute(
+        "UPDATE recurring_rules SET next_due = ? WHERE id = ?",
+        libsql::params![next_due.to_rfc3339(), rule.id],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Add `sign * amount` to each category's `spent`, keeping the in-memory balances in step.
+async fn apply_spent(
+    conn: &libsql::Connection,
+    balances: &mut [CategoryBalance],
+    changes: &[(i64, Decimal)],
+    sign: Decimal,
+) -> Result<()> {
+    for (cat_id, amount) in changes {
+        let bal = balances
+            .iter_mut()
+            .find(|b| b.category_id == *cat_id)
+            .ok_or(AppError::Config("Balance sync error".into()))?;
+        bal.spent += sign * amount;
+
+        conn.execute(
+            "UPDATE category_balances SET spent = ? WHERE category_id = ?",
+            [bal.spent.to_string(), cat_id.to_string()],
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+// Add strict-compatible inner structs if needed for other methods, or keep them separate?
+// For transactions we do manual extraction (`transaction_from_row`) which is safer heavily.
+
+#[derive(serde::Deserialize)]
+struct CategoryRow {
+    id: i64,
+    name: String,
+    limit_percentage: Decimal,
+    fixed_amount: Option<String>,
+    overflow_to_id: Option<i64>,
+    is_savings: i64,
+    archived: i64,
+}
 
 #[derive(serde::Deserialize)]
 struct BalanceRow {
     category_id: i64,
     category_name: String,
+    is_savings: i64,
     available: Decimal,
     allocated: Decimal,
     spent: Decimal,
@@ -441,3 +2816,306 @@ struct BalanceRow {
     #[allow(dead_code)]
     last_updated: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days_ago(days: i64) -> DateTime<Local> {
+        Local::now() - TimeDelta::days(days)
+    }
+
+    fn dec(value: i64) -> Decimal {
+        Decimal::from(value)
+    }
+
+    /// A fresh ledger with the seeded categories and 1000 of income three days ago
+    async fn ledger() -> DB {
+        let db = DB::new(Path::new(":memory:")).await.unwrap();
+        db.add_funds(dec(1000), cash(&db).await, None, None, days_ago(3))
+            .await
+            .unwrap();
+        db
+    }
+
+    async fn cash(db: &DB) -> i64 {
+        db.get_accounts().await.unwrap()[0].id
+    }
+
+    async fn category(db: &DB, name: &str) -> i64 {
+        db.get_category_by_name(name).await.unwrap().id
+    }
+
+    async fn spent(db: &DB, name: &str) -> Decimal {
+        db.get_category_balances()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|b| b.category_name == name)
+            .unwrap()
+            .spent
+    }
+
+    async fn last_transaction(db: &DB) -> i64 {
+        db.conn
+            .query("SELECT MAX(id) FROM transactions", ())
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap()
+            .get(0)
+            .unwrap()
+    }
+
+    async fn spend(db: &DB, name: &str, amount: i64, at: DateTime<Local>) -> i64 {
+        let category_id = category(db, name).await;
+        db.create_transaction(category_id, dec(amount), None, cash(db).await, at)
+            .await
+            .unwrap();
+        last_transaction(db).await
+    }
+
+    #[tokio::test]
+    async fn refund_credits_the_last_overflow_hop_first() {
+        let db = ledger().await;
+        // Needs holds 300, so 50 overflows to Unexpected
+        let id = spend(&db, "Needs", 350, days_ago(2)).await;
+        assert_eq!(spent(&db, "Needs").await, dec(300));
+        assert_eq!(spent(&db, "Unexpected").await, dec(50));
+
+        db.create_refund(id, dec(30), None, days_ago(1))
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(300));
+        assert_eq!(spent(&db, "Unexpected").await, dec(20));
+
+        db.create_refund(id, dec(40), None, days_ago(1))
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(280));
+        assert_eq!(spent(&db, "Unexpected").await, dec(0));
+        assert!(
+            db.create_refund(id, dec(281), None, days_ago(1))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn refunded_expense_keeps_its_category_and_date() {
+        let db = ledger().await;
+        let id = spend(&db, "Needs", 100, days_ago(2)).await;
+        db.create_refund(id, dec(40), None, days_ago(1))
+            .await
+            .unwrap();
+
+        let wants = category(&db, "Wants").await;
+        let needs = category(&db, "Needs").await;
+        assert!(
+            db.update_transaction(id, wants, dec(100), None, days_ago(2))
+                .await
+                .is_err()
+        );
+        assert!(
+            db.update_transaction(id, needs, dec(100), None, days_ago(4))
+                .await
+                .is_err()
+        );
+        assert_eq!(spent(&db, "Needs").await, dec(60));
+        assert_eq!(spent(&db, "Wants").await, dec(0));
+
+        let currency = db.get_currency().await.unwrap();
+        let err = db
+            .update_transaction(id, needs, dec(30), None, days_ago(2))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&currency.format(dec(40))));
+    }
+
+    #[tokio::test]
+    async fn editing_a_refunded_expense_only_moves_the_difference() {
+        let db = ledger().await;
+        let needs = category(&db, "Needs").await;
+        let id = spend(&db, "Needs", 350, days_ago(2)).await;
+        let original = db.get_transaction(id).await.unwrap();
+        db.create_refund(id, dec(30), None, days_ago(1))
+            .await
+            .unwrap();
+
+        // 30 comes off what Unexpected still covers after the refund, the rest off Needs
+        db.update_transaction(id, needs, dec(320), None, original.created_at)
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(290));
+        assert_eq!(spent(&db, "Unexpected").await, dec(0));
+
+        db.update_transaction(id, needs, dec(400), None, original.created_at)
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(300));
+        assert_eq!(spent(&db, "Unexpected").await, dec(70));
+
+        // Refunding the rest leaves nothing spent anywhere
+        db.create_refund(id, dec(370), None, days_ago(1))
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(0));
+        assert_eq!(spent(&db, "Unexpected").await, dec(0));
+    }
+
+    #[tokio::test]
+    async fn split_lines_are_refunded_separately() {
+        let db = ledger().await;
+        let needs = category(&db, "Needs").await;
+        let wants = category(&db, "Wants").await;
+        db.create_split_transaction(
+            &[(needs, dec(100)), (wants, dec(40))],
+            None,
+            cash(&db).await,
+            days_ago(2),
+        )
+        .await
+        .unwrap();
+        let wants_line = last_transaction(&db).await;
+        assert_eq!(
+            db.get_transaction(wants_line).await.unwrap().category_id,
+            wants
+        );
+
+        db.create_refund(wants_line, dec(40), None, days_ago(1))
+            .await
+            .unwrap();
+        assert_eq!(spent(&db, "Needs").await, dec(100));
+        assert_eq!(spent(&db, "Wants").await, dec(0));
+        assert!(
+            db.create_refund(wants_line, dec(1), None, days_ago(1))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn backdated_payday_moves_later_expenses_into_its_period() {
+        let db = ledger().await;
+        spend(&db, "Needs", 100, days_ago(1)).await;
+        assert_eq!(spent(&db, "Needs").await, dec(100));
+
+        // A payday between the first income and the expense starts the period it belongs to
+        let overdrawn = db
+            .add_funds(dec(1000), cash(&db).await, None, None, days_ago(2))
+            .await
+            .unwrap();
+        assert_eq!(overdrawn, dec(0));
+        assert_eq!(spent(&db, "Needs").await, dec(100));
+        let balances = db.get_category_balances().await.unwrap();
+        let savings = balances.iter().find(|b| b.is_savings).unwrap();
+        // Its own half plus the old period's half, all of which rolled over unspent
+        assert_eq!(savings.available, dec(1500));
+    }
+
+    #[tokio::test]
+    async fn backdated_payday_charges_savings_what_the_chain_cannot_cover() {
+        let db = ledger().await;
+        spend(&db, "Needs", 450, days_ago(1)).await;
+
+        // The small payday gives Needs 30 and Unexpected 10; Savings, holding the old
+        // period's rollover, pays the rest
+        let overdrawn = db
+            .add_funds(dec(100), cash(&db).await, None, None, days_ago(2))
+            .await
+            .unwrap();
+        assert_eq!(overdrawn, dec(0));
+        assert_eq!(spent(&db, "Needs").await, dec(30));
+        assert_eq!(spent(&db, "Unexpected").await, dec(10));
+        assert_eq!(spent(&db, "Savings").await, dec(410));
+    }
+
+    #[tokio::test]
+    async fn backfilled_payees_match_like_new_expenses() {
+        let db = ledger().await;
+        let needs = category(&db, "Needs").await;
+        let account = cash(&db).await;
+        // Expenses from before payees existed
+        for description in [
+            "Indomaret - Jl. Sudirman",
+            "Indomaret  snacks #food",
+            "indomaret",
+            "Kopi Kenangan #work #coffee",
+            "#untagged",
+        ] {
+            db.conn
+                .execute(
+                    "INSERT INTO transactions (category_id, amount, description, created_at, account_id) VALUES (?, '10', ?, ?, ?)",
+                    libsql::params![needs, description, days_ago(2).to_rfc3339(), account],
+                )
+                .await
+                .unwrap();
+        }
+        let first = last_transaction(&db).await - 4;
+        db.conn
+            .execute(
+                "INSERT INTO transactions (category_id, amount, description, created_at, account_id, refund_of_id) VALUES (?, '-5', '', ?, ?, ?)",
+                libsql::params![needs, days_ago(1).to_rfc3339(), account, first + 1],
+            )
+            .await
+            .unwrap();
+
+        db.backfill_payees().await.unwrap();
+        let mut names: Vec<String> = db
+            .get_payees()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Indomaret", "Kopi Kenangan"]);
+
+        let mut rows = db
+            .conn
+            .query(
+                "SELECT COUNT(DISTINCT payee_id), COUNT(*) FROM transactions WHERE id <= ? OR refund_of_id IS NOT NULL",
+                [first + 2],
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        // The three Indomaret expenses and the refund share one payee
+        assert_eq!(row.get::<i64>(0).unwrap(), 1);
+        assert_eq!(row.get::<i64>(1).unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn rule_started_in_the_past_is_first_due_after_now() {
+        let db = ledger().await;
+        let starts_at = days_ago(730);
+        let rule = RecurringRule {
+            id: 0,
+            kind: RecurringKind::Expense,
+            amount: dec(5),
+            category_id: Some(category(&db, "Needs").await),
+            account_id: Some(cash(&db).await),
+            description: Some("Coffee".into()),
+            cadence: "daily".parse().unwrap(),
+            starts_at,
+            next_due: starts_at,
+            requires_confirmation: false,
+            active: true,
+        };
+        let id = db.create_recurring_rule(&rule).await.unwrap();
+
+        let stored = db
+            .get_recurring_rules()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|r| r.id == id)
+            .unwrap();
+        assert_eq!(stored.starts_at, starts_at);
+        assert!(stored.next_due > Local::now());
+        assert!(stored.next_due <= Local::now() + TimeDelta::days(1));
+        assert_eq!(stored.next_due.time(), starts_at.time());
+    }
+}
@@ -14,14 +14,31 @@ pub enum AppError {
     #[error("Configuration error: {0}")]
     Config(String),
 
-    #[error("Insufficient funds in category and overflow chain")]
-    InsufficientFunds,
+    #[error(
+        "Insufficient funds: {requested} requested but only {available} left in {category} and its overflow chain"
+    )]
+    InsufficientFunds {
+        category: String,
+        requested: rust_decimal::Decimal,
+        available: rust_decimal::Decimal,
+    },
 
     #[error("Category not found: {0}")]
     CategoryNotFound(String),
 
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
     #[error("Deserialization error: {0}")]
     Deserialization(String),
+
+    #[error("Migration failed: {0}")]
+    Migration(String),
+
+    #[error(
+        "Database schema version {found} is newer than this build supports ({supported}); please upgrade Ebisu"
+    )]
+    SchemaTooNew { found: i64, supported: i64 },
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
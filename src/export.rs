@@ -0,0 +1,190 @@
+//! CSV export of transactions, fund entries and category balances.
+//!
+//! Column names are part of the format: add new columns at the end, never rename or reorder.
+//! Dates are RFC3339 (ISO 8601 with offset) and amounts plain decimal strings, so the files
+//! read the same whatever the ledger currency.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::database::DB;
+use crate::error::{AppError, Result};
+use crate::models::DateRange;
+
+/// Usage line of the `export` subcommand
+const USAGE: &str =
+    "Usage: ebisu-tui export [--range today|7d|30d|year|5y|all] [--out DIR] [--db PATH]";
+
+/// What an export wrote
+#[derive(Debug)]
+pub struct ExportSummary {
+    pub dir: PathBuf,
+    pub transactions: usize,
+    pub funds: usize,
+}
+
+#[derive(Serialize)]
+struct TransactionRecord {
+    id: i64,
+    date: String,
+    category: String,
+    amount: String,
+    description: String,
+    payee: String,
+    account: String,
+    /// Tag names separated by `;`
+    tags: String,
+    refund_of: Option<i64>,
+    split_id: Option<i64>,
+    /// `category=amount` per category that paid, separated by `;`
+    covered_by: String,
+}
+
+#[derive(Serialize)]
+struct FundRecord {
+    id: i64,
+    date: String,
+    amount: String,
+    account: String,
+    source: String,
+    note: String,
+    rolled_over: String,
+}
+
+#[derive(Serialize)]
+struct BalanceRecord {
+    category: String,
+    is_savings: bool,
+    allocated: String,
+    available: String,
+    spent: String,
+    remaining: String,
+}
+
+/// Write `transactions.csv` and `funds.csv` for the range (all time for `None`), plus
+/// `balances.csv` with the current period's category balances, into `dir`.
+pub async fn export_csv(db: &DB, range: Option<DateRange>, dir: &Path) -> Result<ExportSummary> {
+    std::fs::create_dir_all(dir)?;
+    let accounts = db.get_accounts().await?;
+    let account_name = |id: Option<i64>| {
+        id.and_then(|id| accounts.iter().find(|a| a.id == id))
+            .map(|a| a.name.clone())
+            .unwrap_or_default()
+    };
+
+    // Oldest first reads naturally in a spreadsheet
+    let mut transactions = db.get_transactions_in(range).await?;
+    transactions.reverse();
+    let mut writer = csv::Writer::from_path(dir.join("transactions.csv"))?;
+    for t in &transactions {
+        writer.serialize(TransactionRecord {
+            id: t.id,
+            date: iso_date(t.created_at),
+            category: t.category_name.clone().unwrap_or_default(),
+            amount: t.amount.to_string(),
+            description: t.description.clone().unwrap_or_default(),
+            payee: t.payee.clone().unwrap_or_default(),
+            account: account_name(t.account_id),
+            tags: t.tags.join(";"),
+            refund_of: t.refund_of_id,
+            split_id: t.split_id,
+            covered_by: t
+                .allocations
+                .iter()
+                .map(|a| {
+                    format!(
+                        "{}={}",
+                        a.category_name.as_deref().unwrap_or_default(),
+                        a.amount
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(";"),
+        })?;
+    }
+    writer.flush()?;
+
+    let funds = db.get_funds(range).await?;
+    let mut writer = csv::Writer::from_path(dir.join("funds.csv"))?;
+    for f in &funds {
+        writer.serialize(FundRecord {
+            id: f.id,
+            date: iso_date(f.added_at),
+            amount: f.amount.to_string(),
+            account: account_name(f.account_id),
+            source: f.source.clone().unwrap_or_default(),
+            note: f.note.clone().unwrap_or_default(),
+            rolled_over: f.remaining_balance_rolled.to_string(),
+        })?;
+    }
+    writer.flush()?;
+
+    let mut writer = csv::Writer::from_path(dir.join("balances.csv"))?;
+    for b in db.get_category_balances().await? {
+        writer.serialize(BalanceRecord {
+            remaining: (b.available - b.spent).to_string(),
+            category: b.category_name,
+            is_savings: b.is_savings,
+            allocated: b.allocated.to_string(),
+            available: b.available.to_string(),
+            spent: b.spent.to_string(),
+        })?;
+    }
+    writer.flush()?;
+
+    Ok(ExportSummary {
+        dir: dir.to_path_buf(),
+        transactions: transactions.len(),
+        funds: funds.len(),
+    })
+}
+
+/// `ebisu-tui export ...`: export the configured ledger without starting the TUI. `args`
+/// come without `--db`, which `Config::load` already applied.
+pub async fn run_cli(config: &Config, args: &[String]) -> Result<()> {
+    let mut range = None;
+    let mut out = PathBuf::from(".");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--range" => range = parse_range(iter.next().map(String::as_str).unwrap_or_default())?,
+            "--out" => match iter.next() {
+                Some(dir) => out = PathBuf::from(dir),
+                None => return Err(AppError::Config(USAGE.into())),
+            },
+            _ => return Err(AppError::Config(USAGE.into())),
+        }
+    }
+
+    let db = DB::new(&config.db_path).await?;
+    let summary = export_csv(&db, range, &out).await?;
+    println!(
+        "Exported {} transactions and {} fund entries ({}) from {} to {}",
+        summary.transactions,
+        summary.funds,
+        range.map_or("all time".to_string(), |r| r.title().to_string()),
+        config.db_path.display(),
+        summary.dir.display()
+    );
+    Ok(())
+}
+
+/// `--range` value: a `DateRange` or `all`
+fn parse_range(value: &str) -> Result<Option<DateRange>> {
+    match value {
+        "today" => Ok(Some(DateRange::Today)),
+        "7d" => Ok(Some(DateRange::Last7Days)),
+        "30d" | "month" => Ok(Some(DateRange::Month)),
+        "year" => Ok(Some(DateRange::Year)),
+        "5y" => Ok(Some(DateRange::FiveYears)),
+        "all" => Ok(None),
+        _ => Err(AppError::Config(USAGE.into())),
+    }
+}
+
+fn iso_date(at: DateTime<Local>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+}
@@ -0,0 +1,883 @@
+//! Bank and e-wallet statement import.
+//!
+//! A statement is read into `ImportRow`s for the Import tab preview; nothing touches the
+//! ledger until the user commits, and then every kept row goes through
+//! `DB::create_transaction` like an expense typed on Add Expense. OFX and QIF rows carry the
+//! bank's transaction id, which `DB::import_transaction` records so a statement imported twice
+//! only counts once.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime};
+use csv::StringRecord;
+use rust_decimal::Decimal;
+
+use crate::error::{AppError, Result};
+use crate::models::{
+    Category, ImportProfile, Payee, STATEMENT_DUPLICATE_WINDOW, SignConvention, Transaction,
+    find_duplicate, match_payee,
+};
+
+/// Date formats tried for QIF: US order first, as Quicken writes it
+const QIF_DATE_FORMATS: [&str; 5] = ["%m/%d/%Y", "%m/%d/%y", "%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y"];
+
+/// What happens to a statement row on commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowStatus {
+    /// Recorded as an expense on commit
+    Ready,
+    /// Left out by the user
+    Skipped,
+    /// Money in; fund entries are recorded on Add Funds, where they get allocated
+    Income,
+    /// Could not be read, with the reason
+    Invalid(String),
+    /// Recorded by this commit
+    Imported,
+    /// Its transaction id was recorded by an earlier import
+    AlreadyImported,
+    /// Rejected on commit, with the error; can be retried
+    Failed(String),
+}
+
+/// One statement row in the import preview
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub date: Option<DateTime<Local>>,
+    pub description: String,
+    /// Money out is positive, money in negative
+    pub amount: Decimal,
+    /// Category the expense is filed under
+    pub category_id: Option<i64>,
+    /// Bank transaction id (OFX FITID, derived for QIF); `None` for CSV rows
+    pub import_id: Option<String>,
+    /// Recorded expense the row looks like (several lines for a split receipt)
+    pub duplicate_of: Option<Vec<Transaction>>,
+    pub status: RowStatus,
+}
+
+/// Read a statement, picking the format from the file extension: `.ofx`/`.qfx`, `.qif`, or
+/// CSV with `profile`. QIF only falls back on the profile's date format and decimal separator
+/// for files its own defaults can't read.
+pub fn read_statement(profile: Option<&ImportProfile>, path: &Path) -> Result<Vec<ImportRow>> {
+    let bytes = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match (extension.as_str(), profile) {
+        ("ofx" | "qfx", _) => parse_ofx(&text),
+        ("qif", _) => parse_qif(&text, profile),
+        (_, Some(profile)) => parse_csv(profile, &text),
+        (_, None) => Err(AppError::Validation(
+            "CSV files need an import profile (n)".into(),
+        )),
+    }
+}
+
+/// Parse CSV statement text. The first row after `skip_lines` is a header unless every
+/// column of the profile is referenced by number. Blank rows are dropped; rows that can't
+/// be read are kept as `Invalid` so the preview shows what was left out.
+pub fn parse_csv(profile: &ImportProfile, text: &str) -> Result<Vec<ImportRow>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let body = text
+        .split_inclusive('\n')
+        .skip(profile.skip_lines)
+        .collect::<String>();
+    let has_header = [
+        Some(&profile.date_column),
+        Some(&profile.amount_column),
+        Some(&profile.description_column),
+        profile.second_column.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|column| column_number(column).is_none());
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(profile.delimiter as u8)
+        .has_headers(has_header)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let headers = if has_header {
+        Some(reader.headers()?.clone())
+    } else {
+        None
+    };
+    let headers = headers.as_ref();
+
+    let date_at = column_index(&profile.date_column, headers)?;
+    let amount_at = column_index(&profile.amount_column, headers)?;
+    let description_at = column_index(&profile.description_column, headers)?;
+    let second_at = match &profile.second_column {
+        Some(column) if profile.sign_convention.uses_second_column() => {
+            Some(column_index(column, headers)?)
+        }
+        _ => None,
+    };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |at: usize| record.get(at).unwrap_or_default().trim();
+
+        let amount = signed_amount(
+            profile,
+            field(amount_at),
+            second_at.map(field).unwrap_or_default(),
+        );
+        rows.push(statement_row(
+            field(date_at),
+            parse_date(field(date_at), &profile.date_format),
+            amount,
+            field(description_at),
+            None,
+        ));
+    }
+    Ok(rows)
+}
+
+/// Parse an OFX/QFX statement, SGML (1.x) or XML (2.x). Amounts are signed with money out
+/// below zero; the import id is the account id plus FITID.
+pub fn parse_ofx(text: &str) -> Result<Vec<ImportRow>> {
+    if !text.to_uppercase().contains("<OFX>") {
+        return Err(AppError::Validation(
+            "Not an OFX file: no <OFX> element".into(),
+        ));
+    }
+
+    let mut rows = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    let mut rest = text;
+    let mut account = String::new();
+    while let Some(start) = rest.find("<STMTTRN>") {
+        // The transaction list follows its statement's account, so the last ACCTID wins
+        if let Some(id) = ofx_value(&rest[..start], "ACCTID") {
+            account = id;
+        }
+        let block = &rest[start + "<STMTTRN>".len()..];
+        let end = block.find("</STMTTRN>").unwrap_or(block.len());
+        let (block, after) = (&block[..end], &block[end..]);
+        rest = after;
+
+        let date_text = ofx_value(block, "DTPOSTED").unwrap_or_default();
+        let amount_text = ofx_value(block, "TRNAMT").unwrap_or_default();
+        let amount = parse_amount(&amount_text, '.')
+            .map(|a| -a)
+            .ok_or_else(|| format!("unreadable amount {:?}", amount_text));
+        let description = ofx_value(block, "NAME")
+            .filter(|name| !name.is_empty())
+            .or_else(|| ofx_value(block, "MEMO"))
+            .unwrap_or_default();
+        let fitid = match ofx_value(block, "FITID").filter(|id| !id.is_empty()) {
+            Some(fitid) => fitid,
+            None => derived_id(&mut seen, &[&date_text, &amount_text, &description]),
+        };
+        rows.push(statement_row(
+            &date_text,
+            parse_ofx_date(&date_text),
+            amount,
+            &description,
+            Some(format!("ofx:{}:{}", account, fitid)),
+        ));
+    }
+    Ok(rows)
+}
+
+/// Parse a QIF bank, cash or credit card export. Entries carry no id, so one is derived from
+/// date, amount, number and payee (counting repeats), which is stable for the same file.
+///
+/// Amounts use `.` decimals and dates `QIF_DATE_FORMATS`. A CSV profile describes some other
+/// file, so it is only tried when those defaults leave rows unreadable (e.g. `T-25,00`), and
+/// only kept if it reads more of them.
+pub fn parse_qif(text: &str, profile: Option<&ImportProfile>) -> Result<Vec<ImportRow>> {
+    let rows = read_qif(text, '.', &QIF_DATE_FORMATS);
+    let invalid = |rows: &[ImportRow]| {
+        rows.iter()
+            .filter(|r| matches!(r.status, RowStatus::Invalid(_)))
+            .count()
+    };
+    let Some(profile) = profile.filter(|_| invalid(&rows) > 0) else {
+        return Ok(rows);
+    };
+    let mut formats = vec![profile.date_format.as_str()];
+    formats.extend(QIF_DATE_FORMATS);
+    let retried = read_qif(text, profile.decimal_separator, &formats);
+    Ok(if invalid(&retried) < invalid(&rows) {
+        retried
+    } else {
+        rows
+    })
+}
+
+fn read_qif(text: &str, decimal_separator: char, formats: &[&str]) -> Vec<ImportRow> {
+    let mut rows = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    let mut in_transactions = true;
+    let mut entry: Vec<(char, String)> = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end();
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.to_lowercase();
+            if let Some(kind) = header.strip_prefix("type:") {
+                in_transactions =
+                    matches!(kind.trim(), "bank" | "cash" | "ccard" | "oth a" | "oth l");
+            } else if header.starts_with("account") {
+                in_transactions = false;
+            }
+            entry.clear();
+            continue;
+        }
+        if line != "^" {
+            let mut chars = line.chars();
+            if let Some(code) = chars.next() {
+                entry.push((code, chars.as_str().trim().to_string()));
+            }
+            continue;
+        }
+        if !in_transactions || entry.is_empty() {
+            entry.clear();
+            continue;
+        }
+
+        let value = |code: char| {
+            entry
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map(|(_, v)| v.as_str())
+                .unwrap_or_default()
+        };
+        let date_text = value('D');
+        let amount_text = if value('T').is_empty() {
+            value('U')
+        } else {
+            value('T')
+        };
+        let amount = qif_amount(amount_text, decimal_separator)
+            .map(|a| -a)
+            .ok_or_else(|| format!("unreadable amount {:?}", amount_text));
+        let description = if value('P').is_empty() {
+            value('M')
+        } else {
+            value('P')
+        };
+        let id = derived_id(
+            &mut seen,
+            &[date_text, amount_text, value('N'), description],
+        );
+        rows.push(statement_row(
+            date_text,
+            parse_qif_date(date_text, formats),
+            amount,
+            description,
+            Some(format!("qif:{}", id)),
+        ));
+        entry.clear();
+    }
+    rows
+}
+
+/// File each row under the category last used for its payee, or `fallback` for payees with
+/// no earlier expense. Only the given (expense) categories are used; the first one is the
+/// last resort.
+pub fn assign_categories(
+    rows: &mut [ImportRow],
+    payees: &[Payee],
+    categories: &[&Category],
+    fallback: Option<i64>,
+) {
+    let usable = |id: Option<i64>| id.filter(|id| categories.iter().any(|c| c.id == *id));
+    let default = usable(fallback).or(categories.first().map(|c| c.id));
+    for row in rows {
+        let last_used = match_payee(&row.description, payees.iter().map(|p| p.name.as_str()))
+            .and_then(|name| {
+                payees
+                    .iter()
+                    .find(|p| p.name.eq_ignore_ascii_case(&name))
+                    .and_then(|p| usable(p.last_category_id))
+            });
+        row.category_id = last_used.or(default);
+    }
+}
+
+/// Dates of the Ready rows widened by the duplicate window: the range of recorded expenses
+/// `mark_duplicates` needs
+pub fn duplicate_search_range(rows: &[ImportRow]) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    let dates = rows
+        .iter()
+        .filter(|r| r.status == RowStatus::Ready)
+        .filter_map(|r| r.date);
+    let first = dates.clone().min()?;
+    let last = dates.max()?;
+    Some((
+        first - STATEMENT_DUPLICATE_WINDOW,
+        last + STATEMENT_DUPLICATE_WINDOW,
+    ))
+}
+
+/// Skip the Ready rows that look like an expense already recorded, remembering the match so
+/// the preview can show it; `space` keeps such a row anyway. Returns how many were marked.
+pub fn mark_duplicates(rows: &mut [ImportRow], existing: &[Transaction]) -> usize {
+    let mut marked = 0;
+    for row in rows.iter_mut().filter(|r| r.status == RowStatus::Ready) {
+        let Some(date) = row.date else {
+            continue;
+        };
+        let description = Some(row.description.as_str()).filter(|d| !d.is_empty());
+        if let Some(entry) = find_duplicate(
+            existing,
+            row.amount,
+            date,
+            description,
+            STATEMENT_DUPLICATE_WINDOW,
+        ) {
+            row.duplicate_of = Some(entry.to_vec());
+            row.status = RowStatus::Skipped;
+            marked += 1;
+        }
+    }
+    marked
+}
+
+/// Preview row for a parsed date and amount (money out positive)
+fn statement_row(
+    date_text: &str,
+    date: Option<DateTime<Local>>,
+    amount: std::result::Result<Decimal, String>,
+    description: &str,
+    import_id: Option<String>,
+) -> ImportRow {
+    let status = match (date, &amount) {
+        (None, _) => RowStatus::Invalid(format!("unreadable date {:?}", date_text)),
+        (_, Err(reason)) => RowStatus::Invalid(reason.clone()),
+        (_, Ok(amount)) if amount.is_zero() => RowStatus::Invalid("zero amount".into()),
+        (_, Ok(amount)) if *amount < Decimal::ZERO => RowStatus::Income,
+        _ => RowStatus::Ready,
+    };
+    ImportRow {
+        date,
+        description: description.split_whitespace().collect::<Vec<_>>().join(" "),
+        amount: amount.unwrap_or_default(),
+        category_id: None,
+        import_id,
+        duplicate_of: None,
+        status,
+    }
+}
+
+/// Id for an entry without one: its fields, plus a counter when the same fields repeat
+fn derived_id(seen: &mut Vec<String>, fields: &[&str]) -> String {
+    let key = fields.join("|");
+    let repeats = seen.iter().filter(|k| **k == key).count();
+    seen.push(key.clone());
+    format!("{}#{}", key, repeats + 1)
+}
+
+/// Text of an OFX element: up to the next tag, entities decoded. Works for SGML, where
+/// elements are not closed, and XML alike.
+fn ofx_value(block: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = block.rfind(&open)? + open.len();
+    let value = block[start..].split('<').next().unwrap_or_default().trim();
+    Some(
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// OFX date: `YYYYMMDD`, optionally `HHMMSS[.XXX]` and a `[offset:TZ]` zone. A date alone is
+/// midday local time; a time without zone is GMT, as the spec says.
+fn parse_ofx_date(value: &str) -> Option<DateTime<Local>> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let date = NaiveDate::parse_from_str(digits.get(..8)?, "%Y%m%d").ok()?;
+    let Some(time) = digits.get(8..14) else {
+        return parse_date(&date.format("%Y-%m-%d").to_string(), "%Y-%m-%d");
+    };
+    let naive = date.and_time(chrono::NaiveTime::parse_from_str(time, "%H%M%S").ok()?);
+    let offset_hours = value
+        .split_once('[')
+        .and_then(|(_, zone)| zone.split([':', ']']).next())
+        .and_then(|hours| hours.trim().parse::<f64>().ok())
+        .unwrap_or(0.0);
+    let offset = chrono::FixedOffset::east_opt((offset_hours * 3600.0).round() as i32)?;
+    Some(
+        naive
+            .and_local_timezone(offset)
+            .single()?
+            .with_timezone(&Local),
+    )
+}
+
+/// QIF date with the first format that gives a plausible year. Quicken writes `10/ 1'26` for
+/// 2026, so apostrophes and padding spaces are normalized first.
+fn parse_qif_date(value: &str, formats: &[&str]) -> Option<DateTime<Local>> {
+    let value = value.replace('\'', "/").replace(' ', "");
+    formats
+        .iter()
+        .filter_map(|format| parse_date(&value, format))
+        .find(|at| at.year() >= 1970)
+}
+
+/// 1-based column number, if the reference is one
+fn column_number(reference: &str) -> Option<usize> {
+    reference
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .map(|n| n - 1)
+}
+
+/// Zero-based position of a column referenced by number or header name
+fn column_index(reference: &str, headers: Option<&StringRecord>) -> Result<usize> {
+    if let Some(index) = column_number(reference) {
+        return Ok(index);
+    }
+    headers
+        .and_then(|headers| {
+            headers
+                .iter()
+                .position(|name| name.trim().eq_ignore_ascii_case(reference.trim()))
+        })
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "No column {:?} in the file; check the profile's header names",
+                reference.trim()
+            ))
+        })
+}
+
+/// Statement date with the profile's format; date-only formats land at midday, like typed
+/// dates, and today's rows are capped at now
+fn parse_date(value: &str, format: &str) -> Option<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, format)
+                .ok()
+                .and_then(|d| d.and_hms_opt(12, 0, 0))
+        })?;
+    let at = naive.and_local_timezone(Local).earliest()?;
+    let now = Local::now();
+    Some(if at > now && at.date_naive() == now.date_naive() {
+        now
+    } else {
+        at
+    })
+}
+
+/// Amount of a row with money out positive, per the profile's sign convention
+fn signed_amount(
+    profile: &ImportProfile,
+    amount: &str,
+    second: &str,
+) -> std::result::Result<Decimal, String> {
+    let parse = |value: &str| {
+        parse_amount(value, profile.decimal_separator)
+            .ok_or_else(|| format!("unreadable amount {:?}", value))
+    };
+    match profile.sign_convention {
+        SignConvention::NegativeIsExpense => parse(amount).map(|a| -a),
+        SignConvention::PositiveIsExpense => parse(amount),
+        SignConvention::DebitCreditColumns => {
+            let debit = if amount.is_empty() {
+                Decimal::ZERO
+            } else {
+                parse(amount)?.abs()
+            };
+            let credit = if second.is_empty() {
+                Decimal::ZERO
+            } else {
+                parse(second)?.abs()
+            };
+            Ok(debit - credit)
+        }
+        SignConvention::DbCrMarker => {
+            // BCA-style statements put the marker after the amount: "50,000.00 DB"
+            let marker = if second.is_empty() { amount } else { second };
+            let value = parse(amount)?.abs();
+            match debit_marker(marker) {
+                Some(true) => Ok(value),
+                Some(false) => Ok(-value),
+                None => Err(format!("no DB/CR marker in {:?}", marker)),
+            }
+        }
+    }
+}
+
+/// Parse a statement amount such as `-1.234.567,89`, `Rp 50,000.00`, `(12.50)` or `25.000-`.
+/// Everything but digits, the decimal separator and the sign is ignored.
+pub fn parse_amount(value: &str, decimal_separator: char) -> Option<Decimal> {
+    let mut digits = String::new();
+    let mut negative = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            c if c == decimal_separator => digits.push('.'),
+            '-' | '(' | '\u{2212}' => negative = true,
+            _ => {}
+        }
+    }
+    if !digits.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let amount = Decimal::from_str(&digits).ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// A QIF amount, where the separator that isn't the decimal one may only group thousands, so
+/// `-25,00` read with `.` decimals is unreadable instead of 2500
+fn qif_amount(value: &str, decimal_separator: char) -> Option<Decimal> {
+    let grouping = if decimal_separator == ',' { '.' } else { ',' };
+    let grouped = value.match_indices(grouping).all(|(i, _)| {
+        value[i + 1..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+            == 3
+    });
+    if grouped {
+        parse_amount(value, decimal_separator)
+    } else {
+        None
+    }
+}
+
+/// `Some(true)` for a debit marker (DB, D, Debit, Debet), `Some(false)` for a credit one
+/// (CR, C, Credit, K, Kredit); looks at the letters only
+fn debit_marker(text: &str) -> Option<bool> {
+    let word: String = text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect::<String>()
+        .to_uppercase();
+    let word = word.trim_start_matches("RP").trim_start_matches("IDR");
+    if word.starts_with('D') {
+        Some(true)
+    } else if word.starts_with('C') || word.starts_with('K') {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn profile(sign_convention: SignConvention, decimal_separator: char) -> ImportProfile {
+        ImportProfile {
+            id: 1,
+            name: "Bank".into(),
+            delimiter: ',',
+            skip_lines: 0,
+            date_column: "Date".into(),
+            date_format: "%d/%m/%Y".into(),
+            amount_column: "Amount".into(),
+            second_column: None,
+            description_column: "Description".into(),
+            sign_convention,
+            decimal_separator,
+            account_id: None,
+            category_id: None,
+        }
+    }
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn noon(year: i32, month: u32, day: u32) -> Option<DateTime<Local>> {
+        Local.with_ymd_and_hms(year, month, day, 12, 0, 0).single()
+    }
+
+    #[test]
+    fn parse_amount_reads_both_decimal_separators() {
+        assert_eq!(parse_amount("-1.234.567,89", ','), Some(dec("-1234567.89")));
+        assert_eq!(parse_amount("1,234,567.89", '.'), Some(dec("1234567.89")));
+        assert_eq!(parse_amount("Rp 50.000", ','), Some(dec("50000")));
+        assert_eq!(parse_amount("IDR 50,000.00", '.'), Some(dec("50000")));
+    }
+
+    #[test]
+    fn parse_amount_reads_accounting_and_trailing_signs() {
+        assert_eq!(parse_amount("(12.50)", '.'), Some(dec("-12.50")));
+        assert_eq!(parse_amount("(1.250,00)", ','), Some(dec("-1250")));
+        assert_eq!(parse_amount("25.000-", ','), Some(dec("-25000")));
+        assert_eq!(parse_amount("\u{2212}7.5", '.'), Some(dec("-7.5")));
+        assert_eq!(parse_amount("", '.'), None);
+        assert_eq!(parse_amount("n/a", '.'), None);
+    }
+
+    #[test]
+    fn debit_marker_reads_english_and_indonesian_markers() {
+        for debit in ["DB", "d", "Debit", "Debet", "50,000.00 DB", "Rp 5.000 D"] {
+            assert_eq!(debit_marker(debit), Some(true), "{:?}", debit);
+        }
+        for credit in ["CR", "c", "Credit", "K", "Kredit", "IDR 5.000 CR"] {
+            assert_eq!(debit_marker(credit), Some(false), "{:?}", credit);
+        }
+        for neither in ["", "50.000", "Rp", "Transfer"] {
+            assert_eq!(debit_marker(neither), None, "{:?}", neither);
+        }
+    }
+
+    #[test]
+    fn signed_amount_follows_each_sign_convention() {
+        let negative = profile(SignConvention::NegativeIsExpense, '.');
+        assert_eq!(signed_amount(&negative, "-50.00", ""), Ok(dec("50")));
+        assert_eq!(signed_amount(&negative, "20", ""), Ok(dec("-20")));
+
+        let positive = profile(SignConvention::PositiveIsExpense, '.');
+        assert_eq!(signed_amount(&positive, "50.00", ""), Ok(dec("50")));
+        assert_eq!(signed_amount(&positive, "-20", ""), Ok(dec("-20")));
+
+        let columns = profile(SignConvention::DebitCreditColumns, ',');
+        assert_eq!(signed_amount(&columns, "50.000", ""), Ok(dec("50000")));
+        assert_eq!(signed_amount(&columns, "-50.000", ""), Ok(dec("50000")));
+        assert_eq!(signed_amount(&columns, "", "20,5"), Ok(dec("-20.5")));
+        assert!(signed_amount(&columns, "abc", "").is_err());
+
+        let marker = profile(SignConvention::DbCrMarker, '.');
+        assert_eq!(signed_amount(&marker, "50.00", "DB"), Ok(dec("50")));
+        assert_eq!(signed_amount(&marker, "-50.00", "CR"), Ok(dec("-50")));
+        assert_eq!(signed_amount(&marker, "50,000.00 DB", ""), Ok(dec("50000")));
+        assert_eq!(
+            signed_amount(&marker, "50.00", ""),
+            Err("no DB/CR marker in \"50.00\"".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_csv_reads_named_columns_after_skipped_lines() {
+        let mut bank = profile(SignConvention::NegativeIsExpense, ',');
+        bank.skip_lines = 1;
+        let text = "\u{feff}Account 123\n\
+                    Date,Description,Amount\n\
+                    01/10/2026,  Indomaret   snacks ,\"-50.000\"\n\
+                    \n\
+                    02/10/2026,Salary,\"1.000.000\"\n\
+                    yesterday,Broken,-1\n\
+                    03/10/2026,Nothing,0\n";
+        let rows = parse_csv(&bank, text).unwrap();
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].date, noon(2026, 10, 1));
+        assert_eq!(rows[0].description, "Indomaret snacks");
+        assert_eq!(rows[0].amount, dec("50000"));
+        assert_eq!(rows[0].status, RowStatus::Ready);
+        assert_eq!(rows[0].import_id, None);
+        assert_eq!(rows[1].amount, dec("-1000000"));
+        assert_eq!(rows[1].status, RowStatus::Income);
+        assert_eq!(
+            rows[2].status,
+            RowStatus::Invalid("unreadable date \"yesterday\"".into())
+        );
+        assert_eq!(rows[3].status, RowStatus::Invalid("zero amount".into()));
+    }
+
+    #[test]
+    fn parse_csv_reads_numbered_columns_without_a_header() {
+        let mut bank = profile(SignConvention::DbCrMarker, ',');
+        bank.delimiter = ';';
+        bank.date_column = "1".into();
+        bank.description_column = "2".into();
+        bank.amount_column = "3".into();
+        bank.second_column = Some("4".into());
+        let rows = parse_csv(&bank, "01/10/2026;Kopi;25,50;DB\n02/10/2026;Refund;10;CR\n").unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].amount, dec("25.50"));
+        assert_eq!(rows[0].status, RowStatus::Ready);
+        assert_eq!(rows[1].amount, dec("-10"));
+        assert_eq!(rows[1].status, RowStatus::Income);
+    }
+
+    #[test]
+    fn parse_csv_reads_debit_and_credit_columns() {
+        let mut bank = profile(SignConvention::DebitCreditColumns, '.');
+        bank.amount_column = "Debit".into();
+        bank.second_column = Some("Credit".into());
+        let text = "Date,Description,Debit,Credit\n\
+                    01/10/2026,Rent,1500.00,\n\
+                    02/10/2026,Interest,,3.25\n";
+        let rows = parse_csv(&bank, text).unwrap();
+
+        assert_eq!(rows[0].amount, dec("1500"));
+        assert_eq!(rows[1].amount, dec("-3.25"));
+    }
+
+    #[test]
+    fn parse_csv_rejects_a_missing_column() {
+        let mut bank = profile(SignConvention::NegativeIsExpense, '.');
+        bank.amount_column = "Nominal".into();
+        let error = parse_csv(&bank, "Date,Description,Amount\n01/10/2026,Kopi,-5\n");
+        assert!(matches!(error, Err(AppError::Validation(_))));
+    }
+
+    const OFX_SGML: &str = "OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\n\n\
+        <OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n\
+        <BANKACCTFROM><BANKID>014<ACCTID>1234567890<ACCTTYPE>CHECKING</BANKACCTFROM>\n\
+        <BANKTRANLIST>\n\
+        <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20261001<TRNAMT>-25.00<FITID>T1<NAME>Kopi Kenangan</STMTTRN>\n\
+        <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20261002<TRNAMT>1000.00<FITID>T2<NAME><MEMO>Salary</STMTTRN>\n\
+        </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>\n</OFX>\n";
+
+    const OFX_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <?OFX OFXHEADER=\"200\" VERSION=\"220\"?>\n\
+        <OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>\n\
+        <CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM>\n\
+        <BANKTRANLIST>\n\
+        <STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20261003120000[+7:WIB]</DTPOSTED>\n\
+        <TRNAMT>-12.5</TRNAMT><FITID>X9</FITID><NAME>Toko &amp; Co</NAME></STMTTRN>\n\
+        <STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20261004</DTPOSTED>\n\
+        <TRNAMT>-3</TRNAMT><NAME>Parking</NAME></STMTTRN>\n\
+        </BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>\n";
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<DateTime<Local>> {
+        chrono::Utc
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .single()
+            .map(|at| at.with_timezone(&Local))
+    }
+
+    #[test]
+    fn parse_ofx_reads_sgml_statements() {
+        let rows = parse_ofx(OFX_SGML).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].date, noon(2026, 10, 1));
+        assert_eq!(rows[0].description, "Kopi Kenangan");
+        assert_eq!(rows[0].amount, dec("25"));
+        assert_eq!(rows[0].status, RowStatus::Ready);
+        assert_eq!(rows[0].import_id.as_deref(), Some("ofx:1234567890:T1"));
+        // An empty NAME falls back to MEMO
+        assert_eq!(rows[1].description, "Salary");
+        assert_eq!(rows[1].amount, dec("-1000"));
+        assert_eq!(rows[1].status, RowStatus::Income);
+    }
+
+    #[test]
+    fn parse_ofx_reads_xml_statements() {
+        let rows = parse_ofx(OFX_XML).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].date, utc(2026, 10, 3, 5, 0));
+        assert_eq!(rows[0].description, "Toko & Co");
+        assert_eq!(rows[0].amount, dec("12.5"));
+        assert_eq!(rows[0].import_id.as_deref(), Some("ofx:4111:X9"));
+        // No FITID: the id is derived from the entry itself
+        assert_eq!(
+            rows[1].import_id.as_deref(),
+            Some("ofx:4111:20261004|-3|Parking#1")
+        );
+    }
+
+    #[test]
+    fn parse_ofx_rejects_other_files() {
+        assert!(parse_ofx("Date,Amount\n01/10/2026,5\n").is_err());
+    }
+
+    #[test]
+    fn parse_ofx_date_applies_the_zone_in_brackets() {
+        assert_eq!(parse_ofx_date("20261003"), noon(2026, 10, 3));
+        // A time without a zone is GMT
+        assert_eq!(parse_ofx_date("20261003143000"), utc(2026, 10, 3, 14, 30));
+        assert_eq!(
+            parse_ofx_date("20261003143000.000[-5:EST]"),
+            utc(2026, 10, 3, 19, 30)
+        );
+        assert_eq!(
+            parse_ofx_date("20261003143000[+7:WIB]"),
+            utc(2026, 10, 3, 7, 30)
+        );
+        assert_eq!(
+            parse_ofx_date("20261003143000[5.75:NPT]"),
+            utc(2026, 10, 3, 8, 45)
+        );
+        assert_eq!(
+            parse_ofx_date("20261003143000[0]"),
+            utc(2026, 10, 3, 14, 30)
+        );
+        assert_eq!(parse_ofx_date("2026"), None);
+        assert_eq!(parse_ofx_date("20261341"), None);
+    }
+
+    #[test]
+    fn parse_qif_date_reads_quicken_years() {
+        let formats = QIF_DATE_FORMATS;
+        assert_eq!(parse_qif_date("10/ 1'26", &formats), noon(2026, 10, 1));
+        assert_eq!(parse_qif_date("1/15'2026", &formats), noon(2026, 1, 15));
+        assert_eq!(parse_qif_date("12/31/99", &formats), noon(1999, 12, 31));
+        assert_eq!(parse_qif_date("2026-10-01", &formats), noon(2026, 10, 1));
+        assert_eq!(parse_qif_date("someday", &formats), None);
+    }
+
+    #[test]
+    fn parse_qif_ignores_a_csv_profile_it_does_not_need() {
+        // A `,`-decimal, day-first bank profile must not turn T-25.00 into 2500
+        let indonesian = profile(SignConvention::NegativeIsExpense, ',');
+        let text =
+            "!Type:Bank\nD01/02/2026\nT-25.00\nPKopi\n^\nD01/03/2026\nT-1,250.50\nPRent\n^\n";
+        let rows = parse_qif(text, Some(&indonesian)).unwrap();
+        assert_eq!(rows[0].amount, dec("25.00"));
+        assert_eq!(rows[0].date, noon(2026, 1, 2));
+        assert_eq!(rows[1].amount, dec("1250.50"));
+    }
+
+    #[test]
+    fn parse_qif_falls_back_on_the_profile_for_decimal_commas() {
+        let text = "!Type:Bank\nD01/02/2026\nT-25,00\nPKopi\n^\n";
+        let rows = parse_qif(text, None).unwrap();
+        assert!(matches!(rows[0].status, RowStatus::Invalid(_)));
+
+        let indonesian = profile(SignConvention::NegativeIsExpense, ',');
+        let rows = parse_qif(text, Some(&indonesian)).unwrap();
+        assert_eq!(rows[0].status, RowStatus::Ready);
+        assert_eq!(rows[0].amount, dec("25.00"));
+        assert_eq!(rows[0].date, noon(2026, 2, 1));
+    }
+
+    #[test]
+    fn parse_qif_derives_stable_ids() {
+        let entries = "D10/ 1'26\nT-25.00\nPKopi\n^\n\
+                       D10/ 1'26\nT-25.00\nPKopi\n^\n\
+                       D10/ 2'26\nU-8.00\nN1001\nMParking\n^\n";
+        let text = format!("!Type:Bank\n{}", entries);
+        let ids = |text: &str| -> Vec<Option<String>> {
+            parse_qif(text, None)
+                .unwrap()
+                .into_iter()
+                .map(|row| row.import_id)
+                .collect()
+        };
+
+        let first = ids(&text);
+        assert_eq!(
+            first,
+            [
+                Some("qif:10/ 1'26|-25.00||Kopi#1".to_string()),
+                Some("qif:10/ 1'26|-25.00||Kopi#2".to_string()),
+                Some("qif:10/ 2'26|-8.00|1001|Parking#1".to_string()),
+            ]
+        );
+        // Reading the same file again gives the same ids, and so does a later statement
+        // that repeats these entries after an account list
+        assert_eq!(ids(&text), first);
+        let overlapping = format!(
+            "!Account\nNChecking\n^\n!Type:Bank\n{}D10/ 3'26\nT-4.00\nPBread\n^\n",
+            entries
+        );
+        assert_eq!(ids(&overlapping)[..3], first);
+    }
+}
@@ -2,18 +2,38 @@
 
 mod action;
 mod app;
+mod config;
+mod currency;
 mod database;
 mod error;
+mod export;
+mod import;
+mod migrations;
 mod models;
 mod state;
 mod tui;
 
 use app::App;
+use config::Config;
 
 #[tokio::main]
 async fn main() -> error::Result<()> {
+    let config = Config::load()?;
+
+    let args = config::without_db_flag(&std::env::args().skip(1).collect::<Vec<_>>());
+    match args.first().map(String::as_str) {
+        Some("export") => return export::run_cli(&config, &args[1..]).await,
+        Some(arg) => {
+            return Err(error::AppError::Config(format!(
+                "Unknown argument {:?}. Usage: ebisu-tui [--db PATH] [export ...]",
+                arg
+            )));
+        }
+        None => {}
+    }
+
     // Initialize and run the TUI application
-    let mut app = App::new().await?;
+    let mut app = App::new(config).await?;
     app.run().await?;
     Ok(())
 }
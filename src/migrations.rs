@@ -0,0 +1,159 @@
+use crate::error::{AppError, Result};
+
+/// A single embedded schema migration
+pub struct Migration {
+    /// Target `PRAGMA user_version` once this step is applied
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Ordered list of schema migrations. Append new steps; never edit a released one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "transaction_allocations",
+        sql: include_str!("../migrations/0002_transaction_allocations.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "category_roles",
+        sql: include_str!("../migrations/0003_category_roles.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "settings",
+        sql: include_str!("../migrations/0004_settings.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "accounts",
+        sql: include_str!("../migrations/0005_accounts.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "recurring_rules",
+        sql: include_str!("../migrations/0006_recurring_rules.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "fund_sources",
+        sql: include_str!("../migrations/0007_fund_sources.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "tags",
+        sql: include_str!("../migrations/0008_tags.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "transactions_fts",
+        sql: include_str!("../migrations/0009_transactions_fts.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "reflections",
+        sql: include_str!("../migrations/0010_reflections.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "savings_goals",
+        sql: include_str!("../migrations/0011_savings_goals.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "fixed_category_budgets",
+        sql: include_str!("../migrations/0012_fixed_category_budgets.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "refunds",
+        sql: include_str!("../migrations/0013_refunds.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "split_expenses",
+        sql: include_str!("../migrations/0014_split_expenses.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "payees",
+        sql: include_str!("../migrations/0015_payees.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "import_profiles",
+        sql: include_str!("../migrations/0016_import_profiles.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "import_ids",
+        sql: include_str!("../migrations/0017_import_ids.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "archived_percentages",
+        sql: include_str!("../migrations/0018_archived_percentages.sql"),
+    },
+];
+
+/// Version that added `payees`; expenses recorded before it are backfilled in Rust
+pub const PAYEES_VERSION: i64 = 15;
+
+/// Highest schema version this build understands
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Bring the database up to `latest_version`, one transaction per step, returning the version
+/// it started from.
+///
+/// Databases created before migrations existed report `user_version = 0`; migration 1
+/// only uses `CREATE TABLE IF NOT EXISTS`, so they upgrade in place.
+pub async fn run(conn: &libsql::Connection) -> Result<i64> {
+    let current = user_version(conn).await?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(AppError::SchemaTooNew {
+            found: current,
+            supported: latest,
+        });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+            .await?;
+
+        tx.execute_batch(migration.sql).await.map_err(|e| {
+            AppError::Migration(format!(
+                "{:04}_{}: {}",
+                migration.version, migration.name, e
+            ))
+        })?;
+        // PRAGMA does not accept bound parameters
+        tx.execute(&format!("PRAGMA user_version = {}", migration.version), ())
+            .await?;
+
+        tx.commit().await?;
+    }
+
+    Ok(current)
+}
+
+async fn user_version(conn: &libsql::Connection) -> Result<i64> {
+    let version: i64 = conn
+        .query("PRAGMA user_version", ())
+        .await?
+        .next()
+        .await?
+        .ok_or(AppError::Database(libsql::Error::QueryReturnedNoRows))?
+        .get(0)?;
+    Ok(version)
+}
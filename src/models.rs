@@ -1,45 +1,352 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, TimeDelta};
 use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIter, EnumString};
 
+use crate::error::{AppError, Result};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Category {
     pub id: i64,
-    pub name: CategoryName,
+    pub name: String,
     pub limit_percentage: Decimal,
+    /// Allocated before any percentage each payday; `limit_percentage` is ignored while set
+    pub fixed_amount: Option<Decimal>,
     pub overflow_to_id: Option<i64>,
+    /// Receives the rollover on payday and is never picked directly for expenses
+    pub is_savings: bool,
+    /// Hidden from forms and allocation, kept for history
+    pub archived: bool,
+}
+
+/// Split `income` across categories: fixed amounts first, the remainder by percentage.
+/// Archived categories get nothing. Income too small for the fixed amounts is shared
+/// between them in proportion, and whatever no percentage claims goes to Savings.
+pub fn allocate_income(categories: &[Category], income: Decimal) -> Vec<(i64, Decimal)> {
+    let fixed_total: Decimal = categories
+        .iter()
+        .filter(|c| !c.archived)
+        .filter_map(|c| c.fixed_amount)
+        .sum();
+    let remainder = (income - fixed_total).max(Decimal::ZERO);
+
+    let mut allocations: Vec<(i64, Decimal)> = categories
+        .iter()
+        .map(|cat| {
+            let portion = match cat.fixed_amount {
+                _ if cat.archived => Decimal::ZERO,
+                Some(fixed) if fixed_total > income => fixed * income / fixed_total,
+                Some(fixed) => fixed,
+                None => remainder * cat.limit_percentage / Decimal::ONE_HUNDRED,
+            };
+            (cat.id, portion)
+        })
+        .collect();
+
+    let unallocated = income
+        - allocations
+            .iter()
+            .map(|(_, portion)| *portion)
+            .sum::<Decimal>();
+    if unallocated > Decimal::ZERO
+        && let Some(savings) = categories.iter().position(|c| c.is_savings)
+    {
+        allocations[savings].1 += unallocated;
+    }
+    allocations
+}
+
+/// Follow the `overflow_to_id` links from `start`, returning the visited category ids in
+/// order. Fails on a cycle or a link to a category that doesn't exist.
+pub fn overflow_chain(categories: &[Category], start: i64) -> Result<Vec<i64>> {
+    let name = |id: i64| {
+        categories
+            .iter()
+            .find(|c| c.id == id)
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| format!("#{}", id))
+    };
+
+    let mut chain = vec![start];
+    let mut current = categories
+        .iter()
+        .find(|c| c.id == start)
+        .ok_or(AppError::CategoryNotFound(start.to_string()))?;
+
+    while let Some(next_id) = current.overflow_to_id {
+        if chain.contains(&next_id) {
+            let path = chain
+                .iter()
+                .chain(std::iter::once(&next_id))
+                .map(|id| name(*id))
+                .collect::<Vec<_>>()
+                .join(" → ");
+            return Err(AppError::Validation(format!(
+                "Overflow chain loops: {}",
+                path
+            )));
+        }
+        current = categories.iter().find(|c| c.id == next_id).ok_or_else(|| {
+            AppError::Validation(format!(
+                "{} overflows into a category that doesn't exist",
+                current.name
+            ))
+        })?;
+        chain.push(next_id);
+    }
+
+    Ok(chain)
+}
+
+/// Every category's overflow chain must end without looping back on itself.
+pub fn validate_overflow_chains(categories: &[Category]) -> Result<()> {
+    for cat in categories {
+        overflow_chain(categories, cat.id)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fund {
+    pub id: i64,
+    pub amount: Decimal,
+    pub added_at: DateTime<Local>,
+    pub remaining_balance_rolled: Decimal,
+    pub account_id: Option<i64>,
+    /// e.g. Salary, Bonus, Side gig
+    pub source: Option<String>,
+    pub note: Option<String>,
 }
 
 #[derive(
     Debug,
     Clone,
     Copy,
+    Default,
     Serialize,
     Deserialize,
     PartialEq,
     Eq,
-    PartialOrd,
-    Ord,
-    Hash,
     Display,
     EnumString,
     EnumIter,
 )]
-pub enum CategoryName {
-    Needs,
-    Wants,
-    Culture,
-    Unexpected,
-    Savings,
+pub enum AccountKind {
+    #[default]
+    Cash,
+    Bank,
+    #[strum(serialize = "E-Wallet")]
+    EWallet,
+}
+
+impl AccountKind {
+    pub fn next(&self) -> Self {
+        match self {
+            AccountKind::Cash => AccountKind::Bank,
+            AccountKind::Bank => AccountKind::EWallet,
+            AccountKind::EWallet => AccountKind::Cash,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Fund {
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+    pub kind: AccountKind,
+    /// Funds in, minus expenses, plus/minus transfers
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
     pub id: i64,
+    pub from_account_id: i64,
+    pub to_account_id: i64,
     pub amount: Decimal,
-    pub added_at: DateTime<Local>,
-    pub remaining_balance_rolled: Decimal,
+    pub note: Option<String>,
+    pub created_at: DateTime<Local>,
+}
+
+/// Whether a recurring rule posts an expense or a fund entry
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Display, EnumString,
+)]
+pub enum RecurringKind {
+    #[default]
+    Expense,
+    Income,
+}
+
+impl RecurringKind {
+    pub fn toggle(&self) -> Self {
+        match self {
+            RecurringKind::Expense => RecurringKind::Income,
+            RecurringKind::Income => RecurringKind::Expense,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// RRULE-like schedule, stored as e.g. `FREQ=MONTHLY;INTERVAL=1`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cadence {
+    pub frequency: Frequency,
+    pub interval: u32,
+}
+
+impl std::fmt::Display for Cadence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FREQ={};INTERVAL={}", self.frequency, self.interval)
+    }
+}
+
+impl std::str::FromStr for Cadence {
+    type Err = AppError;
+
+    /// Accepts `FREQ=WEEKLY;INTERVAL=2` as well as a bare `weekly`
+    fn from_str(input: &str) -> Result<Self> {
+        let invalid = || {
+            AppError::Validation(format!(
+                "Invalid cadence {:?}, use e.g. monthly or FREQ=WEEKLY;INTERVAL=2",
+                input
+            ))
+        };
+
+        let mut frequency = None;
+        let mut interval = 1;
+        for part in input.trim().split(';').filter(|p| !p.trim().is_empty()) {
+            match part.split_once('=') {
+                Some((key, value)) if key.trim().eq_ignore_ascii_case("FREQ") => {
+                    frequency = Some(Frequency::from_str(value.trim()).map_err(|_| invalid())?);
+                }
+                Some((key, value)) if key.trim().eq_ignore_ascii_case("INTERVAL") => {
+                    interval = value.trim().parse().map_err(|_| invalid())?;
+                }
+                Some(_) => return Err(invalid()),
+                None => frequency = Some(Frequency::from_str(part.trim()).map_err(|_| invalid())?),
+            }
+        }
+
+        match frequency {
+            Some(frequency) if interval > 0 => Ok(Cadence {
+                frequency,
+                interval,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Cadence {
+    /// Human readable form, e.g. `every 2 weeks`
+    pub fn describe(&self) -> String {
+        let unit = match self.frequency {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        };
+        if self.interval == 1 {
+            format!("every {}", unit)
+        } else {
+            format!("every {} {}s", self.interval, unit)
+        }
+    }
+
+    /// The `n`th occurrence counted from `start`. Months are always added to the original
+    /// date, so a rule starting on the 31st lands on the last day of shorter months without
+    /// drifting afterwards.
+    fn occurrence(&self, start: DateTime<Local>, n: u32) -> Option<DateTime<Local>> {
+        let steps = n.checked_mul(self.interval)?;
+        let naive = start.naive_local();
+        let naive = match self.frequency {
+            Frequency::Daily => naive.checked_add_days(Days::new(steps.into())),
+            Frequency::Weekly => naive.checked_add_days(Days::new(u64::from(steps) * 7)),
+            Frequency::Monthly => naive.checked_add_months(Months::new(steps)),
+            Frequency::Yearly => naive.checked_add_months(Months::new(steps.checked_mul(12)?)),
+        }?;
+        naive.and_local_timezone(Local).earliest()
+    }
+
+    /// First occurrence of the schedule starting at `start` that is later than `after`
+    pub fn next_after(
+        &self,
+        start: DateTime<Local>,
+        after: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        if start > after {
+            return Some(start);
+        }
+
+        // Jump close to `after` instead of walking every occurrence since `start`
+        let (from, to) = (start.date_naive(), after.date_naive());
+        let elapsed_steps = match self.frequency {
+            Frequency::Daily => (to - from).num_days(),
+            Frequency::Weekly => (to - from).num_days() / 7,
+            Frequency::Monthly => months_between(from, to),
+            Frequency::Yearly => months_between(from, to) / 12,
+        } / i64::from(self.interval);
+
+        let mut n = u32::try_from(elapsed_steps.saturating_sub(1).max(0)).ok()?;
+        loop {
+            let candidate = self.occurrence(start, n)?;
+            if candidate > after {
+                return Some(candidate);
+            }
+            n = n.checked_add(1)?;
+        }
+    }
+}
+
+fn months_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    (i64::from(to.year()) - i64::from(from.year())) * 12 + i64::from(to.month())
+        - i64::from(from.month())
+}
+
+/// A scheduled expense or fund entry (rent, subscriptions, salary)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringRule {
+    pub id: i64,
+    pub kind: RecurringKind,
+    pub amount: Decimal,
+    /// Expense category; unused for income
+    pub category_id: Option<i64>,
+    pub account_id: Option<i64>,
+    pub description: Option<String>,
+    pub cadence: Cadence,
+    /// First occurrence; later ones are counted from here
+    pub starts_at: DateTime<Local>,
+    pub next_due: DateTime<Local>,
+    /// Ask before posting each occurrence
+    pub requires_confirmation: bool,
+    /// Paused rules are never posted
+    pub active: bool,
+}
+
+impl RecurringRule {
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.active && self.next_due <= now
+    }
+
+    /// Short label for status messages and lists
+    pub fn label(&self) -> String {
+        self.description
+            .clone()
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| self.kind.to_string())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,43 +357,217 @@ pub struct Transaction {
     pub description: Option<String>,
     pub created_at: DateTime<Local>,
     pub overflow_from_id: Option<i64>,
+    pub account_id: Option<i64>,
+    /// Set on refunds: the expense this one gives money back for. Refund amounts are negative.
+    pub refund_of_id: Option<i64>,
+    /// Shared by the lines of one receipt split across categories
+    pub split_id: Option<i64>,
     // Enriched data (joined)
-    pub category_name: Option<CategoryName>,
+    pub category_name: Option<String>,
+    /// Registered payee; refunds carry the payee of their expense
+    pub payee: Option<String>,
+    /// How much each category in the overflow chain covered (empty for legacy rows)
+    pub allocations: Vec<Allocation>,
+    /// Lowercase tag names, from the `#tags` in the description
+    pub tags: Vec<String>,
+}
+
+impl Transaction {
+    pub fn is_refund(&self) -> bool {
+        self.refund_of_id.is_some()
+    }
+
+    /// True when part of the amount was covered by another category
+    pub fn overflowed(&self) -> bool {
+        self.allocations
+            .iter()
+            .any(|a| a.category_id != self.category_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Allocation {
+    pub category_id: i64,
+    pub category_name: Option<String>,
+    pub amount: Decimal,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CategoryBalance {
     pub category_id: i64,
-    pub category_name: CategoryName,
+    pub category_name: String,
+    pub is_savings: bool,
     pub available: Decimal,
     pub allocated: Decimal,
     pub spent: Decimal,
     pub last_updated: DateTime<Local>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceTotal {
+    pub source: String,
+    pub total: Decimal,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummaryStats {
     pub total_funds_added: Decimal,
     pub total_spent: Decimal,
+    /// Funds added per source, largest first
+    pub income_by_source: Vec<SourceTotal>,
     pub current_settings: Vec<Category>,
     pub balances: Vec<CategoryBalance>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+/// The four Kakeibo questions, answered once per budget period
+pub const KAKEIBO_QUESTIONS: [&str; 4] = [
+    "How much did I receive?",
+    "How much do I want to save?",
+    "How much did I spend?",
+    "How can I improve?",
+];
+
+/// A budget period: opened by a fund entry and closed by the next one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPeriod {
+    pub fund_id: i64,
+    pub start: DateTime<Local>,
+    /// When the next fund entry opened a new period; `None` for the current one
+    pub end: Option<DateTime<Local>>,
+}
+
+impl BudgetPeriod {
+    /// e.g. `2026-09-25 → 2026-10-25` or `2026-10-25 → now`
+    pub fn label(&self) -> String {
+        let end = self
+            .end
+            .map(|end| end.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "now".to_string());
+        format!("{} → {}", self.start.format("%Y-%m-%d"), end)
+    }
+}
+
+/// Kakeibo journal entry of one budget period, with its figures filled in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reflection {
+    pub period: BudgetPeriod,
+    pub received: Decimal,
+    pub spent: Decimal,
+    pub savings_goal: Option<Decimal>,
+    /// Answers to `KAKEIBO_QUESTIONS`, in order
+    pub answers: [String; 4],
+}
+
+impl Reflection {
+    /// What was left of the period's income
+    pub fn saved(&self) -> Decimal {
+        self.received - self.spent
+    }
+
+    pub fn goal_met(&self) -> Option<bool> {
+        self.savings_goal.map(|goal| self.saved() >= goal)
+    }
+}
+
+/// Period length assumed before there are two fund entries to measure one
+const DEFAULT_PERIOD_DAYS: i64 = 30;
+
+/// Average length of the last few closed budget periods (newest first, as returned by
+/// `DB::get_budget_periods`), or 30 days without history
+pub fn typical_period_length(periods: &[BudgetPeriod]) -> TimeDelta {
+    let closed: Vec<TimeDelta> = periods
+        .iter()
+        .filter_map(|p| p.end.map(|end| end - p.start))
+        .filter(|len| *len > TimeDelta::zero())
+        .take(6)
+        .collect();
+    if closed.is_empty() {
+        return TimeDelta::days(DEFAULT_PERIOD_DAYS);
+    }
+    closed.iter().fold(TimeDelta::zero(), |sum, len| sum + *len) / closed.len() as i32
+}
+
+/// Named target funded by withdrawing money from the Savings category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavingsGoal {
+    pub id: i64,
+    pub name: String,
+    pub target: Decimal,
+    pub deadline: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    /// Total withdrawn from Savings for this goal
+    pub saved: Decimal,
+}
+
+impl SavingsGoal {
+    pub fn remaining(&self) -> Decimal {
+        (self.target - self.saved).max(Decimal::ZERO)
+    }
+
+    pub fn achieved(&self) -> bool {
+        self.saved >= self.target
+    }
+
+    /// Share of the target reached, between 0 and 1
+    pub fn progress(&self) -> f64 {
+        if self.target <= Decimal::ZERO {
+            return 1.0;
+        }
+        (self.saved / self.target)
+            .to_f64()
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0)
+    }
+
+    /// Budget periods of `period` length left before the deadline, counting the current one
+    pub fn periods_left(&self, now: DateTime<Local>, period: TimeDelta) -> Option<i64> {
+        let left = self.deadline? - now;
+        if left <= TimeDelta::zero() || period <= TimeDelta::zero() {
+            return Some(0);
+        }
+        let seconds = period.num_seconds().max(1);
+        Some((left.num_seconds() + seconds - 1) / seconds)
+    }
+
+    /// What has to be set aside each period to reach the target by the deadline. `None`
+    /// without a deadline or once it has passed with money still missing.
+    pub fn required_per_period(&self, now: DateTime<Local>, period: TimeDelta) -> Option<Decimal> {
+        if self.achieved() {
+            return Some(Decimal::ZERO);
+        }
+        match self.periods_left(now, period)? {
+            0 => None,
+            periods => Some(self.remaining() / Decimal::from(periods)),
+        }
+    }
+}
+
+/// Parse a goal deadline: `YYYY-MM` (end of that month) or `YYYY-MM-DD` (end of that day)
+pub fn parse_deadline(input: &str) -> Option<DateTime<Local>> {
+    let input = input.trim();
+    let day = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(&format!("{}-01", input), "%Y-%m-%d")
+                .ok()
+                .and_then(|first| first.checked_add_months(Months::new(1)))
+                .and_then(|next| next.pred_opt())
+        })?;
+    day.and_hms_opt(23, 59, 59)?
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumIter)]
 pub enum DateRange {
     Today,
     Last7Days,
+    #[default]
     Month,
     Year,
     FiveYears,
 }
 
-impl Default for DateRange {
-    fn default() -> Self {
-        Self::Month
-    }
-}
-
 impl DateRange {
     pub fn get_dates(&self) -> (DateTime<Local>, DateTime<Local>) {
         let now = Local::now();
@@ -133,3 +614,612 @@ impl DateRange {
         }
     }
 }
+
+/// `#tags` written in a description, lowercased and deduplicated in order of appearance.
+/// A tag is `#` followed by letters, digits, `-` or `_`.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(rest) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .collect::<String>()
+            .to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Words of a search query, lowercased with punctuation stripped, as they are matched
+/// against the description index and highlighted in the list
+pub fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split_whitespace() {
+        let term: String = word
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if !term.is_empty() && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+/// Someone expenses are paid to, e.g. "Indomaret"
+#[derive(Debug, Clone)]
+pub struct Payee {
+    pub id: i64,
+    pub name: String,
+    /// Expenses recorded for this payee; a split receipt counts once
+    pub uses: i64,
+    /// Category of the payee's most recent expense
+    pub last_category_id: Option<i64>,
+}
+
+/// Payee part of a description: the text without its `#tags`, whitespace collapsed, up to
+/// a ` - ` separating the payee from a note (`Indomaret - snacks`)
+pub fn payee_name(description: &str) -> Option<String> {
+    let text = untagged(description);
+    let name = text.split(" - ").next().unwrap_or_default().trim();
+    if !name.is_empty() {
+        Some(name.to_string())
+    } else {
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// Payee of a description, preferring a `known` payee it starts with, so `Indomaret snacks`
+/// files under `Indomaret` once that payee exists. The longest such payee wins; without one
+/// this is `payee_name`.
+pub fn match_payee<'a>(
+    description: &str,
+    known: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let text = untagged(description).to_lowercase();
+    known
+        .into_iter()
+        .filter(|name| {
+            let name = name.to_lowercase();
+            !name.is_empty()
+                && text
+                    .strip_prefix(&name)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+        .max_by_key(|name| name.len())
+        .map(str::to_string)
+        .or_else(|| payee_name(description))
+}
+
+/// Description without its `#tags`, whitespace collapsed
+fn untagged(description: &str) -> String {
+    description
+        .split_whitespace()
+        .filter(|word| !word.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Spending with one payee within the transactions it was computed from
+#[derive(Debug, Clone)]
+pub struct PayeeSummary {
+    /// `None` collects the expenses without a payee
+    pub payee: Option<String>,
+    /// Expenses, a split receipt counting once; refunds only lower the total
+    pub count: usize,
+    pub total: Decimal,
+    /// Filed category name and amount, largest first
+    pub by_category: Vec<(String, Decimal)>,
+}
+
+/// Group transactions by payee, biggest total first, expenses without a payee last
+pub fn group_by_payee(transactions: &[Transaction]) -> Vec<PayeeSummary> {
+    let mut groups: Vec<PayeeSummary> = Vec::new();
+    let mut counted_splits: Vec<i64> = Vec::new();
+
+    for t in transactions {
+        let index = match groups.iter().position(|g| g.payee == t.payee) {
+            Some(i) => i,
+            None => {
+                groups.push(PayeeSummary {
+                    payee: t.payee.clone(),
+                    count: 0,
+                    total: Decimal::ZERO,
+                    by_category: Vec::new(),
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        let new_receipt = match t.split_id {
+            Some(split) if counted_splits.contains(&split) => false,
+            Some(split) => {
+                counted_splits.push(split);
+                true
+            }
+            None => true,
+        };
+        if new_receipt && !t.is_refund() {
+            group.count += 1;
+        }
+        group.total += t.amount;
+        let category = t.category_name.clone().unwrap_or_else(|| "Unknown".into());
+        match group
+            .by_category
+            .iter_mut()
+            .find(|(name, _)| *name == category)
+        {
+            Some((_, amount)) => *amount += t.amount,
+            None => group.by_category.push((category, t.amount)),
+        }
+    }
+
+    groups.sort_by_key(|g| (g.payee.is_none(), std::cmp::Reverse(g.total)));
+    for group in &mut groups {
+        group
+            .by_category
+            .sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    }
+    groups
+}
+
+/// How far apart an Add Expense entry and a recorded expense can be to look like duplicates
+pub const ENTRY_DUPLICATE_WINDOW: TimeDelta = TimeDelta::hours(24);
+/// The same for statement rows, whose posting date can lag the purchase by a few days
+pub const STATEMENT_DUPLICATE_WINDOW: TimeDelta = TimeDelta::days(3);
+
+/// Recorded expense (a split receipt as a whole) that a new one of `amount` at `at` looks
+/// like: the same amount, within `window`, with a similar description. The closest in time
+/// wins. `existing` must be ordered like `DB::get_transactions_between` returns it.
+pub fn find_duplicate<'a>(
+    existing: &'a [Transaction],
+    amount: Decimal,
+    at: DateTime<Local>,
+    description: Option<&str>,
+    window: TimeDelta,
+) -> Option<&'a [Transaction]> {
+    existing
+        .chunk_by(|a, b| a.split_id.is_some() && a.split_id == b.split_id)
+        .filter(|entry| !entry[0].is_refund())
+        .filter(|entry| (entry[0].created_at - at).abs() <= window)
+        .filter(|entry| entry.iter().map(|t| t.amount).sum::<Decimal>() == amount)
+        .filter(|entry| similar_descriptions(entry[0].description.as_deref(), description))
+        .min_by_key(|entry| (entry[0].created_at - at).abs())
+}
+
+/// Whether two descriptions could name the same purchase: at least half the words of the
+/// shorter one appear in the other, ignoring case, punctuation and `#tags`. A missing
+/// description matches anything, since the amount and time already agree.
+fn similar_descriptions(a: Option<&str>, b: Option<&str>) -> bool {
+    let words = |description: Option<&str>| -> Vec<String> {
+        description
+            .and_then(payee_name)
+            .unwrap_or_default()
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() > 1)
+            .map(String::from)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() || b.is_empty() {
+        return true;
+    }
+    let shared = a.iter().filter(|word| b.contains(word)).count();
+    shared * 2 >= a.len().min(b.len())
+}
+
+/// Spending under one tag within the transactions it was computed from
+#[derive(Debug, Clone)]
+pub struct TagSummary {
+    /// `None` collects the untagged transactions
+    pub tag: Option<String>,
+    pub count: usize,
+    pub total: Decimal,
+    /// Filed category name and amount, largest first
+    pub by_category: Vec<(String, Decimal)>,
+}
+
+/// Group transactions by tag, biggest total first, untagged last. A transaction with
+/// several tags counts towards each of them.
+pub fn group_by_tag(transactions: &[Transaction]) -> Vec<TagSummary> {
+    let mut groups: Vec<TagSummary> = Vec::new();
+    let mut untagged = TagSummary {
+        tag: None,
+        count: 0,
+        total: Decimal::ZERO,
+        by_category: Vec::new(),
+    };
+
+    for t in transactions {
+        let category = t.category_name.clone().unwrap_or_else(|| "Unknown".into());
+        let add = |group: &mut TagSummary| {
+            group.count += 1;
+            group.total += t.amount;
+            match group
+                .by_category
+                .iter_mut()
+                .find(|(name, _)| *name == category)
+            {
+                Some((_, amount)) => *amount += t.amount,
+                None => group.by_category.push((category.clone(), t.amount)),
+            }
+        };
+
+        if t.tags.is_empty() {
+            add(&mut untagged);
+        }
+        for tag in &t.tags {
+            match groups.iter_mut().find(|g| g.tag.as_ref() == Some(tag)) {
+                Some(group) => add(group),
+                None => {
+                    let mut group = TagSummary {
+                        tag: Some(tag.clone()),
+                        count: 0,
+                        total: Decimal::ZERO,
+                        by_category: Vec::new(),
+                    };
+                    add(&mut group);
+                    groups.push(group);
+                }
+            }
+        }
+    }
+
+    groups.sort_by_key(|g| std::cmp::Reverse(g.total));
+    if untagged.count > 0 {
+        groups.push(untagged);
+    }
+    for group in &mut groups {
+        group
+            .by_category
+            .sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    }
+    groups
+}
+
+/// How a statement tells money out from money in
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Display,
+    EnumString,
+    EnumIter,
+)]
+pub enum SignConvention {
+    /// Signed amounts, spending below zero (most e-wallet exports)
+    #[default]
+    #[strum(serialize = "Negative is expense")]
+    NegativeIsExpense,
+    /// Signed amounts, spending above zero (credit card statements)
+    #[strum(serialize = "Positive is expense")]
+    PositiveIsExpense,
+    /// Money out in the amount column, money in in the second column
+    #[strum(serialize = "Debit/credit columns")]
+    DebitCreditColumns,
+    /// Unsigned amounts with a DB/CR (or D/K) marker in the second column or after the amount
+    #[strum(serialize = "DB/CR marker")]
+    DbCrMarker,
+}
+
+impl SignConvention {
+    pub fn next(&self) -> Self {
+        match self {
+            SignConvention::NegativeIsExpense => SignConvention::PositiveIsExpense,
+            SignConvention::PositiveIsExpense => SignConvention::DebitCreditColumns,
+            SignConvention::DebitCreditColumns => SignConvention::DbCrMarker,
+            SignConvention::DbCrMarker => SignConvention::NegativeIsExpense,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            SignConvention::NegativeIsExpense => SignConvention::DbCrMarker,
+            SignConvention::PositiveIsExpense => SignConvention::NegativeIsExpense,
+            SignConvention::DebitCreditColumns => SignConvention::PositiveIsExpense,
+            SignConvention::DbCrMarker => SignConvention::DebitCreditColumns,
+        }
+    }
+
+    /// Whether the convention reads the profile's second column
+    pub fn uses_second_column(&self) -> bool {
+        matches!(
+            self,
+            SignConvention::DebitCreditColumns | SignConvention::DbCrMarker
+        )
+    }
+}
+
+/// Column mapping for one bank's or e-wallet's CSV statement. Columns are referenced by
+/// header name (case-insensitive) or 1-based number.
+#[derive(Debug, Clone, Default)]
+pub struct ImportProfile {
+    pub id: i64,
+    pub name: String,
+    pub delimiter: char,
+    /// Lines above the header row (or the first row, without a header)
+    pub skip_lines: usize,
+    pub date_column: String,
+    /// chrono format, e.g. `%d/%m/%Y` or `%Y-%m-%d %H:%M:%S`
+    pub date_format: String,
+    pub amount_column: String,
+    /// Credit column or DB/CR marker column, for the conventions that use one
+    pub second_column: Option<String>,
+    pub description_column: String,
+    pub sign_convention: SignConvention,
+    /// `,` for statements written like 1.234.567,89
+    pub decimal_separator: char,
+    /// Account the imported expenses are paid from
+    pub account_id: Option<i64>,
+    /// Category for rows whose payee has no earlier expense
+    pub category_id: Option<i64>,
+}
+
+/// Parse a user-typed timestamp: `YYYY-MM-DD HH:MM`, `YYYY-MM-DD` (midday local time), or a
+/// shortcut relative to now that keeps the current time of day: `t` (today), `y` (yesterday),
+/// `-3d`, `-2w`.
+pub fn parse_datetime_input(input: &str) -> Option<DateTime<Local>> {
+    let input = input.trim();
+    let now = Local::now();
+    match input.to_lowercase().as_str() {
+        "t" | "today" => return Some(now),
+        "y" | "yesterday" => return now.checked_sub_days(Days::new(1)),
+        relative if relative.starts_with('-') => {
+            let days = if let Some(count) = relative[1..].strip_suffix('d') {
+                count.parse::<u64>().ok()?
+            } else if let Some(count) = relative[1..].strip_suffix('w') {
+                count.parse::<u64>().ok()?.checked_mul(7)?
+            } else {
+                return None;
+            };
+            return now.checked_sub_days(Days::new(days));
+        }
+        _ => {}
+    }
+
+    let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(12, 0, 0))
+        })?;
+    naive.and_local_timezone(Local).earliest()
+}
+
+/// `parse_datetime_input` for the date of an entry, which can't lie in the future: a plain
+/// date for today would be midday, so it is capped at now.
+pub fn parse_entry_date(input: &str) -> Option<DateTime<Local>> {
+    let at = parse_datetime_input(input)?;
+    let now = Local::now();
+    Some(if at > now && at.date_naive() == now.date_naive() {
+        now
+    } else {
+        at
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, 9, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn cadence(input: &str) -> Cadence {
+        input.parse().unwrap()
+    }
+
+    fn category(id: i64, percentage: i64, fixed_amount: Option<i64>) -> Category {
+        Category {
+            id,
+            name: format!("#{}", id),
+            limit_percentage: Decimal::from(percentage),
+            fixed_amount: fixed_amount.map(Decimal::from),
+            overflow_to_id: None,
+            is_savings: id == 1,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn payee_is_the_description_up_to_a_note() {
+        assert_eq!(payee_name("Indomaret  #snacks"), Some("Indomaret".into()));
+        assert_eq!(
+            payee_name("Indomaret - snacks #food"),
+            Some("Indomaret".into())
+        );
+        assert_eq!(payee_name("Coffee beans"), Some("Coffee beans".into()));
+        assert_eq!(payee_name(" - refill"), Some("- refill".into()));
+        assert_eq!(payee_name("#tagonly"), None);
+    }
+
+    #[test]
+    fn payee_prefers_a_known_payee_at_the_start() {
+        let known = ["Indomaret", "Indomaret Point", "Ind"];
+        assert_eq!(
+            match_payee("indomaret snacks #food", known),
+            Some("Indomaret".into())
+        );
+        assert_eq!(
+            match_payee("Indomaret Point coffee", known),
+            Some("Indomaret Point".into())
+        );
+        // Only whole words match
+        assert_eq!(
+            match_payee("Indoor plants", known),
+            Some("Indoor plants".into())
+        );
+        assert_eq!(
+            match_payee("Alfamart - water", known),
+            Some("Alfamart".into())
+        );
+    }
+
+    #[test]
+    fn allocation_takes_fixed_amounts_first() {
+        let categories = [
+            category(1, 0, None),
+            category(2, 60, None),
+            category(3, 40, None),
+            category(4, 0, Some(300)),
+        ];
+        let allocations = allocate_income(&categories, Decimal::from(1300));
+        let expected = [(1, 0), (2, 600), (3, 400), (4, 300)].map(|(id, v)| (id, Decimal::from(v)));
+        assert_eq!(allocations, expected);
+    }
+
+    #[test]
+    fn allocation_scales_fixed_amounts_down_for_small_income() {
+        let categories = [
+            category(1, 100, None),
+            category(2, 0, Some(300)),
+            category(3, 0, Some(100)),
+        ];
+        let allocations = allocate_income(&categories, Decimal::from(200));
+        let expected = [(1, 0), (2, 150), (3, 50)].map(|(id, v)| (id, Decimal::from(v)));
+        assert_eq!(allocations, expected);
+    }
+
+    #[test]
+    fn allocation_sends_unclaimed_remainder_to_savings() {
+        let mut archived = category(3, 100, None);
+        archived.archived = true;
+        let categories = [
+            category(1, 0, Some(50)),
+            category(2, 0, Some(100)),
+            archived,
+        ];
+        let allocations = allocate_income(&categories, Decimal::from(400));
+        let expected = [(1, 300), (2, 100), (3, 0)].map(|(id, v)| (id, Decimal::from(v)));
+        assert_eq!(allocations, expected);
+    }
+
+    #[test]
+    fn cadence_parses_rrule_and_bare_forms() {
+        assert_eq!(
+            cadence("FREQ=WEEKLY;INTERVAL=2"),
+            Cadence {
+                frequency: Frequency::Weekly,
+                interval: 2
+            }
+        );
+        assert_eq!(cadence(" monthly "), cadence("FREQ=MONTHLY;INTERVAL=1"));
+        assert_eq!(cadence("interval=3;freq=daily;").interval, 3);
+        assert_eq!(
+            cadence("FREQ=YEARLY;INTERVAL=1").to_string(),
+            "FREQ=YEARLY;INTERVAL=1"
+        );
+    }
+
+    #[test]
+    fn cadence_rejects_bad_input() {
+        for input in [
+            "",
+            "fortnightly",
+            "FREQ=MONTHLY;INTERVAL=0",
+            "FREQ=MONTHLY;INTERVAL=-1",
+            "INTERVAL=2",
+            "FREQ=MONTHLY;BYDAY=MO",
+        ] {
+            assert!(input.parse::<Cadence>().is_err(), "{:?} parsed", input);
+        }
+    }
+
+    #[test]
+    fn month_end_rule_does_not_drift() {
+        let monthly = cadence("monthly");
+        let start = at(2026, 1, 31);
+        let mut due = start;
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            due = monthly.next_after(start, due).unwrap();
+            seen.push(due.date_naive());
+        }
+        let expected: Vec<NaiveDate> = [(2, 28), (3, 31), (4, 30), (5, 31)]
+            .iter()
+            .map(|&(m, d)| NaiveDate::from_ymd_opt(2026, m, d).unwrap())
+            .collect();
+        assert_eq!(seen, expected);
+
+        let leap = monthly
+            .next_after(at(2028, 1, 31), at(2028, 1, 31))
+            .unwrap();
+        assert_eq!(
+            leap.date_naive(),
+            NaiveDate::from_ymd_opt(2028, 2, 29).unwrap()
+        );
+    }
+
+    #[test]
+    fn intervals_skip_whole_periods() {
+        let start = at(2026, 1, 5);
+        let fortnightly = cadence("FREQ=WEEKLY;INTERVAL=2");
+        assert_eq!(fortnightly.next_after(start, start), Some(at(2026, 1, 19)));
+        assert_eq!(
+            fortnightly.next_after(start, at(2026, 1, 19)),
+            Some(at(2026, 2, 2))
+        );
+
+        let quarterly = cadence("FREQ=MONTHLY;INTERVAL=3");
+        assert_eq!(quarterly.next_after(start, start), Some(at(2026, 4, 5)));
+        assert_eq!(
+            quarterly.next_after(start, at(2026, 5, 1)),
+            Some(at(2026, 7, 5))
+        );
+
+        let every_other_year = cadence("FREQ=YEARLY;INTERVAL=2");
+        assert_eq!(
+            every_other_year.next_after(at(2024, 2, 29), at(2024, 3, 1)),
+            Some(at(2026, 2, 28))
+        );
+    }
+
+    #[test]
+    fn next_after_jumps_ahead_from_an_old_start() {
+        let start = at(2000, 1, 31);
+        assert_eq!(
+            cadence("daily").next_after(start, at(2026, 6, 15)),
+            Some(at(2026, 6, 16))
+        );
+        assert_eq!(
+            cadence("weekly").next_after(start, at(2026, 6, 15)),
+            Some(at(2026, 6, 22))
+        );
+        assert_eq!(
+            cadence("FREQ=MONTHLY;INTERVAL=5").next_after(start, at(2026, 6, 15)),
+            Some(at(2026, 9, 30))
+        );
+        // Exactly on an occurrence: the next one is returned, not the same
+        assert_eq!(
+            cadence("monthly").next_after(start, at(2026, 3, 31)),
+            Some(at(2026, 4, 30))
+        );
+    }
+
+    #[test]
+    fn next_after_before_start_is_the_start() {
+        let start = at(2026, 3, 1);
+        assert_eq!(
+            cadence("monthly").next_after(start, at(2026, 1, 1)),
+            Some(start)
+        );
+    }
+}
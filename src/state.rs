@@ -1,4 +1,11 @@
-use crate::models::{Category, CategoryBalance};
+use crate::import::ImportRow;
+use crate::models::{
+    Account, Category, CategoryBalance, ImportProfile, Payee, RecurringKind, RecurringRule,
+    Reflection, SavingsGoal, SignConvention, Transfer, payee_name,
+};
+
+/// Payee suggestions shown under the Add Expense description
+const MAX_PAYEE_SUGGESTIONS: usize = 5;
 
 /// Input mode for the application
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -17,6 +24,89 @@ pub enum ActiveInput {
     Description,
     Category,
     CategoryLimit,
+    CategoryName,
+    Date,
+    Account,
+    AccountName,
+    TransferTarget,
+    ProfileName,
+    Source,
+    RuleKind,
+    Cadence,
+    Search,
+    /// Answer to one of the Kakeibo questions on the Reflection form
+    Answer(usize),
+    GoalName,
+    /// Amount of the next split line on the Add Expense form
+    LineAmount,
+    /// Statement file on the Import tab
+    ImportPath,
+    /// One of the text fields of the import profile form
+    Mapping(usize),
+    SignConvention,
+}
+
+/// What the Settings/Accounts/profile name prompt will do on Enter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamePrompt {
+    NewCategory,
+    RenameCategory(i64),
+    NewAccount,
+    NewProfile,
+}
+
+/// Action waiting for a y/n answer
+#[derive(Debug, Clone)]
+pub enum PendingConfirm {
+    DeleteTransaction(i64),
+    /// Expense that would overflow into other categories
+    OverflowExpense {
+        category_id: i64,
+        category_name: String,
+        amount: rust_decimal::Decimal,
+        description: Option<String>,
+        account_id: i64,
+        created_at: chrono::DateTime<chrono::Local>,
+        /// Dated before the current period, so Savings pays for it
+        closed_period: bool,
+        allocations: Vec<crate::models::Allocation>,
+    },
+    /// Split expense with at least one line that would overflow
+    OverflowSplit {
+        lines: Vec<(i64, rust_decimal::Decimal)>,
+        description: Option<String>,
+        account_id: i64,
+        created_at: chrono::DateTime<chrono::Local>,
+        closed_period: bool,
+        /// What each category covers, summed over the lines
+        allocations: Vec<crate::models::Allocation>,
+    },
+    /// Due occurrence of a rule that asks before posting
+    RecurringOccurrence(RecurringRule),
+    DeleteRecurringRule(i64),
+    DeleteGoal(i64),
+    DeleteImportProfile(i64),
+    /// Re-label the whole ledger in another currency (amounts are not converted)
+    ChangeCurrency(crate::currency::Currency),
+    /// Ready rows of the import preview, recorded oldest first
+    CommitImport {
+        account_id: i64,
+        count: usize,
+        total: rust_decimal::Decimal,
+        /// Rows dated before the current period, which Savings pays for
+        closed_period: usize,
+        /// What each category covers, summed over the rows
+        allocations: Vec<crate::models::Allocation>,
+    },
+}
+
+/// What the Reports tab lists below the stats block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportView {
+    #[default]
+    Transactions,
+    Tags,
+    Payees,
 }
 
 /// Active tab/page
@@ -27,6 +117,10 @@ pub enum ActiveTab {
     AddFunds,
     AddExpense,
     Reports,
+    Reflection,
+    Accounts,
+    Recurring,
+    Import,
     Settings,
 }
 
@@ -36,7 +130,11 @@ impl ActiveTab {
             Self::Dashboard => Self::AddFunds,
             Self::AddFunds => Self::AddExpense,
             Self::AddExpense => Self::Reports,
-            Self::Reports => Self::Settings,
+            Self::Reports => Self::Reflection,
+            Self::Reflection => Self::Accounts,
+            Self::Accounts => Self::Recurring,
+            Self::Recurring => Self::Import,
+            Self::Import => Self::Settings,
             Self::Settings => Self::Dashboard,
         }
     }
@@ -47,7 +145,11 @@ impl ActiveTab {
             Self::AddFunds => Self::Dashboard,
             Self::AddExpense => Self::AddFunds,
             Self::Reports => Self::AddExpense,
-            Self::Settings => Self::Reports,
+            Self::Reflection => Self::Reports,
+            Self::Accounts => Self::Reflection,
+            Self::Recurring => Self::Accounts,
+            Self::Import => Self::Recurring,
+            Self::Settings => Self::Import,
         }
     }
 
@@ -57,6 +159,10 @@ impl ActiveTab {
             Self::AddFunds => "Add Funds",
             Self::AddExpense => "Add Expense",
             Self::Reports => "Reports",
+            Self::Reflection => "Reflection",
+            Self::Accounts => "Accounts",
+            Self::Recurring => "Recurring",
+            Self::Import => "Import",
             Self::Settings => "Settings",
         }
     }
@@ -67,18 +173,39 @@ impl ActiveTab {
             Self::AddFunds,
             Self::AddExpense,
             Self::Reports,
+            Self::Reflection,
+            Self::Accounts,
+            Self::Recurring,
+            Self::Import,
             Self::Settings,
         ]
     }
 }
 
+/// Add Expense entry held back because it looks like an expense already recorded
+#[derive(Debug, Clone)]
+pub struct DuplicateWarning {
+    /// Lines of the recorded expense (several for a split receipt)
+    pub entry: Vec<crate::models::Transaction>,
+    /// Amount, description and date inputs the match was found for
+    pub inputs: [String; 3],
+}
+
 /// Shared application state
 #[derive(Debug, Default)]
 pub struct State {
     /// Cached category configurations
     pub categories: Vec<Category>,
+    /// Amount of the newest fund entry, which fixed budgets must fit into
+    pub latest_income: Option<rust_decimal::Decimal>,
     /// Cached current balances
     pub balances: Vec<CategoryBalance>,
+    /// Cached accounts with their balances
+    pub accounts: Vec<Account>,
+    /// Recent transfers between accounts
+    pub transfers: Vec<Transfer>,
+    /// Currency of the open ledger
+    pub currency: crate::currency::Currency,
     /// Current input mode
     pub input_mode: InputMode,
     /// Which input field is active
@@ -87,25 +214,123 @@ pub struct State {
     pub active_tab: ActiveTab,
     /// Selected category index (for forms)
     pub selected_category: usize,
+    /// Account used by the Add Funds/Add Expense forms (kept between entries)
+    pub form_account: usize,
+    /// Highlighted account on the Accounts tab (source of a transfer)
+    pub selected_account: usize,
+    /// Destination account index while a transfer form is open
+    pub transfer_target: usize,
+    /// Whether the Accounts tab transfer form is open
+    pub transferring: bool,
     /// Input buffer for amount
     pub amount_input: String,
-    /// Input buffer for description
+    /// Input buffer for description (also the note on fund entries and transfers)
     pub description_input: String,
+    /// Input buffer for the income source on Add Funds
+    pub source_input: String,
+    /// Input buffer for date/time (entry date on Add Funds/Add Expense, empty = now)
+    pub date_input: String,
+    /// Input buffer for category names
+    pub name_input: String,
+    /// Active Settings name prompt, if any
+    pub name_prompt: Option<NamePrompt>,
+    /// Highlighted row of the overflow target picker, when open
+    pub overflow_picker: Option<usize>,
+    /// Scheduled expenses and incomes
+    pub recurring_rules: Vec<RecurringRule>,
+    /// Highlighted rule on the Recurring tab
+    pub selected_rule: usize,
+    /// Whether the new recurring rule form is open
+    pub creating_rule: bool,
+    /// Kind of the rule being created
+    pub rule_kind: RecurringKind,
+    /// Input buffer for the rule cadence
+    pub cadence_input: String,
+    /// Rules that failed to post this session; retried on next start
+    pub recurring_failed: Vec<i64>,
+    /// Tags in use, most used first, for the tag picker
+    pub known_tags: Vec<String>,
+    /// Highlighted row of the tag picker, when open
+    pub tag_picker: Option<usize>,
+    /// Payees with expenses, most used first, for Description autocomplete
+    pub payees: Vec<Payee>,
+    /// Highlighted payee suggestion, once the user moves into the list
+    pub payee_pick: Option<usize>,
+    /// Whether the Add Expense form splits the amount across categories
+    pub splitting: bool,
+    /// Category and amount of each split line added so far
+    pub split_lines: Vec<(i64, rust_decimal::Decimal)>,
+    pub line_amount_input: String,
+    /// Recorded expense the Add Expense entry looks like, held back on submit
+    pub possible_duplicate: Option<DuplicateWarning>,
+    /// Statement import profiles, by name
+    pub import_profiles: Vec<ImportProfile>,
+    /// Highlighted profile on the Import tab
+    pub selected_import_profile: usize,
+    /// Import profile being edited (0 while creating one)
+    pub editing_import_profile: Option<i64>,
+    /// Input buffers of the import profile form: name, delimiter, skip lines, date column,
+    /// date format, amount column, credit/marker column, description column, decimal separator
+    pub mapping_inputs: [String; 9],
+    /// Sign convention picked on the import profile form
+    pub sign_convention: SignConvention,
+    /// Statement file to import; kept after loading so it can be reloaded
+    pub import_path_input: String,
+    /// Rows of the loaded statement, in file order
+    pub import_rows: Vec<ImportRow>,
+    /// Selection/scroll state of the import preview
+    pub import_table: ratatui::widgets::TableState,
+    /// Account the previewed statement is paid from; the CSV profile's when it names one
+    pub import_account: usize,
+    /// Kakeibo reflections, newest period first
+    pub reflections: Vec<Reflection>,
+    /// Period highlighted on the Reflection tab (index into `reflections`)
+    pub selected_reflection: usize,
+    /// Whether the Reflection form is open for the selected period
+    pub editing_reflection: bool,
+    /// Input buffers for the answers to the Kakeibo questions
+    pub answer_inputs: [String; 4],
+    /// Savings goals shown on the Dashboard
+    pub savings_goals: Vec<SavingsGoal>,
+    /// Highlighted goal on the Dashboard
+    pub selected_goal: usize,
+    /// Whether the new savings goal form is open
+    pub creating_goal: bool,
+    /// Whether the withdraw-for-goal form is open for the selected goal
+    pub funding_goal: bool,
+    /// Typical budget period length, for goal projections
+    pub period_length: chrono::TimeDelta,
+    /// Ledger files offered by the profile picker
+    pub profiles: Vec<std::path::PathBuf>,
+    /// Highlighted row of the profile picker, when open
+    pub profile_picker: Option<usize>,
     /// Status message to display
     pub status_message: Option<String>,
     /// Whether to show help overlay
     pub show_help: bool,
+    /// Action awaiting confirmation in a modal
+    pub pending_confirm: Option<PendingConfirm>,
 
     // Reporting state
     pub report_date_range: crate::models::DateRange,
     pub transactions: Vec<crate::models::Transaction>,
     pub summary_stats: Option<crate::models::SummaryStats>,
+    pub report_view: ReportView,
+    /// Reports description search; kept until cleared with Esc
+    pub search_input: String,
+    /// Selection/scroll state of the Reports transaction list
+    pub transaction_list: ratatui::widgets::ListState,
+    /// Transaction being edited from the Reports tab
+    pub editing_transaction: Option<i64>,
+    /// Expense a refund is being recorded for from the Reports tab
+    pub refunding: Option<i64>,
 }
 
 impl State {
     pub fn new() -> Self {
         Self {
             report_date_range: crate::models::DateRange::Month,
+            transaction_list: ratatui::widgets::ListState::default().with_selected(Some(0)),
             ..Default::default()
         }
     }
@@ -121,8 +346,285 @@ impl State {
     pub fn clear_inputs(&mut self) {
         self.amount_input.clear();
         self.description_input.clear();
+        self.source_input.clear();
+        self.date_input.clear();
+        self.name_input.clear();
+        self.name_prompt = None;
+        self.overflow_picker = None;
+        self.profile_picker = None;
+        self.tag_picker = None;
+        self.payee_pick = None;
+        self.splitting = false;
+        self.split_lines.clear();
+        self.line_amount_input.clear();
+        self.possible_duplicate = None;
+        self.editing_transaction = None;
+        self.refunding = None;
+        self.transferring = false;
+        self.transfer_target = 0;
+        self.creating_rule = false;
+        self.rule_kind = RecurringKind::default();
+        self.cadence_input.clear();
+        self.editing_reflection = false;
+        self.creating_goal = false;
+        self.funding_goal = false;
+        self.answer_inputs = Default::default();
+        self.editing_import_profile = None;
+        self.mapping_inputs = Default::default();
+        self.sign_convention = SignConvention::default();
         self.selected_category = 0;
         self.active_input = ActiveInput::None;
         self.input_mode = InputMode::Normal;
     }
+
+    /// Fields Tab cycles through on the current form, in order
+    pub fn form_fields(&self) -> &'static [ActiveInput] {
+        match self.active_tab {
+            ActiveTab::AddFunds => &[
+                ActiveInput::Amount,
+                ActiveInput::Source,
+                ActiveInput::Description,
+                ActiveInput::Account,
+                ActiveInput::Date,
+            ],
+            ActiveTab::AddExpense if self.splitting => &[
+                ActiveInput::Amount,
+                ActiveInput::Description,
+                ActiveInput::Category,
+                ActiveInput::LineAmount,
+                ActiveInput::Account,
+                ActiveInput::Date,
+            ],
+            ActiveTab::AddExpense => &[
+                ActiveInput::Amount,
+                ActiveInput::Description,
+                ActiveInput::Category,
+                ActiveInput::Account,
+                ActiveInput::Date,
+            ],
+            ActiveTab::Reports if self.refunding.is_some() => &[
+                ActiveInput::Amount,
+                ActiveInput::Description,
+                ActiveInput::Date,
+            ],
+            ActiveTab::Reports if self.editing_transaction.is_some() => &[
+                ActiveInput::Amount,
+                ActiveInput::Description,
+                ActiveInput::Category,
+                ActiveInput::Date,
+            ],
+            ActiveTab::Recurring if self.creating_rule => match self.rule_kind {
+                RecurringKind::Expense => &[
+                    ActiveInput::RuleKind,
+                    ActiveInput::Amount,
+                    ActiveInput::Description,
+                    ActiveInput::Category,
+                    ActiveInput::Account,
+                    ActiveInput::Cadence,
+                    ActiveInput::Date,
+                ],
+                RecurringKind::Income => &[
+                    ActiveInput::RuleKind,
+                    ActiveInput::Amount,
+                    ActiveInput::Description,
+                    ActiveInput::Account,
+                    ActiveInput::Cadence,
+                    ActiveInput::Date,
+                ],
+            },
+            ActiveTab::Reflection if self.editing_reflection => &[
+                ActiveInput::Amount,
+                ActiveInput::Answer(0),
+                ActiveInput::Answer(1),
+                ActiveInput::Answer(2),
+                ActiveInput::Answer(3),
+            ],
+            ActiveTab::Dashboard if self.creating_goal => &[
+                ActiveInput::GoalName,
+                ActiveInput::Amount,
+                ActiveInput::Date,
+            ],
+            ActiveTab::Dashboard if self.funding_goal => {
+                &[ActiveInput::Amount, ActiveInput::Description]
+            }
+            ActiveTab::Import if self.editing_import_profile.is_some() => &[
+                ActiveInput::Mapping(0),
+                ActiveInput::Mapping(1),
+                ActiveInput::Mapping(2),
+                ActiveInput::Mapping(3),
+                ActiveInput::Mapping(4),
+                ActiveInput::SignConvention,
+                ActiveInput::Mapping(5),
+                ActiveInput::Mapping(6),
+                ActiveInput::Mapping(7),
+                ActiveInput::Mapping(8),
+                ActiveInput::Account,
+                ActiveInput::Category,
+            ],
+            ActiveTab::Import => &[ActiveInput::ImportPath],
+            ActiveTab::Accounts if self.transferring => &[
+                ActiveInput::Amount,
+                ActiveInput::TransferTarget,
+                ActiveInput::Description,
+            ],
+            _ => &[],
+        }
+    }
+
+    /// Whether the selected Settings category budgets a fixed amount rather than a percentage
+    pub fn selected_is_fixed(&self) -> bool {
+        self.categories
+            .get(self.selected_category)
+            .is_some_and(|c| c.fixed_amount.is_some())
+    }
+
+    /// Account picked in the Add Funds/Add Expense forms
+    pub fn form_account(&self) -> Option<&Account> {
+        self.accounts.get(self.form_account)
+    }
+
+    /// Account the previewed statement rows will be recorded against
+    pub fn import_account(&self) -> Option<&Account> {
+        self.accounts.get(self.import_account)
+    }
+
+    /// When the Add Funds/Add Expense entry (or the expense being edited) happened: now when
+    /// the date field is empty, otherwise `models::parse_entry_date`. `None` if invalid.
+    pub fn entry_date(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        if self.date_input.trim().is_empty() {
+            return Some(chrono::Local::now());
+        }
+        crate::models::parse_entry_date(&self.date_input)
+    }
+
+    /// Categories an expense can be filed under (Savings is only reachable through overflow,
+    /// archived ones are hidden)
+    pub fn expense_categories(&self) -> Vec<&Category> {
+        self.categories
+            .iter()
+            .filter(|c| !c.is_savings && !c.archived)
+            .collect()
+    }
+
+    /// Amount, description and date inputs of the Add Expense form, as a duplicate warning
+    /// remembers them
+    pub fn duplicate_inputs(&self) -> [String; 3] {
+        [
+            self.amount_input.clone(),
+            self.description_input.clone(),
+            self.date_input.clone(),
+        ]
+    }
+
+    /// Recorded expense the Add Expense entry looks like, while the inputs it was found for
+    /// are unchanged
+    pub fn duplicate_warning(&self) -> Option<&[crate::models::Transaction]> {
+        self.possible_duplicate
+            .as_ref()
+            .filter(|warning| warning.inputs == self.duplicate_inputs())
+            .map(|warning| warning.entry.as_slice())
+    }
+
+    /// Payees matching the Add Expense description being typed, most used first. Empty once
+    /// the description spells a payee exactly.
+    pub fn payee_suggestions(&self) -> Vec<&Payee> {
+        if self.active_tab != ActiveTab::AddExpense
+            || self.input_mode != InputMode::Insert
+            || self.active_input != ActiveInput::Description
+            || self.tag_picker.is_some()
+        {
+            return Vec::new();
+        }
+        let Some(typed) = payee_name(&self.description_input) else {
+            return Vec::new();
+        };
+        if self.payees.iter().any(|p| p.name == typed) {
+            return Vec::new();
+        }
+        let typed = typed.to_lowercase();
+        self.payees
+            .iter()
+            .filter(|p| p.name.to_lowercase().contains(&typed))
+            .take(MAX_PAYEE_SUGGESTIONS)
+            .collect()
+    }
+
+    /// Overflow targets offered for the selected Settings category (`None` = end of chain)
+    pub fn overflow_candidates(&self) -> Vec<Option<&Category>> {
+        let selected_id = self.categories.get(self.selected_category).map(|c| c.id);
+        std::iter::once(None)
+            .chain(
+                self.categories
+                    .iter()
+                    .filter(|c| Some(c.id) != selected_id && !c.archived)
+                    .map(Some),
+            )
+            .collect()
+    }
+
+    /// Import profile highlighted on the Import tab
+    pub fn selected_import_profile(&self) -> Option<&ImportProfile> {
+        self.import_profiles.get(self.selected_import_profile)
+    }
+
+    /// Earliest occurrence due for posting now whose rule hasn't already failed this session,
+    /// so catch-up entries land in date order. Rules that ask first are skipped while the
+    /// user is busy, so their modal waits for a later tick.
+    pub fn due_rule(&self, now: chrono::DateTime<chrono::Local>) -> Option<&RecurringRule> {
+        let can_ask = self.is_idle();
+        self.recurring_rules
+            .iter()
+            .filter(|r| {
+                r.is_due(now)
+                    && !self.recurring_failed.contains(&r.id)
+                    && (can_ask || !r.requires_confirmation)
+            })
+            .min_by_key(|r| r.next_due)
+    }
+
+    /// Whether the user is in Normal mode with no form, picker or overlay open, so a modal
+    /// can't swallow keys meant for something else
+    pub fn is_idle(&self) -> bool {
+        self.input_mode == InputMode::Normal
+            && self.pending_confirm.is_none()
+            && !self.show_help
+            && self.name_prompt.is_none()
+            && self.overflow_picker.is_none()
+            && self.tag_picker.is_none()
+            && self.profile_picker.is_none()
+            && self.possible_duplicate.is_none()
+            && self.editing_transaction.is_none()
+            && self.refunding.is_none()
+            && self.editing_import_profile.is_none()
+            && !self.transferring
+            && !self.creating_rule
+            && !self.creating_goal
+            && !self.funding_goal
+            && !self.editing_reflection
+            && !self.splitting
+    }
+
+    /// Terms of the active Reports search (empty when not searching)
+    pub fn search_terms(&self) -> Vec<String> {
+        crate::models::search_terms(&self.search_input)
+    }
+
+    /// Rows of the Reports list: the lines of a split expense collapse into one entry
+    pub fn report_entries(&self) -> Vec<&[crate::models::Transaction]> {
+        self.transactions
+            .chunk_by(|a, b| a.split_id.is_some() && a.split_id == b.split_id)
+            .collect()
+    }
+
+    /// Currently highlighted entry in the Reports list
+    pub fn selected_entry(&self) -> Option<&[crate::models::Transaction]> {
+        self.transaction_list
+            .selected()
+            .and_then(|i| self.report_entries().get(i).copied())
+    }
+
+    /// Currently highlighted transaction in the Reports list (first line of a split)
+    pub fn selected_transaction(&self) -> Option<&crate::models::Transaction> {
+        self.selected_entry().and_then(|entry| entry.first())
+    }
 }